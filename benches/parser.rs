@@ -0,0 +1,132 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::fmt::Write;
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+
+use chemgdb::parser::{parse_xyz_borrowed, parse_xyz_frames, parse_xyz_str, FrameReader, ParseOptions};
+
+/// System allocator that counts allocations, to compare the parsers
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+  unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+    ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    unsafe { System.alloc(layout) }
+  }
+
+  unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+    unsafe { System.dealloc(ptr, layout) }
+  }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Number of allocations made by `f`
+fn count_allocations<T>(f: impl FnOnce() -> T) -> usize {
+  let before = ALLOCATIONS.load(Ordering::Relaxed);
+  black_box(f());
+  ALLOCATIONS.load(Ordering::Relaxed) - before
+}
+
+/// Build an XYZ file with `n` atoms on a cubic grid
+fn generate_xyz(n: usize) -> String {
+  let side = (n as f64).cbrt().ceil() as usize;
+  let elements = ["C", "H", "O", "N"];
+
+  let mut content = String::with_capacity(n * 48);
+  writeln!(content, "{}", n).unwrap();
+  writeln!(content, "synthetic benchmark molecule").unwrap();
+  for i in 0..n {
+    let x = (i % side) as f64 * 1.5;
+    let y = ((i / side) % side) as f64 * 1.5;
+    let z = (i / (side * side)) as f64 * 1.5;
+    writeln!(content, "{} {:.6} {:.6} {:.6}", elements[i % elements.len()], x, y, z).unwrap();
+  }
+  content
+}
+
+/// Build a trajectory of `frames` frames of `n` atoms, each frame shifting
+/// the grid a little so no two frames are identical
+fn generate_trajectory(n: usize, frames: usize) -> String {
+  let frame = generate_xyz(n);
+  let mut content = String::with_capacity(frame.len() * frames);
+  for f in 0..frames {
+    let shift = f as f64 * 0.001;
+    let mut lines = frame.lines();
+    writeln!(content, "{}", lines.next().unwrap()).unwrap();
+    writeln!(content, "frame={}", f).unwrap();
+    for line in lines.skip(1) {
+      let mut fields = line.split_whitespace();
+      let element = fields.next().unwrap();
+      let [x, y, z] = [(); 3].map(|_| fields.next().unwrap().parse::<f64>().unwrap() + shift);
+      writeln!(content, "{} {:.6} {:.6} {:.6}", element, x, y, z).unwrap();
+    }
+  }
+  content
+}
+
+fn bench_parse_xyz_str(c: &mut Criterion) {
+  let mut group = c.benchmark_group("parse_xyz_str");
+
+  for (name, n) in [("small", 6), ("medium", 10_000), ("large", 1_000_000)] {
+    let content = generate_xyz(n);
+    group.throughput(Throughput::Bytes(content.len() as u64));
+    if n >= 1_000_000 {
+      group.sample_size(10);
+    }
+    group.bench_function(name, |b| b.iter(|| parse_xyz_str(black_box(&content)).unwrap()));
+  }
+
+  group.finish();
+}
+
+fn bench_parse_xyz_borrowed(c: &mut Criterion) {
+  let mut group = c.benchmark_group("parse_xyz_borrowed");
+
+  for (name, n) in [("small", 6), ("medium", 10_000), ("large", 1_000_000)] {
+    let content = generate_xyz(n);
+    println!(
+      "{}: {} allocations owned, {} borrowed",
+      name,
+      count_allocations(|| parse_xyz_str(&content).unwrap()),
+      count_allocations(|| parse_xyz_borrowed(&content).unwrap()),
+    );
+
+    group.throughput(Throughput::Bytes(content.len() as u64));
+    if n >= 1_000_000 {
+      group.sample_size(10);
+    }
+    group.bench_function(name, |b| b.iter(|| parse_xyz_borrowed(black_box(&content)).unwrap()));
+  }
+
+  group.finish();
+}
+
+fn bench_parse_trajectory(c: &mut Criterion) {
+  let mut group = c.benchmark_group("parse_trajectory");
+  group.sample_size(10);
+
+  let content = generate_trajectory(1_000, 1_000);
+  let options = ParseOptions::default();
+  group.throughput(Throughput::Bytes(content.len() as u64));
+  group.bench_function("parse_xyz_frames", |b| {
+    b.iter(|| parse_xyz_frames(black_box(content.as_bytes()), &options).unwrap())
+  });
+  group.bench_function("frame_reader", |b| {
+    b.iter(|| {
+      FrameReader::new(black_box(content.as_bytes()), options.clone())
+        .map(Result::unwrap)
+        .count()
+    })
+  });
+
+  group.finish();
+}
+
+criterion_group!(benches, bench_parse_xyz_str, bench_parse_xyz_borrowed, bench_parse_trajectory);
+criterion_main!(benches);
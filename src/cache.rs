@@ -0,0 +1,149 @@
+use crate::parser::Molecule;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+
+/// Bumped whenever the cached layout changes so older sidecars are ignored
+const CACHE_VERSION: u32 = 6;
+
+/// Cache error types
+#[derive(Debug, Clone, PartialEq)]
+pub enum CacheError {
+  Io(String),
+  Encode(String),
+}
+
+impl fmt::Display for CacheError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      CacheError::Io(msg) => write!(f, "could not write cache file: {}", msg),
+      CacheError::Encode(msg) => write!(f, "could not encode cache: {}", msg),
+    }
+  }
+}
+
+impl Error for CacheError {}
+
+/// Sidecar contents as written
+#[derive(Serialize)]
+struct CacheRef<'a> {
+  version: u32,
+  source_modified: Duration,
+  source_len: u64,
+  frames: &'a [Molecule],
+}
+
+/// Sidecar contents as read back; encodes the same as `CacheRef`
+#[derive(Deserialize)]
+struct CacheFile {
+  version: u32,
+  source_modified: Duration,
+  source_len: u64,
+  frames: Vec<Molecule>,
+}
+
+/// Sidecar path for `source`: `traj.xyz` is cached in `traj.xyz.cache`
+pub fn cache_path(source: &Path) -> PathBuf {
+  let mut name = source.as_os_str().to_owned();
+  name.push(".cache");
+  PathBuf::from(name)
+}
+
+/// Modification time and length identifying the current version of `source`
+fn source_stamp(source: &Path) -> std::io::Result<(Duration, u64)> {
+  let metadata = fs::metadata(source)?;
+  let modified = metadata.modified()?.duration_since(UNIX_EPOCH).unwrap_or_default();
+  Ok((modified, metadata.len()))
+}
+
+/// Frames cached for `source`, or `None` if there is no usable sidecar
+///
+/// The sidecar is used only if it is newer than the source and records the
+/// source's current modification time and size. Missing, stale, or corrupt
+/// sidecars all give `None` so the caller can fall back to parsing.
+pub fn load_cache(source: &Path) -> Option<Vec<Molecule>> {
+  let (modified, len) = source_stamp(source).ok()?;
+  let path = cache_path(source);
+  if fs::metadata(&path).ok()?.modified().ok()? < UNIX_EPOCH + modified {
+    return None;
+  }
+
+  let mut reader = BufReader::new(File::open(&path).ok()?);
+  let cached: CacheFile = bincode::serde::decode_from_std_read(&mut reader, bincode::config::standard()).ok()?;
+
+  (cached.version == CACHE_VERSION && cached.source_modified == modified && cached.source_len == len)
+    .then_some(cached.frames)
+}
+
+/// Write the frames parsed from `source` to its sidecar
+pub fn write_cache(source: &Path, frames: &[Molecule]) -> Result<(), CacheError> {
+  let (source_modified, source_len) = source_stamp(source).map_err(|e| CacheError::Io(e.to_string()))?;
+  let file = File::create(cache_path(source)).map_err(|e| CacheError::Io(e.to_string()))?;
+  let mut writer = BufWriter::new(file);
+
+  let cache = CacheRef {
+    version: CACHE_VERSION,
+    source_modified,
+    source_len,
+    frames,
+  };
+  bincode::serde::encode_into_std_write(&cache, &mut writer, bincode::config::standard())
+    .map_err(|e| CacheError::Encode(e.to_string()))?;
+  writer.flush().map_err(|e| CacheError::Io(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parser::parse_xyz_str;
+
+  const WATER: &str = "3\nwater\nO 0.0 0.0 0.117\nH 0.0 0.757 -0.467\nH 0.0 -0.757 -0.467\n";
+
+  /// Fresh source file in a per-test scratch directory
+  fn source_file(name: &str, content: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("chemgdb-cache-{}-{}", std::process::id(), name));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("input.xyz");
+    fs::write(&path, content).unwrap();
+    let _ = fs::remove_file(cache_path(&path));
+    path
+  }
+
+  #[test]
+  fn test_cache_path_appends_extension() {
+    assert_eq!(cache_path(Path::new("data/traj.xyz")), PathBuf::from("data/traj.xyz.cache"));
+  }
+
+  #[test]
+  fn test_round_trip() {
+    let source = source_file("round-trip", WATER);
+    let mut frames = vec![parse_xyz_str(WATER).unwrap()];
+    frames[0].atoms[1].label = Some("H1".to_string());
+    frames[0].atoms[2].isotope = Some(2);
+
+    assert_eq!(load_cache(&source), None);
+    write_cache(&source, &frames).unwrap();
+    assert_eq!(load_cache(&source), Some(frames));
+  }
+
+  #[test]
+  fn test_ignore_cache_after_source_changes() {
+    let source = source_file("stale", WATER);
+    write_cache(&source, &[parse_xyz_str(WATER).unwrap()]).unwrap();
+
+    fs::write(&source, "1\n\nHe 0 0 0\n").unwrap();
+    assert_eq!(load_cache(&source), None);
+  }
+
+  #[test]
+  fn test_ignore_corrupt_cache() {
+    let source = source_file("corrupt", WATER);
+    fs::write(cache_path(&source), b"not a cache").unwrap();
+
+    assert_eq!(load_cache(&source), None);
+  }
+}
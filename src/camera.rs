@@ -0,0 +1,182 @@
+use bevy::prelude::*;
+
+/// Closest the camera may zoom to its target
+pub const MIN_DISTANCE: f32 = 2.0;
+
+/// Farthest the camera may zoom from its target
+pub const MAX_DISTANCE: f32 = 100.0;
+
+/// Zoom distance at which the continuous pan speed applies unscaled
+pub const PAN_REFERENCE_DISTANCE: f32 = 15.0;
+
+/// Camera position `distance` from `target` along the view axis of `rotation`
+///
+/// The camera looks down its local -Z axis, so it sits on the +Z side of
+/// the target.
+pub fn calculate_camera_position(rotation: Quat, target: Vec3, distance: f32) -> Vec3 {
+  target + rotation * Vec3::Z * distance
+}
+
+/// Rotation after dragging by `delta` pixels, `sensitivity` radians per pixel
+///
+/// Horizontal movement yaws about the camera's local Y axis and vertical
+/// movement pitches about its local X axis.
+pub fn drag_rotation(rotation: Quat, delta: Vec2, sensitivity: f32) -> Quat {
+  let up = rotation * Vec3::Y;
+  let yaw = Quat::from_axis_angle(up, -delta.x * sensitivity);
+  let right = rotation * Vec3::X;
+  let pitch = Quat::from_axis_angle(right, -delta.y * sensitivity);
+
+  (pitch * yaw * rotation).normalize()
+}
+
+/// Rotation after turning by `angles` radians about the camera's local
+/// x, y, and z axes
+pub fn step_rotation(rotation: Quat, angles: Vec3) -> Quat {
+  let right = rotation * Vec3::X;
+  let up = rotation * Vec3::Y;
+  let forward = rotation * Vec3::Z;
+  let step = Quat::from_axis_angle(right, angles.x)
+    * Quat::from_axis_angle(up, angles.y)
+    * Quat::from_axis_angle(forward, angles.z);
+
+  (step * rotation).normalize()
+}
+
+/// Rotation after turning the view by `angle` radians about the world
+/// vertical axis, as on a turntable
+pub fn turntable_rotation(rotation: Quat, angle: f32) -> Quat {
+  (Quat::from_rotation_y(angle) * rotation).normalize()
+}
+
+/// Target offset for panning `amount` along the screen's x and y directions
+pub fn pan_offset(rotation: Quat, amount: Vec2) -> Vec3 {
+  rotation * Vec3::X * amount.x + rotation * Vec3::Y * amount.y
+}
+
+/// Continuous pan rate at the given zoom distance, so the molecule
+/// crosses the screen equally fast whether zoomed in or out
+pub fn pan_rate(speed: f32, distance: f32) -> f32 {
+  speed * distance / PAN_REFERENCE_DISTANCE
+}
+
+/// Distance after scrolling by `scroll` lines, kept within
+/// [`MIN_DISTANCE`, `MAX_DISTANCE`]
+pub fn zoom_distance(distance: f32, scroll: f32, speed: f32) -> f32 {
+  (distance - scroll * speed).clamp(MIN_DISTANCE, MAX_DISTANCE)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::f32::consts::{FRAC_PI_2, TAU};
+
+  /// Whether two rotations turn vectors the same way (q and -q are equal)
+  fn same_rotation(a: Quat, b: Quat) -> bool {
+    a.dot(b).abs() > 1.0 - 1e-5
+  }
+
+  // ==================== Camera Position ====================
+
+  #[test]
+  fn test_camera_position_at_distance_along_view_axis() {
+    let rotation = Quat::from_euler(EulerRot::XYZ, 0.3, -1.2, 0.5);
+    let target = Vec3::new(1.0, 2.0, 3.0);
+    let position = calculate_camera_position(rotation, target, 15.0);
+
+    assert!((position.distance(target) - 15.0).abs() < 1e-4);
+    // The camera looks down its -Z axis, straight at the target
+    let view_direction = rotation * Vec3::NEG_Z;
+    assert!(view_direction.dot((target - position).normalize()) > 1.0 - 1e-5);
+  }
+
+  #[test]
+  fn test_camera_position_without_rotation() {
+    let position = calculate_camera_position(Quat::IDENTITY, Vec3::ZERO, 10.0);
+    assert_eq!(position, Vec3::new(0.0, 0.0, 10.0));
+  }
+
+  // ==================== Rotation ====================
+
+  #[test]
+  fn test_full_turn_of_yaw_returns_to_start() {
+    let start = Quat::from_rotation_x(-0.3);
+    let sensitivity = 0.005;
+    let steps = 100;
+    let delta = Vec2::new(TAU / sensitivity / steps as f32, 0.0);
+
+    let mut rotation = start;
+    for _ in 0..steps {
+      rotation = drag_rotation(rotation, delta, sensitivity);
+    }
+
+    assert!(same_rotation(rotation, start), "{:?} != {:?}", rotation, start);
+  }
+
+  #[test]
+  fn test_drag_keeps_rotation_normalized() {
+    let mut rotation = Quat::IDENTITY;
+    for i in 0..1000 {
+      rotation = drag_rotation(rotation, Vec2::new(i as f32, -3.0), 0.005);
+    }
+
+    assert!(rotation.is_normalized());
+  }
+
+  #[test]
+  fn test_drag_and_reverse_drag_cancel() {
+    let start = Quat::from_euler(EulerRot::XYZ, 0.4, 0.2, -0.1);
+    let there = drag_rotation(start, Vec2::new(40.0, 0.0), 0.005);
+
+    assert!(!same_rotation(there, start));
+    assert!(same_rotation(drag_rotation(there, Vec2::new(-40.0, 0.0), 0.005), start));
+  }
+
+  #[test]
+  fn test_step_rotation_about_local_axes() {
+    let start = Quat::from_rotation_y(0.7);
+    let turned = step_rotation(start, Vec3::new(0.0, 0.0, FRAC_PI_2));
+
+    // Rolling about the view axis leaves the camera where it was
+    assert!((turned * Vec3::Z).abs_diff_eq(start * Vec3::Z, 1e-5));
+    assert!(same_rotation(step_rotation(turned, Vec3::new(0.0, 0.0, -FRAC_PI_2)), start));
+  }
+
+  #[test]
+  fn test_turntable_keeps_camera_height() {
+    let start = Quat::from_rotation_x(-0.3);
+    let height = calculate_camera_position(start, Vec3::ZERO, 15.0).y;
+
+    let mut rotation = start;
+    for _ in 0..7 {
+      rotation = turntable_rotation(rotation, TAU / 7.0);
+      assert!((calculate_camera_position(rotation, Vec3::ZERO, 15.0).y - height).abs() < 1e-4);
+    }
+    assert!(same_rotation(rotation, start));
+  }
+
+  // ==================== Pan and Zoom ====================
+
+  #[test]
+  fn test_pan_stays_in_view_plane() {
+    let rotation = Quat::from_euler(EulerRot::XYZ, 0.3, -1.2, 0.5);
+    let offset = pan_offset(rotation, Vec2::new(2.0, -1.0));
+
+    assert!(offset.dot(rotation * Vec3::Z).abs() < 1e-5);
+    assert!((offset.length() - 5.0_f32.sqrt()).abs() < 1e-5);
+  }
+
+  #[test]
+  fn test_pan_rate_scales_with_distance() {
+    assert_eq!(pan_rate(5.0, PAN_REFERENCE_DISTANCE), 5.0);
+    assert_eq!(pan_rate(5.0, 2.0 * PAN_REFERENCE_DISTANCE), 10.0);
+    assert!(pan_rate(5.0, MIN_DISTANCE) < pan_rate(5.0, MAX_DISTANCE));
+  }
+
+  #[test]
+  fn test_zoom_is_clamped() {
+    assert_eq!(zoom_distance(15.0, 1.0, 1.0), 14.0);
+    assert_eq!(zoom_distance(3.0, 10.0, 1.0), MIN_DISTANCE);
+    assert_eq!(zoom_distance(90.0, -50.0, 1.0), MAX_DISTANCE);
+  }
+}
@@ -0,0 +1,1474 @@
+use crate::parser::{Atom, Molecule};
+use crate::periodic_table::{atomic_mass, isotope_mass};
+use crate::spatial::{distance_squared, SpatialGrid};
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fmt;
+use std::ops::Range;
+
+/// Tolerance used by `--dedup`, in Angstrom; far below any real bond length
+pub const DEFAULT_DEDUP_TOLERANCE: f64 = 0.1;
+
+/// Atom count above which `nearest_neighbors` searches a spatial grid
+const NEIGHBOR_GRID_THRESHOLD: usize = 64;
+
+/// Largest molecule `match_atoms` matches exhaustively
+const EXACT_MATCH_LIMIT: usize = 8;
+
+/// Rounds of neighbor refinement behind the connectivity labels of `match_atoms`
+const LABEL_ROUNDS: usize = 3;
+
+/// One elementary charge times one Angstrom, in Debye
+const DEBYE_PER_E_ANGSTROM: f64 = 4.803_204_7;
+
+/// Errors from comparing two structures
+#[derive(Debug, Clone, PartialEq)]
+pub enum GeometryError {
+  AtomCountMismatch { expected: usize, actual: usize },
+  /// The structures have different numbers of atoms of this element
+  ElementMismatch(String),
+}
+
+impl fmt::Display for GeometryError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      GeometryError::AtomCountMismatch { expected, actual } => {
+        write!(f, "atom count mismatch: expected {} atoms, found {}", expected, actual)
+      }
+      GeometryError::ElementMismatch(element) => {
+        write!(f, "element mismatch: the structures have different numbers of {} atoms", element)
+      }
+    }
+  }
+}
+
+impl Error for GeometryError {}
+
+/// Correspondence between the atoms of two structures, from `match_atoms`
+#[derive(Debug, Clone, PartialEq)]
+pub struct AtomMatch {
+  /// `mapping[i]` is the atom of the other structure matched to atom `i`
+  pub mapping: Vec<usize>,
+  /// Root-mean-square distance between matched atoms, in Angstrom
+  pub rmsd: f64,
+}
+
+/// One bohr (atomic unit of length), in Angstrom
+const ANGSTROM_PER_BOHR: f64 = 0.529_177_210_903;
+
+/// Length unit of input coordinates
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LengthUnit {
+  Angstrom,
+  /// Nanometers, as written by GROMACS
+  Nanometer,
+  Bohr,
+}
+
+impl LengthUnit {
+  /// Parse `angstrom`, `nm`, or `bohr`, ignoring case
+  pub fn from_name(name: &str) -> Option<LengthUnit> {
+    match name.to_lowercase().as_str() {
+      "angstrom" | "a" => Some(LengthUnit::Angstrom),
+      "nm" | "nanometer" => Some(LengthUnit::Nanometer),
+      "bohr" => Some(LengthUnit::Bohr),
+      _ => None,
+    }
+  }
+
+  /// Angstrom in one of this unit
+  pub fn to_angstrom(self) -> f64 {
+    match self {
+      LengthUnit::Angstrom => 1.0,
+      LengthUnit::Nanometer => 10.0,
+      LengthUnit::Bohr => ANGSTROM_PER_BOHR,
+    }
+  }
+}
+
+impl fmt::Display for LengthUnit {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      LengthUnit::Angstrom => write!(f, "Angstrom"),
+      LengthUnit::Nanometer => write!(f, "nm"),
+      LengthUnit::Bohr => write!(f, "bohr"),
+    }
+  }
+}
+
+/// Periodic cell as three lattice vectors `[a, b, c]`, in Angstrom
+pub type Lattice = [[f64; 3]; 3];
+
+/// Point a rotation is applied about
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RotationCenter {
+  /// Geometric center (unweighted mean) of the atom positions
+  Centroid,
+  /// Coordinate origin
+  Origin,
+}
+
+/// Cartesian coordinate axis
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Axis {
+  X,
+  Y,
+  Z,
+}
+
+impl Axis {
+  /// Parse `x`, `y`, or `z`, ignoring case
+  pub fn from_name(name: &str) -> Option<Axis> {
+    match name.to_lowercase().as_str() {
+      "x" => Some(Axis::X),
+      "y" => Some(Axis::Y),
+      "z" => Some(Axis::Z),
+      _ => None,
+    }
+  }
+}
+
+impl fmt::Display for Axis {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Axis::X => write!(f, "x"),
+      Axis::Y => write!(f, "y"),
+      Axis::Z => write!(f, "z"),
+    }
+  }
+}
+
+impl Molecule {
+  /// Unweighted mean of the atom positions, or `None` for an empty molecule
+  pub fn centroid(&self) -> Option<[f64; 3]> {
+    if self.atoms.is_empty() {
+      return None;
+    }
+
+    let n = self.atoms.len() as f64;
+    let sum = self.atoms.iter().fold([0.0; 3], |acc, a| {
+      [acc[0] + a.x, acc[1] + a.y, acc[2] + a.z]
+    });
+
+    Some([sum[0] / n, sum[1] / n, sum[2] / n])
+  }
+
+  /// Mass-weighted mean of the atom positions
+  ///
+  /// An atom with a mass from a mass column uses it, and one with an isotope
+  /// uses the isotope's mass. Returns `None` for an empty molecule or if any
+  /// atom's mass is unknown.
+  pub fn center_of_mass(&self) -> Option<[f64; 3]> {
+    let masses: Vec<f64> = self.atoms.iter().map(mass_of).collect::<Option<_>>()?;
+    let total: f64 = masses.iter().sum();
+    if total <= 0.0 {
+      return None;
+    }
+
+    let mut center = [0.0; 3];
+    for (atom, m) in self.atoms.iter().zip(&masses) {
+      center[0] += m * atom.x / total;
+      center[1] += m * atom.y / total;
+      center[2] += m * atom.z / total;
+    }
+    Some(center)
+  }
+
+  /// Mass-weighted radius of gyration in Angstrom
+  ///
+  /// Masses are as for `center_of_mass`, and so is the `None` case.
+  pub fn radius_of_gyration(&self) -> Option<f64> {
+    let center = self.center_of_mass()?;
+    let masses: Vec<f64> = self.atoms.iter().map(mass_of).collect::<Option<_>>()?;
+    let total: f64 = masses.iter().sum();
+
+    let sum: f64 = self
+      .atoms
+      .iter()
+      .zip(&masses)
+      .map(|(atom, m)| m * distance_squared(&[atom.x, atom.y, atom.z], &center))
+      .sum();
+    Some((sum / total).sqrt())
+  }
+
+  /// Electric dipole moment in Debye from the atoms' partial charges
+  ///
+  /// Positions are taken relative to the center of mass, which matters only
+  /// for a molecule with a net charge. Returns `None` if any atom has no
+  /// partial charge, or as for `center_of_mass`.
+  pub fn dipole(&self) -> Option<[f64; 3]> {
+    let center = self.center_of_mass()?;
+    let mut dipole = [0.0; 3];
+    for atom in &self.atoms {
+      let q = atom.partial_charge? * DEBYE_PER_E_ANGSTROM;
+      dipole[0] += q * (atom.x - center[0]);
+      dipole[1] += q * (atom.y - center[1]);
+      dipole[2] += q * (atom.z - center[2]);
+    }
+    Some(dipole)
+  }
+
+  /// Sum of the atoms' partial charges in e
+  ///
+  /// Returns `None` for an empty molecule or if any atom has no partial
+  /// charge, since a partial sum would look like a complete one.
+  pub fn net_charge(&self) -> Option<f64> {
+    if self.atoms.is_empty() {
+      return None;
+    }
+    self.atoms.iter().map(|atom| atom.partial_charge).sum()
+  }
+
+  /// Mean partial charge of each element in e, sorted by element
+  ///
+  /// Returns `None` in the same cases as `net_charge`.
+  pub fn mean_charge_by_element(&self) -> Option<Vec<(String, f64)>> {
+    self.net_charge()?;
+    let mut totals: BTreeMap<&str, (f64, usize)> = BTreeMap::new();
+    for atom in &self.atoms {
+      let (sum, count) = totals.entry(&atom.element).or_default();
+      *sum += atom.partial_charge?;
+      *count += 1;
+    }
+    Some(totals.into_iter().map(|(element, (sum, count))| (element.to_string(), sum / count as f64)).collect())
+  }
+
+  /// Smallest and largest coordinate along each axis, or `None` for an empty molecule
+  pub fn bounding_box(&self) -> Option<([f64; 3], [f64; 3])> {
+    let first = self.atoms.first()?;
+    let mut min = [first.x, first.y, first.z];
+    let mut max = min;
+    for atom in &self.atoms {
+      for (k, v) in [atom.x, atom.y, atom.z].into_iter().enumerate() {
+        min[k] = min[k].min(v);
+        max[k] = max[k].max(v);
+      }
+    }
+    Some((min, max))
+  }
+
+  /// Largest distance between any two atom centers, or `None` for an empty molecule
+  ///
+  /// This compares every pair of atoms, so it is quadratic in the atom count.
+  pub fn diameter(&self) -> Option<f64> {
+    if self.atoms.is_empty() {
+      return None;
+    }
+
+    let positions: Vec<[f64; 3]> = self.atoms.iter().map(|a| [a.x, a.y, a.z]).collect();
+    let mut max = 0.0f64;
+    for (i, p) in positions.iter().enumerate() {
+      for q in &positions[i + 1..] {
+        max = max.max(distance_squared(p, q));
+      }
+    }
+    Some(max.sqrt())
+  }
+
+  /// Shift every atom by `v`
+  pub fn translate(&mut self, v: [f64; 3]) {
+    for atom in &mut self.atoms {
+      atom.x += v[0];
+      atom.y += v[1];
+      atom.z += v[2];
+    }
+  }
+
+  /// Multiply every coordinate by `factor`, such as `LengthUnit::to_angstrom`
+  pub fn scale(&mut self, factor: f64) {
+    for atom in &mut self.atoms {
+      atom.x *= factor;
+      atom.y *= factor;
+      atom.z *= factor;
+    }
+  }
+
+  /// Rotate every atom by `angle_rad` (right-handed) about `axis` through `center`
+  ///
+  /// The axis need not be normalized. A zero-length or non-finite axis leaves
+  /// the molecule unchanged.
+  pub fn rotate(&mut self, axis: [f64; 3], angle_rad: f64, center: RotationCenter) {
+    let len = (axis[0] * axis[0] + axis[1] * axis[1] + axis[2] * axis[2]).sqrt();
+    if !len.is_finite() || len == 0.0 {
+      return;
+    }
+    let k = [axis[0] / len, axis[1] / len, axis[2] / len];
+
+    let pivot = match center {
+      RotationCenter::Centroid => self.centroid().unwrap_or([0.0; 3]),
+      RotationCenter::Origin => [0.0; 3],
+    };
+
+    let (sin, cos) = angle_rad.sin_cos();
+
+    // Rodrigues' rotation formula
+    for atom in &mut self.atoms {
+      let p = [atom.x - pivot[0], atom.y - pivot[1], atom.z - pivot[2]];
+      let cross = [
+        k[1] * p[2] - k[2] * p[1],
+        k[2] * p[0] - k[0] * p[2],
+        k[0] * p[1] - k[1] * p[0],
+      ];
+      let dot = k[0] * p[0] + k[1] * p[1] + k[2] * p[2];
+
+      atom.x = pivot[0] + p[0] * cos + cross[0] * sin + k[0] * dot * (1.0 - cos);
+      atom.y = pivot[1] + p[1] * cos + cross[1] * sin + k[1] * dot * (1.0 - cos);
+      atom.z = pivot[2] + p[2] * cos + cross[2] * sin + k[2] * dot * (1.0 - cos);
+    }
+  }
+
+  /// Negate one coordinate of every atom, reflecting through the plane
+  /// where that coordinate is zero
+  ///
+  /// This converts between left- and right-handed conventions and inverts
+  /// the chirality of every stereocenter.
+  pub fn mirror(&mut self, axis: Axis) {
+    for atom in &mut self.atoms {
+      match axis {
+        Axis::X => atom.x = -atom.x,
+        Axis::Y => atom.y = -atom.y,
+        Axis::Z => atom.z = -atom.z,
+      }
+    }
+  }
+
+  /// Reorder atoms by element symbol, keeping the file order within each element
+  pub fn sort_by_element(&mut self) {
+    self.atoms.sort_by(|a, b| a.element.cmp(&b.element));
+  }
+
+  /// Reorder atoms lexicographically by x, then y, then z
+  ///
+  /// Atoms at identical positions keep their file order.
+  pub fn sort_by_position(&mut self) {
+    self
+      .atoms
+      .sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)).then(a.z.total_cmp(&b.z)));
+  }
+
+  /// Remove atoms closer than `tol` to an earlier, kept atom of the same element
+  ///
+  /// Returns the number of atoms removed. The remaining atoms keep their
+  /// relative order. A tolerance that is not positive removes nothing.
+  pub fn deduplicate(&mut self, tol: f64) -> usize {
+    if tol.is_nan() || tol <= 0.0 || self.atoms.is_empty() {
+      return 0;
+    }
+
+    let positions: Vec<[f64; 3]> = self.atoms.iter().map(|a| [a.x, a.y, a.z]).collect();
+    let grid = SpatialGrid::new(&positions, tol.min(f64::MAX));
+    let mut removed = vec![false; self.atoms.len()];
+
+    for i in 0..self.atoms.len() {
+      if removed[i] {
+        continue;
+      }
+      for j in grid.neighbors_within(positions[i], tol) {
+        if j > i
+          && !removed[j]
+          && self.atoms[j].element == self.atoms[i].element
+          && distance_squared(&positions[i], &positions[j]) < tol * tol
+        {
+          removed[j] = true;
+        }
+      }
+    }
+
+    let mut flags = removed.iter();
+    self.atoms.retain(|_| !flags.next().unwrap());
+    removed.iter().filter(|r| **r).count()
+  }
+
+  /// Append copies of `other`'s atoms shifted by `offset`, returning the
+  /// indices the new atoms were given
+  ///
+  /// The comment stays that of `self`. The total charge is the sum when both
+  /// charges are known, and the multiplicity is dropped since it can't be
+  /// derived from the parts.
+  pub fn merge(&mut self, other: &Molecule, offset: [f64; 3]) -> Range<usize> {
+    let start = self.atoms.len();
+    self.atoms.extend(other.atoms.iter().map(|atom| {
+      let mut atom = atom.clone();
+      atom.x += offset[0];
+      atom.y += offset[1];
+      atom.z += offset[2];
+      atom
+    }));
+
+    self.charge = self.charge.zip(other.charge).map(|(a, b)| a + b);
+    self.multiplicity = None;
+    start..self.atoms.len()
+  }
+
+  /// Remove the atoms at `indices`, returning how many were removed
+  ///
+  /// Indices refer to the atoms before any are removed, so they may come in
+  /// any order and repeat. The remaining atoms keep their relative order.
+  ///
+  /// # Panics
+  ///
+  /// Panics if an index is out of range.
+  pub fn remove_atoms(&mut self, indices: &[usize]) -> usize {
+    let mut removed = vec![false; self.atoms.len()];
+    for &index in indices {
+      assert!(
+        index < self.atoms.len(),
+        "atom index {} is out of range for {} atoms",
+        index,
+        self.atoms.len()
+      );
+      removed[index] = true;
+    }
+
+    let mut flags = removed.iter();
+    self.atoms.retain(|_| !flags.next().unwrap());
+    removed.iter().filter(|r| **r).count()
+  }
+
+  /// Atom pairs `(a, b, distance)` with `a` from `frag_a` and `b` from `frag_b`
+  /// no more than `cutoff` apart, sorted by `a` then `b`
+  ///
+  /// An atom listed in both fragments is never paired with itself. A cutoff
+  /// that is not a positive, finite number yields no contacts.
+  ///
+  /// # Panics
+  ///
+  /// Panics if a fragment index is out of range.
+  pub fn contacts_between(&self, frag_a: &[usize], frag_b: &[usize], cutoff: f64) -> Vec<(usize, usize, f64)> {
+    if !cutoff.is_finite() || cutoff <= 0.0 {
+      return Vec::new();
+    }
+
+    let position = |i: usize| {
+      let atom = &self.atoms[i];
+      [atom.x, atom.y, atom.z]
+    };
+    let b_positions: Vec<[f64; 3]> = frag_b.iter().map(|&i| position(i)).collect();
+    let grid = SpatialGrid::new(&b_positions, cutoff);
+
+    let mut contacts = Vec::new();
+    for &a in frag_a {
+      let p = position(a);
+      for k in grid.neighbors_within(p, cutoff) {
+        let b = frag_b[k];
+        if a != b {
+          contacts.push((a, b, distance_squared(&p, &b_positions[k]).sqrt()));
+        }
+      }
+    }
+
+    contacts.sort_by_key(|&(a, b, _)| (a, b));
+    contacts.dedup_by_key(|&mut (a, b, _)| (a, b));
+    contacts
+  }
+
+  /// Distance each atom moved between this structure and `other`, matching
+  /// atoms by index
+  ///
+  /// Elements are not compared, so a reordered structure gives meaningless
+  /// but valid results.
+  pub fn displacement(&self, other: &Molecule) -> Result<Vec<f64>, GeometryError> {
+    if self.atoms.len() != other.atoms.len() {
+      return Err(GeometryError::AtomCountMismatch {
+        expected: self.atoms.len(),
+        actual: other.atoms.len(),
+      });
+    }
+
+    Ok(
+      self
+        .atoms
+        .iter()
+        .zip(&other.atoms)
+        .map(|(a, b)| distance_squared(&[a.x, a.y, a.z], &[b.x, b.y, b.z]).sqrt())
+        .collect(),
+    )
+  }
+
+  /// Root-mean-square distance between atoms with the same index, in
+  /// Angstrom, without superposing the structures
+  ///
+  /// Returns `None` for empty structures. Use `match_atoms` when the files
+  /// list atoms in different orders.
+  pub fn rmsd(&self, other: &Molecule) -> Result<Option<f64>, GeometryError> {
+    let distances = self.displacement(other)?;
+    if distances.is_empty() {
+      return Ok(None);
+    }
+    Ok(Some((distances.iter().map(|d| d * d).sum::<f64>() / distances.len() as f64).sqrt()))
+  }
+
+  /// Pair each atom with an atom of `other` regardless of order, and the
+  /// RMSD under that pairing
+  ///
+  /// Atoms only pair with atoms of the same element. Bonds are atoms within
+  /// `cutoff`, as for `coordination_numbers`, and atoms with the same
+  /// element and neighborhood are preferred, then the closest. Molecules of
+  /// up to eight atoms are matched exhaustively, keeping every bond and
+  /// minimizing the RMSD; larger ones, or small ones whose bond graphs
+  /// differ, are matched greedily. Coordinates are compared as given,
+  /// without superposition.
+  pub fn match_atoms(&self, other: &Molecule, cutoff: f64) -> Result<AtomMatch, GeometryError> {
+    if self.atoms.len() != other.atoms.len() {
+      return Err(GeometryError::AtomCountMismatch {
+        expected: self.atoms.len(),
+        actual: other.atoms.len(),
+      });
+    }
+    let mut counts: HashMap<&str, i64> = HashMap::new();
+    for atom in &self.atoms {
+      *counts.entry(&atom.element).or_default() += 1;
+    }
+    for atom in &other.atoms {
+      *counts.entry(&atom.element).or_default() -= 1;
+    }
+    if let Some((element, _)) = counts.into_iter().filter(|(_, n)| *n != 0).min() {
+      return Err(GeometryError::ElementMismatch(element.to_string()));
+    }
+
+    let neighbors = [self.neighbor_lists(cutoff), other.neighbor_lists(cutoff)];
+    let labels = connectivity_labels([self, other], &neighbors);
+    let distance = |i: usize, j: usize| {
+      let (a, b) = (&self.atoms[i], &other.atoms[j]);
+      distance_squared(&[a.x, a.y, a.z], &[b.x, b.y, b.z])
+    };
+
+    let exact = if self.atoms.len() <= EXACT_MATCH_LIMIT {
+      let mut search = ExactMatch {
+        compatible: &|i, j| labels[0][i] == labels[1][j],
+        neighbors: &neighbors,
+        distance: &distance,
+        mapping: Vec::new(),
+        used: vec![false; self.atoms.len()],
+        best: None,
+      };
+      search.extend(0.0);
+      search.best.map(|(mapping, _)| mapping)
+    } else {
+      None
+    };
+    let mapping = exact.unwrap_or_else(|| {
+      // Cheapest pairs first: same neighborhood, then shortest distance
+      let mut pairs: Vec<(bool, f64, usize, usize)> = Vec::new();
+      for (i, a) in self.atoms.iter().enumerate() {
+        for (j, b) in other.atoms.iter().enumerate() {
+          if a.element == b.element {
+            pairs.push((labels[0][i] != labels[1][j], distance(i, j), i, j));
+          }
+        }
+      }
+      pairs.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.total_cmp(&b.1)));
+
+      let mut mapping = vec![usize::MAX; self.atoms.len()];
+      let mut used = vec![false; other.atoms.len()];
+      for (_, _, i, j) in pairs {
+        if mapping[i] == usize::MAX && !used[j] {
+          mapping[i] = j;
+          used[j] = true;
+        }
+      }
+      mapping
+    });
+
+    let total: f64 = mapping.iter().enumerate().map(|(i, &j)| distance(i, j)).sum();
+    let rmsd = if mapping.is_empty() {
+      0.0
+    } else {
+      (total / mapping.len() as f64).sqrt()
+    };
+    Ok(AtomMatch { mapping, rmsd })
+  }
+
+  /// Periodic cell from an extended-XYZ `Lattice="ax ay az bx by bz cx cy cz"`
+  /// comment field, or `None` if it is absent, malformed, or flat
+  pub fn lattice(&self) -> Option<Lattice> {
+    let field = self.comment_fields().remove("Lattice")?;
+    let values: Vec<f64> = field.split_whitespace().map(|v| v.parse().ok()).collect::<Option<_>>()?;
+    if values.len() != 9 || values.iter().any(|v| !v.is_finite()) {
+      return None;
+    }
+
+    let lattice = [
+      [values[0], values[1], values[2]],
+      [values[3], values[4], values[5]],
+      [values[6], values[7], values[8]],
+    ];
+    invert(&lattice).map(|_| lattice)
+  }
+
+  /// Distance between atoms `i` and `j` under the minimum-image convention,
+  /// or the plain Euclidean distance without a lattice
+  ///
+  /// The separation is wrapped into the cell and the neighboring images are
+  /// checked too, so skewed cells give the shortest image as well. A flat
+  /// lattice is ignored.
+  ///
+  /// # Panics
+  ///
+  /// Panics if `i` or `j` is out of range.
+  pub fn min_image_distance(&self, i: usize, j: usize, lattice: Option<&Lattice>) -> f64 {
+    let delta = self.min_image_vector(i, j, lattice);
+    dot(&delta, &delta).sqrt()
+  }
+
+  /// Shortest vector from atom `i` to an image of atom `j`, as for
+  /// `min_image_distance`
+  fn min_image_vector(&self, i: usize, j: usize, lattice: Option<&Lattice>) -> [f64; 3] {
+    let [a, b] = self.positions_of([i, j]);
+    let delta = sub(&b, &a);
+    let Some((lattice, inverse)) = lattice.and_then(|l| Some((l, invert(l)?))) else {
+      return delta;
+    };
+
+    // Fractional separation, wrapped to the nearest image
+    let fractional: [f64; 3] = std::array::from_fn(|k| {
+      let f = (0..3).map(|m| delta[m] * inverse[m][k]).sum::<f64>();
+      f - f.round()
+    });
+
+    let mut shortest = delta;
+    let mut shortest_squared = f64::INFINITY;
+    for shift in 0..27 {
+      let image = [
+        fractional[0] + (shift % 3) as f64 - 1.0,
+        fractional[1] + (shift / 3 % 3) as f64 - 1.0,
+        fractional[2] + (shift / 9) as f64 - 1.0,
+      ];
+      let cartesian: [f64; 3] = std::array::from_fn(|k| (0..3).map(|m| image[m] * lattice[m][k]).sum());
+      if dot(&cartesian, &cartesian) < shortest_squared {
+        shortest_squared = dot(&cartesian, &cartesian);
+        shortest = cartesian;
+      }
+    }
+    shortest
+  }
+
+  /// Angle i-j-k at atom `j` in degrees, or `None` if atom `i` or `k`
+  /// sits on atom `j`
+  ///
+  /// With a lattice, each bond runs to the nearest image, as for
+  /// `min_image_distance`.
+  ///
+  /// # Panics
+  ///
+  /// Panics if any index is out of range.
+  pub fn angle(&self, i: usize, j: usize, k: usize, lattice: Option<&Lattice>) -> Option<f64> {
+    let (u, v) = (self.min_image_vector(j, i, lattice), self.min_image_vector(j, k, lattice));
+    let lengths = (dot(&u, &u) * dot(&v, &v)).sqrt();
+    if lengths < 1e-12 {
+      return None;
+    }
+    Some((dot(&u, &v) / lengths).clamp(-1.0, 1.0).acos().to_degrees())
+  }
+
+  /// Dihedral angle i-j-k-l about the j-k bond in degrees, from -180 to
+  /// 180, or `None` if three of the atoms are collinear
+  ///
+  /// With a lattice, each bond runs to the nearest image, as for
+  /// `min_image_distance`.
+  ///
+  /// # Panics
+  ///
+  /// Panics if any index is out of range.
+  pub fn dihedral(&self, i: usize, j: usize, k: usize, l: usize, lattice: Option<&Lattice>) -> Option<f64> {
+    let b1 = self.min_image_vector(i, j, lattice);
+    let b2 = self.min_image_vector(j, k, lattice);
+    let b3 = self.min_image_vector(k, l, lattice);
+    let (n1, n2) = (cross(&b1, &b2), cross(&b2, &b3));
+    if dot(&n1, &n1) < 1e-12 || dot(&n2, &n2) < 1e-12 {
+      return None;
+    }
+    let x = dot(&n1, &n2) * dot(&b2, &b2).sqrt();
+    let y = dot(&cross(&n1, &n2), &b2);
+    Some(y.atan2(x).to_degrees())
+  }
+
+  fn positions_of<const N: usize>(&self, indices: [usize; N]) -> [[f64; 3]; N] {
+    indices.map(|index| {
+      assert!(
+        index < self.atoms.len(),
+        "atom index {} is out of range for {} atoms",
+        index,
+        self.atoms.len()
+      );
+      let atom = &self.atoms[index];
+      [atom.x, atom.y, atom.z]
+    })
+  }
+
+  /// Number of other atoms within `cutoff` of each atom (inclusive)
+  ///
+  /// A cutoff that is not a positive, finite number counts no neighbors.
+  pub fn coordination_numbers(&self, cutoff: f64) -> Vec<usize> {
+    self.neighbor_lists(cutoff).iter().map(Vec::len).collect()
+  }
+
+  /// Indices of the other atoms within `cutoff` of each atom, the bond
+  /// graph used by `coordination_numbers` and `match_atoms`
+  fn neighbor_lists(&self, cutoff: f64) -> Vec<Vec<usize>> {
+    if !cutoff.is_finite() || cutoff <= 0.0 {
+      return vec![Vec::new(); self.atoms.len()];
+    }
+
+    let positions: Vec<[f64; 3]> = self.atoms.iter().map(|a| [a.x, a.y, a.z]).collect();
+    let grid = SpatialGrid::new(&positions, cutoff);
+    positions
+      .iter()
+      .enumerate()
+      .map(|(i, p)| grid.neighbors_within(*p, cutoff).into_iter().filter(|&j| j != i).collect())
+      .collect()
+  }
+
+  /// The `k` atoms nearest to atom `index` as `(index, distance)`, closest first
+  ///
+  /// Ties are broken by index, and asking for more neighbors than there are
+  /// other atoms returns all of them. Large molecules are searched with a
+  /// spatial grid, widening the radius until enough atoms are found.
+  ///
+  /// # Panics
+  ///
+  /// Panics if `index` is out of range.
+  pub fn nearest_neighbors(&self, index: usize, k: usize) -> Vec<(usize, f64)> {
+    assert!(
+      index < self.atoms.len(),
+      "atom index {} is out of range for {} atoms",
+      index,
+      self.atoms.len()
+    );
+
+    let positions: Vec<[f64; 3]> = self.atoms.iter().map(|a| [a.x, a.y, a.z]).collect();
+    let p = positions[index];
+    let k = k.min(positions.len() - 1);
+
+    // Cells sized to hold about one atom each. Extreme coordinates can
+    // overflow the volume, leaving no usable cell size.
+    let cell = self
+      .bounding_box()
+      .filter(|_| positions.len() > NEIGHBOR_GRID_THRESHOLD && k > 0)
+      .map(|(min, max)| {
+        let volume: f64 = (0..3).map(|axis| (max[axis] - min[axis]).max(1.0)).product();
+        (volume / positions.len() as f64).cbrt()
+      })
+      .filter(|cell| cell.is_finite() && *cell > 0.0);
+
+    let candidates = match cell {
+      Some(cell) => {
+        let grid = SpatialGrid::new(&positions, cell);
+
+        // Everything outside the radius is farther than everything inside,
+        // so the first radius holding k other atoms holds the k nearest
+        let mut radius = cell * ((k + 1) as f64).cbrt();
+        loop {
+          let found = grid.neighbors_within(p, radius);
+          if found.len() > k {
+            break found;
+          }
+          radius *= 2.0;
+        }
+      }
+      None => (0..positions.len()).collect(),
+    };
+
+    let mut neighbors: Vec<(usize, f64)> = candidates
+      .into_iter()
+      .filter(|&j| j != index)
+      .map(|j| (j, distance_squared(&p, &positions[j]).sqrt()))
+      .collect();
+    neighbors.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+    neighbors.truncate(k);
+    neighbors
+  }
+}
+
+fn sub(a: &[f64; 3], b: &[f64; 3]) -> [f64; 3] {
+  std::array::from_fn(|k| a[k] - b[k])
+}
+
+fn dot(a: &[f64; 3], b: &[f64; 3]) -> f64 {
+  (0..3).map(|k| a[k] * b[k]).sum()
+}
+
+fn cross(a: &[f64; 3], b: &[f64; 3]) -> [f64; 3] {
+  [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+/// Inverse of a 3x3 matrix, or `None` if it is singular
+fn invert(m: &[[f64; 3]; 3]) -> Option<[[f64; 3]; 3]> {
+  let cofactor = |r: usize, c: usize| {
+    let (r1, r2) = ((r + 1) % 3, (r + 2) % 3);
+    let (c1, c2) = ((c + 1) % 3, (c + 2) % 3);
+    m[r1][c1] * m[r2][c2] - m[r1][c2] * m[r2][c1]
+  };
+  let determinant: f64 = (0..3).map(|c| m[0][c] * cofactor(0, c)).sum();
+  if determinant.abs() < 1e-12 {
+    return None;
+  }
+  // The inverse is the transposed cofactor matrix over the determinant
+  Some(std::array::from_fn(|r| std::array::from_fn(|c| cofactor(c, r) / determinant)))
+}
+
+/// Labels equal for atoms with the same element and, out to `LABEL_ROUNDS`
+/// bonds, the same neighborhood, numbered consistently across both molecules
+fn connectivity_labels(molecules: [&Molecule; 2], neighbors: &[Vec<Vec<usize>>; 2]) -> [Vec<usize>; 2] {
+  let mut ids: HashMap<(String, usize), usize> = HashMap::new();
+  let mut labels = [0, 1].map(|m| {
+    molecules[m]
+      .atoms
+      .iter()
+      .zip(&neighbors[m])
+      .map(|(atom, bonded)| {
+        let next = ids.len();
+        *ids.entry((atom.element.clone(), bonded.len())).or_insert(next)
+      })
+      .collect::<Vec<usize>>()
+  });
+
+  for _ in 0..LABEL_ROUNDS {
+    let mut ids: HashMap<(usize, Vec<usize>), usize> = HashMap::new();
+    labels = [0, 1].map(|m| {
+      (0..labels[m].len())
+        .map(|i| {
+          let mut around: Vec<usize> = neighbors[m][i].iter().map(|&j| labels[m][j]).collect();
+          around.sort_unstable();
+          let next = ids.len();
+          *ids.entry((labels[m][i], around)).or_insert(next)
+        })
+        .collect()
+    });
+  }
+  labels
+}
+
+/// Depth-first search for the bond-preserving atom mapping with the lowest
+/// RMSD, used by `match_atoms` on small molecules
+struct ExactMatch<'a> {
+  compatible: &'a dyn Fn(usize, usize) -> bool,
+  neighbors: &'a [Vec<Vec<usize>>; 2],
+  distance: &'a dyn Fn(usize, usize) -> f64,
+  mapping: Vec<usize>,
+  used: Vec<bool>,
+  /// Best complete mapping so far and its summed squared distance
+  best: Option<(Vec<usize>, f64)>,
+}
+
+impl ExactMatch<'_> {
+  fn extend(&mut self, cost: f64) {
+    if self.best.as_ref().is_some_and(|(_, best)| cost >= *best) {
+      return;
+    }
+    let i = self.mapping.len();
+    if i == self.used.len() {
+      self.best = Some((self.mapping.clone(), cost));
+      return;
+    }
+
+    for j in 0..self.used.len() {
+      if self.used[j] || !(self.compatible)(i, j) {
+        continue;
+      }
+      // Atom i must be bonded to exactly the mapped atoms j is bonded to
+      let keeps_bonds = self.mapping.iter().enumerate().all(|(k, &mapped)| {
+        self.neighbors[0][i].contains(&k) == self.neighbors[1][j].contains(&mapped)
+      });
+      if !keeps_bonds {
+        continue;
+      }
+
+      self.used[j] = true;
+      self.mapping.push(j);
+      self.extend(cost + (self.distance)(i, j));
+      self.mapping.pop();
+      self.used[j] = false;
+    }
+  }
+}
+
+/// Atom mass in g/mol: the mass read from a mass column, else the isotope's
+/// mass (its mass number if untabulated), else the periodic-table mass
+fn mass_of(atom: &Atom) -> Option<f64> {
+  if let Some(mass) = atom.mass {
+    return Some(mass);
+  }
+  match atom.isotope {
+    Some(mass_number) => Some(isotope_mass(&atom.element, mass_number).unwrap_or(f64::from(mass_number))),
+    None => atomic_mass(&atom.element),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parser::{parse_xyz_str, parse_xyz_with_options, ParseOptions};
+  use crate::testing::generate_lattice_molecule;
+  use crate::writer::write_xyz_string;
+  use std::f64::consts::PI;
+
+  const EPS: f64 = 1e-9;
+
+  fn water() -> Molecule {
+    parse_xyz_str("3\nwater\nO 0.0 0.0 0.117\nH 0.0 0.757 -0.467\nH 0.0 -0.757 -0.467\n").unwrap()
+  }
+
+  fn assert_positions_close(a: &Molecule, b: &Molecule) {
+    for (p, q) in a.atoms.iter().zip(&b.atoms) {
+      assert!(
+        (p.x - q.x).abs() < EPS && (p.y - q.y).abs() < EPS && (p.z - q.z).abs() < EPS,
+        "{:?} != {:?}",
+        p,
+        q
+      );
+    }
+  }
+
+  #[test]
+  fn test_translate_shifts_all_atoms() {
+    let mut molecule = water();
+    molecule.translate([1.0, -2.0, 0.5]);
+
+    assert_eq!(molecule.atoms[1].x, 1.0);
+    assert_eq!(molecule.atoms[1].y, 0.757 - 2.0);
+    assert_eq!(molecule.atoms[2].z, -0.467 + 0.5);
+  }
+
+  #[test]
+  fn test_scale_converts_units() {
+    let mut molecule = parse_xyz_str("2\ngromacs\nO 0.1 0.0 -0.05\nH 0.0 0.2 0.0\n").unwrap();
+    molecule.scale(LengthUnit::from_name("nm").unwrap().to_angstrom());
+
+    assert_eq!((molecule.atoms[0].x, molecule.atoms[0].z), (1.0, -0.5));
+    assert_eq!(molecule.atoms[1].y, 2.0);
+    assert!((LengthUnit::Bohr.to_angstrom() - 0.529_177).abs() < 1e-6);
+    assert_eq!(LengthUnit::from_name("Angstrom"), Some(LengthUnit::Angstrom));
+    assert_eq!(LengthUnit::from_name("pm"), None);
+  }
+
+  #[test]
+  fn test_full_turn_returns_to_original() {
+    let original = water();
+
+    for center in [RotationCenter::Centroid, RotationCenter::Origin] {
+      for axis in [[1.0, 0.0, 0.0], [0.3, -1.2, 2.5]] {
+        let mut molecule = original.clone();
+        molecule.rotate(axis, 2.0 * PI, center);
+        assert_positions_close(&molecule, &original);
+      }
+    }
+  }
+
+  #[test]
+  fn test_quarter_turn_about_origin() {
+    let mut molecule = parse_xyz_str("1\n\nC 1.0 0.0 0.0\n").unwrap();
+    molecule.rotate([0.0, 0.0, 2.0], PI / 2.0, RotationCenter::Origin);
+
+    let atom = &molecule.atoms[0];
+    assert!(atom.x.abs() < EPS && (atom.y - 1.0).abs() < EPS && atom.z.abs() < EPS);
+  }
+
+  #[test]
+  fn test_rotation_about_centroid_keeps_centroid() {
+    let mut molecule = water();
+    let before = molecule.centroid().unwrap();
+    molecule.rotate([1.0, 1.0, 0.0], 1.3, RotationCenter::Centroid);
+    let after = molecule.centroid().unwrap();
+
+    for k in 0..3 {
+      assert!((before[k] - after[k]).abs() < EPS);
+    }
+  }
+
+  /// Signed volume of the tetrahedron formed by the first four atoms
+  fn signed_volume(molecule: &Molecule) -> f64 {
+    let p: Vec<[f64; 3]> = molecule.atoms.iter().map(|a| [a.x, a.y, a.z]).collect();
+    let d = |i: usize| [p[i][0] - p[0][0], p[i][1] - p[0][1], p[i][2] - p[0][2]];
+    let (a, b, c) = (d(1), d(2), d(3));
+    (a[0] * (b[1] * c[2] - b[2] * c[1]) - a[1] * (b[0] * c[2] - b[2] * c[0]) + a[2] * (b[0] * c[1] - b[1] * c[0]))
+      / 6.0
+  }
+
+  #[test]
+  fn test_mirror_inverts_chirality() {
+    // Four different substituents around a stereocenter
+    let original = parse_xyz_str("4\nCHFClBr\nH 1.0 0.0 0.0\nF 0.0 1.2 0.0\nCl 0.0 0.0 1.5\nBr -0.8 -0.9 -1.1\n").unwrap();
+
+    for axis in [Axis::X, Axis::Y, Axis::Z] {
+      let mut molecule = original.clone();
+      molecule.mirror(axis);
+
+      assert!((signed_volume(&molecule) + signed_volume(&original)).abs() < EPS);
+      assert!(signed_volume(&original).abs() > 0.1);
+
+      molecule.mirror(axis);
+      assert_eq!(molecule, original);
+    }
+  }
+
+  #[test]
+  fn test_mirror_negates_only_one_axis() {
+    let mut molecule = water();
+    molecule.mirror(Axis::Y);
+
+    assert_eq!(molecule.atoms[1].y, -0.757);
+    assert_eq!(molecule.atoms[1].z, -0.467);
+    assert_eq!(Axis::from_name("Z"), Some(Axis::Z));
+    assert_eq!(Axis::from_name("w"), None);
+  }
+
+  #[test]
+  fn test_radius_of_gyration_is_mass_weighted() {
+    // Two equal masses 2 Angstrom apart are each 1 Angstrom from the center
+    let dimer = parse_xyz_str("2\n\nO 0 0 0\nO 2 0 0\n").unwrap();
+    assert!((dimer.radius_of_gyration().unwrap() - 1.0).abs() < EPS);
+
+    // Deuterium is heavier than hydrogen, pulling the center toward it
+    let mut hd = parse_xyz_str("2\n\nH 0 0 0\nH 1 0 0\n").unwrap();
+    hd.atoms[1].isotope = Some(2);
+    let expected = (1.008f64 * 2.014102).sqrt() / (1.008 + 2.014102);
+    assert!((hd.radius_of_gyration().unwrap() - expected).abs() < EPS);
+  }
+
+  #[test]
+  fn test_center_of_mass_uses_isotope_masses() {
+    let options = ParseOptions {
+      resolve_aliases: true,
+      ..ParseOptions::default()
+    };
+    let content = "3\nheavy water\nO 0.0 0.0 0.0\nD 1.0 0.0 0.0\nT 0.0 1.0 0.0\n";
+    let molecule = parse_xyz_with_options(content.as_bytes(), &options).unwrap();
+    let center = molecule.center_of_mass().unwrap();
+
+    let total = 15.999 + 2.014102 + 3.016049;
+    assert!((center[0] - 2.014102 / total).abs() < EPS);
+    assert!((center[1] - 3.016049 / total).abs() < EPS);
+  }
+
+  #[test]
+  fn test_mass_column_overrides_periodic_table() {
+    let mut dimer = parse_xyz_str("2\n\nXx 0 0 0\nH 3 0 0\n").unwrap();
+    assert_eq!(dimer.center_of_mass(), None);
+
+    dimer.atoms[0].mass = Some(2.0);
+    dimer.atoms[1].mass = Some(1.0);
+    assert!((dimer.center_of_mass().unwrap()[0] - 1.0).abs() < EPS);
+  }
+
+  #[test]
+  fn test_radius_of_gyration_of_noble_gas_and_metal() {
+    let argon = generate_lattice_molecule(2, 2, 2, "Ar", 3.0);
+    // Every atom of a cube is half a diagonal from the center
+    assert!((argon.radius_of_gyration().unwrap() - 3.0 * 3.0_f64.sqrt() / 2.0).abs() < EPS);
+
+    let mut alloy = parse_xyz_str("2\n\nPt 0 0 0\nNi 2 0 0\n").unwrap();
+    let center = alloy.center_of_mass().unwrap();
+    assert!((center[0] - 2.0 * 58.693 / (195.08 + 58.693)).abs() < EPS);
+    alloy.atoms[1].element = "Xx".to_string();
+    assert_eq!(alloy.center_of_mass(), None);
+  }
+
+  #[test]
+  fn test_radius_of_gyration_needs_known_masses() {
+    assert_eq!(parse_xyz_str("1\n\nXx 0 0 0\n").unwrap().radius_of_gyration(), None);
+    assert_eq!(parse_xyz_str("0\n\n").unwrap().radius_of_gyration(), None);
+  }
+
+  #[test]
+  fn test_net_charge_and_mean_by_element() {
+    let mut molecule = water();
+    assert_eq!(molecule.net_charge(), None);
+    assert_eq!(molecule.mean_charge_by_element(), None);
+
+    for (atom, q) in molecule.atoms.iter_mut().zip([-0.834, 0.417, 0.417]) {
+      atom.partial_charge = Some(q);
+    }
+    assert!(molecule.net_charge().unwrap().abs() < EPS);
+    assert_eq!(
+      molecule.mean_charge_by_element(),
+      Some(vec![("H".to_string(), 0.417), ("O".to_string(), -0.834)])
+    );
+
+    // A sum over only some atoms is not reported
+    molecule.atoms[2].partial_charge = None;
+    assert_eq!(molecule.net_charge(), None);
+    assert_eq!(parse_xyz_str("0\n\n").unwrap().net_charge(), None);
+  }
+
+  #[test]
+  fn test_dipole_from_partial_charges() {
+    // TIP3P charges give a dipole along -z, from the oxygen toward the hydrogens' side
+    let mut molecule = water();
+    assert_eq!(molecule.dipole(), None);
+    for (atom, q) in molecule.atoms.iter_mut().zip([-0.834, 0.417, 0.417]) {
+      atom.partial_charge = Some(q);
+    }
+    let dipole = molecule.dipole().unwrap();
+    let expected = (-0.834 * 0.117 + 2.0 * 0.417 * -0.467) * DEBYE_PER_E_ANGSTROM;
+
+    assert!(dipole[0].abs() < EPS && dipole[1].abs() < EPS, "Dipole was: {:?}", dipole);
+    assert!((dipole[2] - expected).abs() < EPS, "Dipole was: {:?}", dipole);
+
+    // A neutral molecule's dipole does not depend on where it sits
+    molecule.translate([5.0, -3.0, 2.0]);
+    assert!((molecule.dipole().unwrap()[2] - expected).abs() < EPS);
+  }
+
+  #[test]
+  fn test_bounding_box_and_diameter() {
+    let molecule = water();
+    let (min, max) = molecule.bounding_box().unwrap();
+
+    assert_eq!(min, [0.0, -0.757, -0.467]);
+    assert_eq!(max, [0.0, 0.757, 0.117]);
+    assert!((molecule.diameter().unwrap() - 1.514).abs() < EPS);
+    assert_eq!(parse_xyz_str("0\n\n").unwrap().diameter(), None);
+  }
+
+  #[test]
+  fn test_sort_by_element_is_stable() {
+    let mut molecule = parse_xyz_str("4\n\nO 0 0 0\nH 3 0 0\nC 1 0 0\nH 2 0 0\n").unwrap();
+    molecule.sort_by_element();
+
+    let order: Vec<(&str, f64)> = molecule.atoms.iter().map(|a| (a.element.as_str(), a.x)).collect();
+    assert_eq!(order, vec![("C", 1.0), ("H", 3.0), ("H", 2.0), ("O", 0.0)]);
+  }
+
+  #[test]
+  fn test_sort_by_position() {
+    let mut molecule = parse_xyz_str("4\n\nO 1 0 0\nH 0 2 0\nC 0 1 5\nN 0 1 -5\n").unwrap();
+    molecule.sort_by_position();
+
+    let elements: Vec<&str> = molecule.atoms.iter().map(|a| a.element.as_str()).collect();
+    assert_eq!(elements, vec!["N", "C", "H", "O"]);
+  }
+
+  #[test]
+  fn test_sorted_output_is_independent_of_input_order() {
+    let a = parse_xyz_str("3\nwater\nO 0.0 0.0 0.117\nH 0.0 0.757 -0.467\nH 0.0 -0.757 -0.467\n").unwrap();
+    let b = parse_xyz_str("3\nwater\nH 0.0 -0.757 -0.467\nO 0.0 0.0 0.117\nH 0.0 0.757 -0.467\n").unwrap();
+
+    // Position order breaks ties between atoms of the same element
+    let canonical = |mut molecule: Molecule| {
+      molecule.sort_by_position();
+      molecule.sort_by_element();
+      write_xyz_string(&molecule)
+    };
+
+    let written = canonical(a);
+    assert_eq!(written, canonical(b));
+    assert_eq!(parse_xyz_str(&written).unwrap().atoms[0].y, -0.757);
+  }
+
+  #[test]
+  fn test_deduplicate_removes_later_copies() {
+    let mut molecule = parse_xyz_str(
+      "5\n\nO 0 0 0\nH 1 0 0\nO 0.05 0 0\nH 1 0 0\nO 0 0.2 0\n",
+    )
+    .unwrap();
+
+    assert_eq!(molecule.deduplicate(0.1), 2);
+    let remaining: Vec<(&str, f64, f64)> = molecule.atoms.iter().map(|a| (a.element.as_str(), a.x, a.y)).collect();
+    assert_eq!(remaining, vec![("O", 0.0, 0.0), ("H", 1.0, 0.0), ("O", 0.0, 0.2)]);
+  }
+
+  #[test]
+  fn test_deduplicate_keeps_different_elements_and_chains() {
+    // Each atom is within tolerance of the next but the third is not near the first
+    let mut molecule = parse_xyz_str("4\n\nC 0 0 0\nC 0.08 0 0\nC 0.16 0 0\nN 0 0 0\n").unwrap();
+
+    assert_eq!(molecule.deduplicate(0.1), 1);
+    assert_eq!(molecule.atoms.len(), 3);
+    assert_eq!(molecule.atoms[1].x, 0.16);
+    assert_eq!(molecule.atoms[2].element, "N");
+  }
+
+  #[test]
+  fn test_deduplicate_overlaid_lattices() {
+    let mut molecule = generate_lattice_molecule(20, 20, 20, "C", 1.5);
+    let mut shifted = molecule.clone();
+    shifted.translate([0.01, 0.0, 0.0]);
+    molecule.atoms.extend(shifted.atoms);
+
+    assert_eq!(molecule.deduplicate(0.1), 8000);
+    assert_eq!(molecule, generate_lattice_molecule(20, 20, 20, "C", 1.5));
+  }
+
+  #[test]
+  fn test_deduplicate_with_invalid_tolerance_is_a_no_op() {
+    let mut molecule = parse_xyz_str("2\n\nC 0 0 0\nC 0 0 0\n").unwrap();
+
+    assert_eq!(molecule.deduplicate(0.0), 0);
+    assert_eq!(molecule.deduplicate(f64::NAN), 0);
+    assert_eq!(molecule.atoms.len(), 2);
+  }
+
+  #[test]
+  fn test_contacts_between_water_dimer() {
+    let dimer = parse_xyz_str(
+      "6\ndimer\nO 0.0 0.0 0.0\nH 0.757 0.586 0.0\nH -0.757 0.586 0.0\n\
+       O 3.0 0.0 0.0\nH 3.757 0.586 0.0\nH 2.243 0.586 0.0\n",
+    )
+    .unwrap();
+
+    let contacts = dimer.contacts_between(&[0, 1, 2], &[3, 4, 5], 2.5);
+    let pairs: Vec<(usize, usize)> = contacts.iter().map(|&(a, b, _)| (a, b)).collect();
+
+    assert_eq!(pairs, vec![(0, 5), (1, 3), (1, 5)]);
+    assert!((contacts[0].2 - contacts[1].2).abs() < EPS);
+    assert!((contacts[2].2 - 1.486).abs() < EPS);
+  }
+
+  #[test]
+  fn test_contacts_skip_self_pairs_and_invalid_cutoff() {
+    let molecule = water();
+
+    assert!(molecule.contacts_between(&[0], &[0], 1.0).is_empty());
+    assert!(molecule.contacts_between(&[0], &[1, 2], 0.0).is_empty());
+    assert!(molecule.contacts_between(&[0], &[1, 2], f64::NAN).is_empty());
+  }
+
+  #[test]
+  fn test_nearest_neighbors_in_water() {
+    let molecule = water();
+    let neighbors = molecule.nearest_neighbors(1, 5);
+
+    assert_eq!(neighbors.iter().map(|n| n.0).collect::<Vec<_>>(), vec![0, 2]);
+    assert!((neighbors[1].1 - 1.514).abs() < EPS);
+    assert!(molecule.nearest_neighbors(0, 0).is_empty());
+  }
+
+  #[test]
+  fn test_nearest_neighbors_grid_matches_brute_force() {
+    // 125 atoms, enough to take the grid path
+    let lattice = generate_lattice_molecule(5, 5, 5, "Ar", 1.5);
+    for index in [0, 62, 124] {
+      for k in [1, 6, 26, 200] {
+        let neighbors = lattice.nearest_neighbors(index, k);
+
+        let position = |i: usize| [lattice.atoms[i].x, lattice.atoms[i].y, lattice.atoms[i].z];
+        let mut expected: Vec<(usize, f64)> = (0..lattice.atoms.len())
+          .filter(|&j| j != index)
+          .map(|j| (j, distance_squared(&position(index), &position(j)).sqrt()))
+          .collect();
+        expected.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+        expected.truncate(k);
+
+        assert_eq!(neighbors, expected, "Index {} with k = {}", index, k);
+      }
+    }
+  }
+
+  #[test]
+  fn test_remove_atoms_uses_original_indices() {
+    let mut molecule = parse_xyz_str("5\n\nC 0 0 0\nH 1 0 0\nH 2 0 0\nO 3 0 0\nH 4 0 0\n").unwrap();
+
+    assert_eq!(molecule.remove_atoms(&[4, 1, 4, 3]), 3);
+    let remaining: Vec<(&str, f64)> = molecule.atoms.iter().map(|a| (a.element.as_str(), a.x)).collect();
+    assert_eq!(remaining, vec![("C", 0.0), ("H", 2.0)]);
+    assert_eq!(molecule.remove_atoms(&[]), 0);
+  }
+
+  #[test]
+  fn test_merge_appends_translated_atoms() {
+    let mut dimer = water();
+    let added = dimer.merge(&water(), [0.0, 0.0, 3.0]);
+
+    assert_eq!(added, 3..6);
+    assert_eq!(dimer.atoms.len(), 6);
+    for (original, copy) in dimer.atoms[..3].iter().zip(&dimer.atoms[3..]) {
+      assert_eq!(copy.element, original.element);
+      assert_eq!((copy.x, copy.y), (original.x, original.y));
+      assert!((copy.z - original.z - 3.0).abs() < 1e-12);
+    }
+  }
+
+  #[test]
+  fn test_merge_charges() {
+    let mut cation = parse_xyz_str("1\n\nNa 0 0 0\n").unwrap();
+    (cation.charge, cation.multiplicity) = (Some(1), Some(1));
+    let mut anion = parse_xyz_str("1\n\nCl 0 0 0\n").unwrap();
+    (anion.charge, anion.multiplicity) = (Some(-1), Some(1));
+    cation.merge(&anion, [2.4, 0.0, 0.0]);
+
+    assert_eq!(cation.charge, Some(0));
+    assert_eq!(cation.multiplicity, None);
+    assert_eq!(cation.merge(&parse_xyz_str("0\n\n").unwrap(), [0.0; 3]), 2..2);
+    assert_eq!(cation.charge, None);
+  }
+
+  #[test]
+  fn test_coordination_numbers() {
+    // Corner, edge, face, and bulk sites of a simple cubic lattice
+    let lattice = generate_lattice_molecule(3, 3, 3, "Cu", 2.5);
+    let numbers = lattice.coordination_numbers(2.6);
+
+    assert_eq!(numbers[0], 3);
+    assert_eq!(numbers[1], 4);
+    assert_eq!(numbers[4], 5);
+    assert_eq!(numbers[13], 6);
+    assert_eq!(water().coordination_numbers(1.0), vec![2, 1, 1]);
+    assert_eq!(water().coordination_numbers(-1.0), vec![0, 0, 0]);
+  }
+
+  #[test]
+  fn test_nearest_neighbors_with_huge_coordinates() {
+    // Enough atoms for the grid, spread so far apart the volume overflows
+    let mut molecule = generate_lattice_molecule(5, 5, 5, "C", 1.0);
+    for (index, coordinate) in [(0, 1e200), (1, -1e200)] {
+      let atom = &mut molecule.atoms[index];
+      (atom.x, atom.y, atom.z) = (coordinate, coordinate, coordinate);
+    }
+    let neighbors = molecule.nearest_neighbors(7, 2);
+
+    assert_eq!(neighbors.len(), 2);
+    assert!(neighbors.iter().all(|&(_, d)| (d - 1.0).abs() < 1e-9), "{:?}", neighbors);
+  }
+
+  #[test]
+  #[should_panic(expected = "atom index 3 is out of range")]
+  fn test_nearest_neighbors_rejects_bad_index() {
+    water().nearest_neighbors(3, 1);
+  }
+
+  #[test]
+  fn test_min_image_distance_wraps_across_the_cell() {
+    let molecule =
+      parse_xyz_str("2\nLattice=\"10 0 0 0 10 0 0 0 10\"\nAr 0.5 0.5 0.5\nAr 9.5 0.5 9.0\n").unwrap();
+    let lattice = molecule.lattice().unwrap();
+
+    assert!((molecule.min_image_distance(0, 1, None) - 153.25_f64.sqrt()).abs() < EPS);
+    assert!((molecule.min_image_distance(0, 1, Some(&lattice)) - 3.25_f64.sqrt()).abs() < EPS);
+  }
+
+  #[test]
+  fn test_min_image_distance_in_a_skewed_cell() {
+    // Rounding the fractional separation alone picks the image 2.05 away
+    let molecule = parse_xyz_str("2\nLattice=\"4 0 0 3.5 1 0 0 0 10\"\nNe 0 0 0\nNe 2.0 0.45 0\n").unwrap();
+    let lattice = molecule.lattice().unwrap();
+
+    assert!((molecule.min_image_distance(0, 1, Some(&lattice)) - 2.5525_f64.sqrt()).abs() < EPS);
+    assert!((molecule.min_image_distance(1, 0, Some(&lattice)) - 2.5525_f64.sqrt()).abs() < EPS);
+  }
+
+  #[test]
+  fn test_angle_in_water() {
+    let water = parse_xyz_str("3\nwater\nO 0.0 0.0 0.0\nH 1.0 0.0 0.0\nH 0.0 1.0 0.0\n").unwrap();
+
+    assert!((water.angle(1, 0, 2, None).unwrap() - 90.0).abs() < 1e-9);
+    assert!(water.angle(1, 1, 2, None).is_none());
+  }
+
+  #[test]
+  fn test_angle_and_dihedral_across_the_cell() {
+    // Bonds that cross the faces of a 10 Angstrom cell
+    let cell = "Lattice=\"10 0 0 0 10 0 0 0 10\"";
+    let bent = parse_xyz_str(&format!("3\n{}\nO 0.5 8.5 9.5\nC 9.5 8.5 9.5\nO 8.5 9.5 9.5\n", cell)).unwrap();
+    let lattice = bent.lattice().unwrap();
+
+    assert!((bent.angle(0, 1, 2, Some(&lattice)).unwrap() - 135.0).abs() < 1e-9);
+    assert!((bent.angle(0, 1, 2, None).unwrap() - 45.0).abs() < 1e-9);
+
+    let chain =
+      parse_xyz_str(&format!("4\n{}\nC 0.5 9.5 9.5\nC 9.5 9.5 9.5\nC 9.5 9.5 0.5\nC 9.5 0.5 0.5\n", cell)).unwrap();
+    assert!((chain.dihedral(0, 1, 2, 3, Some(&lattice)).unwrap() - 90.0).abs() < 1e-9);
+    assert!((chain.dihedral(0, 1, 2, 3, None).unwrap() + 90.0).abs() < 1e-9);
+  }
+
+  #[test]
+  fn test_dihedral_sign_and_collinear_atoms() {
+    let content = "4\nchain\nC 1.0 0.0 0.0\nC 0.0 0.0 0.0\nC 0.0 0.0 1.0\nC 0.0 1.0 1.0\n";
+    let chain = parse_xyz_str(content).unwrap();
+
+    assert!((chain.dihedral(0, 1, 2, 3, None).unwrap() - 90.0).abs() < 1e-9);
+    assert!((chain.dihedral(3, 2, 1, 0, None).unwrap() - 90.0).abs() < 1e-9);
+    let mut mirrored = chain.clone();
+    mirrored.mirror(Axis::Y);
+    assert!((mirrored.dihedral(0, 1, 2, 3, None).unwrap() + 90.0).abs() < 1e-9);
+
+    let line = parse_xyz_str("4\nline\nC 0 0 0\nC 0 0 1\nC 0 0 2\nC 1 0 2\n").unwrap();
+    assert!(line.dihedral(0, 1, 2, 3, None).is_none());
+  }
+
+  #[test]
+  fn test_displacement_between_frames() {
+    let before = water();
+    let mut after = water();
+    after.atoms[1].y += 0.3;
+    after.atoms[2].x -= 0.4;
+    after.atoms[2].z += 0.3;
+
+    let moved = before.displacement(&after).unwrap();
+    assert_eq!(moved[0], 0.0);
+    assert!((moved[1] - 0.3).abs() < EPS);
+    assert!((moved[2] - 0.5).abs() < EPS);
+
+    let err = before.displacement(&parse_xyz_str("1\n\nHe 0 0 0\n").unwrap()).unwrap_err().to_string();
+    assert!(err.contains("atom count mismatch"), "Error was: {}", err);
+  }
+
+  #[test]
+  fn test_rmsd_by_index() {
+    let mut shifted = water();
+    shifted.translate([0.0, 0.0, 0.5]);
+
+    assert!((water().rmsd(&shifted).unwrap().unwrap() - 0.5).abs() < EPS);
+    assert_eq!(parse_xyz_str("0\n\n").unwrap().rmsd(&parse_xyz_str("0\n\n").unwrap()), Ok(None));
+  }
+
+  #[test]
+  fn test_match_atoms_in_a_different_order() {
+    let original = water();
+    let reordered = parse_xyz_str("3\n\nH 0.0 -0.757 -0.467\nO 0.0 0.0 0.117\nH 0.0 0.757 -0.467\n").unwrap();
+
+    let matched = original.match_atoms(&reordered, 1.2).unwrap();
+    assert_eq!(matched.mapping, vec![1, 2, 0]);
+    assert!(matched.rmsd < EPS);
+  }
+
+  #[test]
+  fn test_match_atoms_follows_bonds_over_distance() {
+    // The hydrogen on oxygen sits closer to the other file's hydrogen on
+    // carbon, but only the bonded match is kept
+    let original = parse_xyz_str("4\n\nC 0 0 0\nO 1.4 0 0\nH 1.4 0.95 0\nH 0 -1.0 0\n").unwrap();
+    let other = parse_xyz_str("4\n\nH -0.3 1.0 0\nO 1.4 0 0\nH 1.9 -0.8 0\nC 0 0 0\n").unwrap();
+
+    assert_eq!(original.match_atoms(&other, 1.2).unwrap().mapping, vec![3, 1, 2, 0]);
+  }
+
+  #[test]
+  fn test_match_atoms_greedily_for_large_molecules() {
+    let lattice = generate_lattice_molecule(3, 2, 2, "Cu", 2.5);
+    let mut reversed = lattice.clone();
+    reversed.atoms.reverse();
+
+    let matched = lattice.match_atoms(&reversed, 2.6).unwrap();
+    let n = lattice.atoms.len();
+    assert_eq!(matched.mapping, (0..n).rev().collect::<Vec<_>>());
+    assert!(matched.rmsd < EPS);
+  }
+
+  #[test]
+  fn test_match_atoms_needs_the_same_elements() {
+    let err = water().match_atoms(&parse_xyz_str("1\n\nO 0 0 0\n").unwrap(), 1.2).unwrap_err().to_string();
+    assert!(err.contains("atom count mismatch"), "Error was: {}", err);
+
+    let peroxide = parse_xyz_str("3\n\nO 0 0 0\nO 1.4 0 0\nH 0 1 0\n").unwrap();
+    let err = water().match_atoms(&peroxide, 1.2).unwrap_err().to_string();
+    assert!(err.contains("different numbers of H atoms"), "Error was: {}", err);
+  }
+
+  #[test]
+  fn test_lattice_from_comment() {
+    let parse = |comment: &str| parse_xyz_str(&format!("0\n{}\n", comment)).unwrap().lattice();
+
+    assert_eq!(parse("Lattice=\"2 0 0 0 3 0 0 0 4\""), Some([[2.0, 0.0, 0.0], [0.0, 3.0, 0.0], [0.0, 0.0, 4.0]]));
+    assert_eq!(parse("water"), None);
+    assert_eq!(parse("Lattice=\"2 0 0 0 3 0\""), None);
+    assert_eq!(parse("Lattice=\"1 0 0 2 0 0 0 0 1\""), None);
+  }
+
+  #[test]
+  fn test_zero_axis_is_a_no_op() {
+    let mut molecule = water();
+    molecule.rotate([0.0, 0.0, 0.0], 1.0, RotationCenter::Origin);
+
+    assert_eq!(molecule, water());
+  }
+}
@@ -0,0 +1,19 @@
+//! XYZ parsing and structure analysis for ChemGDB
+//!
+//! The viewer binary is built on top of these modules. None of them depend
+//! on Bevy, so other crates can use them for data processing alone.
+
+#[cfg(feature = "cache")]
+pub mod cache;
+pub mod geometry;
+pub mod modes;
+pub mod mol2;
+pub mod parser;
+pub mod periodic_table;
+pub mod selection;
+#[cfg(feature = "serve")]
+pub mod serve;
+pub mod spatial;
+pub mod testing;
+pub mod validate;
+pub mod writer;
@@ -1,306 +1,3860 @@
 use bevy::prelude::*;
+use bevy::ecs::system::SystemParam;
 use bevy::input::mouse::{AccumulatedMouseMotion, AccumulatedMouseScroll};
+use bevy::core_pipeline::oit::OrderIndependentTransparencySettings;
+use bevy::pbr::{ScreenSpaceAmbientOcclusion, ScreenSpaceAmbientOcclusionQualityLevel};
+use bevy::light::NotShadowCaster;
+use bevy::render::render_resource::Face;
+use bevy::render::view::screenshot::{save_to_disk, Screenshot, ScreenshotCaptured};
+use bevy::window::PrimaryWindow;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
 use std::fs::File;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::io::{BufRead, BufReader, BufWriter};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Mutex;
+use std::time::Instant;
 
 use mdi::{Mdi, Role, Method, Communicator, DataType, MdiData, Error as MdiError};
 use std::ffi::{CStr, CString};
 
-mod parser;
-use parser::parse_xyz;
+use chemgdb::cache::{cache_path, load_cache, write_cache};
+use chemgdb::geometry::{Axis, LengthUnit, DEFAULT_DEDUP_TOLERANCE};
+use chemgdb::modes::{load_modes, NormalMode};
+use chemgdb::mol2::parse_mol2;
+use chemgdb::periodic_table;
+use chemgdb::parser::{self, parse_xyz_frames_with_progress, parse_xyz_with_options, FrameReader, ParseOptions};
+use chemgdb::selection::Selection;
+use chemgdb::serve::FrameServer;
+use chemgdb::writer::write_xyz;
+
+mod camera;
+use camera::{calculate_camera_position, drag_rotation, pan_offset, pan_rate, step_rotation, turntable_rotation, zoom_distance};
+
+mod palette;
+use palette::{load_palette, Palette};
+
+mod scene;
+use scene::{load_scene, save_scene, SceneCamera, SceneConfig};
+
+mod stereo;
+use stereo::{StereoComposite, StereoEye, StereoSettings};
 
 /// Atom data for rendering
 #[derive(Debug, Clone)]
 struct Atom {
   element: String,
   position: Vec3,
+  /// Held fixed during optimization; drawn desaturated
+  frozen: bool,
 }
 
-/// Resource holding molecular data
-#[derive(Resource)]
+/// Molecular data for one loaded structure
 struct Molecule {
     atoms: Vec<Atom>,
+    /// Parsed structure the render atoms were built from, used for selections
+    structure: parser::Molecule,
+    /// Energy of each frame of the source, from `energy=` comment fields
+    frame_energies: Vec<Option<f64>>,
+    /// Index into `frame_energies` of the frame shown
+    frame: usize,
+}
+
+/// Resource holding every loaded molecule and its placement in the scene
+#[derive(Resource)]
+struct Molecules {
+    molecules: Vec<Molecule>,
+    /// Translation applied to each molecule's root entity
+    offsets: Vec<Vec3>,
+    /// Index of the molecule the camera targets
+    focused: usize,
 }
 
-/// Marker component for the molecule parent entity
+/// Parent entity of the molecule at the given index in `Molecules`
 #[derive(Component)]
-struct MoleculeRoot;
+struct MoleculeRoot(usize);
 
-/// Camera orbit controller (VMD-style)
+/// Index of an atom entity within `Molecules`
+#[derive(Component)]
+struct AtomIndex {
+    molecule: usize,
+    atom: usize,
+}
+
+/// Atom color from the element palette and any style override
+#[derive(Component)]
+struct AtomBaseColor(Color);
+
+/// How atoms are colored
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+enum ColorScheme {
+    /// Palette/CPK element colors and style overrides
+    #[default]
+    Element,
+    /// Every atom in one flat color, to show shape rather than composition
+    Uniform(Color),
+    /// Element colors, tinted for atoms behind the cutting plane
+    CrossSection,
+    /// Red for the fewest neighbors within the coordination cutoff through
+    /// blue for the most, per molecule
+    Coordination,
+    /// Gray for atoms of the first molecule that match the --overlay
+    /// reference through red for the one that moved most
+    Displacement,
+}
+
+/// Structure loaded with --overlay that the first molecule's displacements
+/// are measured from
+#[derive(Resource, Default)]
+struct ReferenceStructure(Option<parser::Molecule>);
+
+/// Color of atoms that did not move under the displacement scheme
+const STILL_COLOR: Color = Color::srgb(0.6, 0.6, 0.6);
+
+/// Color of the atom that moved most under the displacement scheme
+const MOVED_COLOR: Color = Color::srgb(1.0, 0.1, 0.1);
+
+/// Plane splitting the scene for the cross-section color scheme
 #[derive(Resource)]
-struct CameraController {
-    distance: f32,
-    rotation: Quat,
-    target: Vec3,
-    rotate_sensitivity: f32,
-    pan_speed: f32,
-    zoom_speed: f32,
+struct CuttingPlane {
+    point: Vec3,
+    /// Unit normal pointing to the untinted side
+    normal: Vec3,
 }
 
-impl Default for CameraController {
+impl Default for CuttingPlane {
     fn default() -> Self {
         Self {
-            distance: 15.0,
-            rotation: Quat::from_rotation_x(-0.3),
-            target: Vec3::ZERO,
-            rotate_sensitivity: 0.005,
-            pan_speed: 5.0,
-            zoom_speed: 1.0,
+            point: Vec3::ZERO,
+            normal: Vec3::X,
         }
     }
 }
 
-fn main() {
-  let molecule = load_xyz_file("water_dimer.xyz").expect("Failed to parse XYZ file");
+/// Fraction of the saturation kept for frozen atoms
+const FROZEN_SATURATION: f32 = 0.2;
 
+/// Washed-out version of `color` marking a frozen atom
+fn frozen_color(color: Color) -> Color {
+    let hsla = Hsla::from(color);
+    hsla.with_saturation(hsla.saturation * FROZEN_SATURATION).into()
+}
 
-    // Parse command line arguments to find -mdi option
-    let args: Vec<String> = std::env::args().collect();
-    let mut mdi_options: Option<String> = None;
+/// Color mixed into atoms behind the cutting plane
+const CROSS_SECTION_TINT: Color = Color::srgb(0.2, 0.4, 1.0);
 
-    let mut i = 1;
-    while i < args.len() {
-        if args[i] == "--mdi" && i + 1 < args.len() {
-            mdi_options = Some(args[i + 1].clone());
-            i += 2;
-        } else {
-            i += 1;
+/// Distance the cutting plane moves per key press
+const CUTTING_PLANE_STEP: f32 = 0.5;
+
+/// Active color scheme and the parameters of the non-element schemes
+#[derive(Resource)]
+struct ColorSettings {
+    scheme: ColorScheme,
+    uniform_color: Color,
+    /// Neighbor distance for the coordination scheme, in Angstrom
+    coordination_cutoff: f32,
+}
+
+impl Default for ColorSettings {
+    fn default() -> Self {
+        Self {
+            scheme: ColorScheme::Element,
+            uniform_color: Color::WHITE,
+            coordination_cutoff: 3.0,
         }
     }
+}
 
-    let options = mdi_options.expect("Must provide -mdi option");
-    //let c_options = CString::new(options).expect("Invalid options string");
+/// Change in the coordination cutoff per key press, in Angstrom
+const COORDINATION_CUTOFF_STEP: f32 = 0.1;
 
-    /*
-    let ret = unsafe { Mdi::init_with_options(c_options.as_ptr()) };
-    if ret != 0 {
-        panic!("MDI_Init_with_options failed");
+/// Table the drawn atom radii come from
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+enum RadiusSource {
+    /// Scaled van der Waals radii, for a space-filling look
+    #[default]
+    VanDerWaals,
+    /// Scaled covalent radii, for a ball-and-stick look
+    Covalent,
+    /// Every atom the same size
+    Uniform(f32),
+}
+
+/// Active radius source and the radius used for the uniform source
+#[derive(Resource)]
+struct RadiusSettings {
+    source: RadiusSource,
+    uniform_radius: f32,
+}
+
+impl Default for RadiusSettings {
+    fn default() -> Self {
+        Self {
+            source: RadiusSource::VanDerWaals,
+            uniform_radius: 0.5,
+        }
     }
-    */
-    Mdi::init_with_options(&options);
+}
 
+/// File the current scene is written to when saved with F2
+const SCENE_FILE: &str = "scene.json";
 
-    App::new()
-        .add_plugins(DefaultPlugins)
-        .insert_resource(molecule)
-        .insert_resource(CameraController::default())
-        .insert_resource(ClearColor(Color::srgb(0.1, 0.1, 0.15)))
-        .add_systems(Startup, setup)
-        .add_systems(Update, (camera_rotation, camera_pan, camera_zoom, update_camera))
-        .run();
+/// File the targeted molecule is written to when saved with F4
+const EDITED_FILE: &str = "edited.xyz";
+
+/// Files the scene was loaded from, recorded when saving it
+#[derive(Resource, Default)]
+struct SceneSources {
+    inputs: Vec<String>,
+    palette: Option<String>,
 }
 
-fn load_xyz_file(path: &str) -> Result<Molecule, Box<dyn std::error::Error>> {
-  let file = File::open(path)?;
-  let parsed = parse_xyz(file)?;
+/// Parts of a loaded scene applied when the scene entities are spawned
+#[derive(Resource, Default)]
+struct StartupView {
+    target: Option<Vec3>,
+    ground_plane: bool,
+    scale_bar: bool,
+}
 
-  let atoms = parsed
-    .atoms
-    .into_iter()
-    .map(|a| Atom {
-      element: a.element,
-      position: Vec3::new(a.x as f32, a.y as f32, a.z as f32),
-    })
-    .collect();
+/// Gap between neighbouring molecules when several are loaded
+const MOLECULE_SPACING: f32 = 2.0;
+
+/// Marker component for the optional shadow-catching ground plane
+#[derive(Component)]
+struct GroundPlane;
+
+/// On-screen scale bar overlay, hidden until toggled on
+#[derive(Component)]
+struct ScaleBar;
+
+/// Horizontal line of the scale bar, sized to a round length in Angstrom
+#[derive(Component)]
+struct ScaleBarLine;
+
+/// Length label of the scale bar
+#[derive(Component)]
+struct ScaleBarLabel;
+
+/// On-screen dipole magnitude of the targeted molecule; the dipole arrows
+/// are drawn while it is visible
+#[derive(Component)]
+struct DipoleLabel;
+
+/// Color of the dipole arrows
+const DIPOLE_COLOR: Color = Color::srgb(1.0, 0.8, 0.1);
+
+/// On-screen list of the targeted molecule's elements and their colors,
+/// hidden until toggled on
+#[derive(Component)]
+struct ElementLegend;
+
+/// Plot of energy against frame for the targeted molecule, shown while at
+/// least two of its frames carry an energy
+#[derive(Component)]
+struct EnergyPlot;
+
+/// Size of the energy plot's drawing area, in pixels
+const ENERGY_PLOT_SIZE: Vec2 = Vec2::new(240.0, 100.0);
+
+/// Color of the energy curve
+const ENERGY_LINE_COLOR: Color = Color::srgb(0.6, 0.8, 1.0);
+
+/// Color of the marker at the frame shown
+const ENERGY_MARKER_COLOR: Color = Color::srgb(1.0, 0.4, 0.2);
 
-  Ok(Molecule { atoms })
+/// Full-window overlay holding the axis tick labels; the axes are drawn
+/// while it is visible
+#[derive(Component)]
+struct AxisTicks;
+
+/// Distance label at one axis tick
+#[derive(Component)]
+struct AxisTickLabel;
+
+/// Parts of an axis tick label updated as the camera moves
+type AxisTickLabelData = (&'static mut Text, &'static mut Node, &'static mut Visibility);
+
+/// Color of the measurement axes and their labels
+const AXIS_TICK_COLOR: Color = Color::srgb(0.85, 0.85, 0.85);
+
+/// Rough number of ticks on each side of the centroid along the longest axis
+const AXIS_TICKS_PER_SIDE: f32 = 4.0;
+
+/// Distance the axes reach past the farthest atom center
+const AXIS_MARGIN: f32 = 1.0;
+
+/// Longest the scale bar line is drawn, in logical pixels
+const SCALE_BAR_MAX_WIDTH: f32 = 150.0;
+
+/// Gap between the lowest atom surface and the ground plane
+const GROUND_PLANE_OFFSET: f32 = 0.5;
+
+/// Screen-space ambient occlusion settings for the camera
+#[derive(Resource)]
+struct AmbientOcclusionSettings {
+    enabled: bool,
+    quality: ScreenSpaceAmbientOcclusionQualityLevel,
+    /// Estimated object thickness; larger values darken crevices more strongly
+    strength: f32,
 }
 
-/// CPK coloring scheme for atoms
-fn get_atom_color(element: &str) -> Color {
-    match element.to_uppercase().as_str() {
-        "H" => Color::srgb(1.0, 1.0, 1.0),        // White
-        "C" => Color::srgb(0.3, 0.3, 0.3),        // Dark gray
-        "N" => Color::srgb(0.2, 0.2, 1.0),        // Blue
-        "O" => Color::srgb(1.0, 0.2, 0.2),        // Red
-        "S" => Color::srgb(1.0, 1.0, 0.2),        // Yellow
-        "P" => Color::srgb(1.0, 0.5, 0.0),        // Orange
-        "F" | "CL" => Color::srgb(0.2, 1.0, 0.2), // Green
-        "BR" => Color::srgb(0.6, 0.1, 0.1),       // Dark red
-        "I" => Color::srgb(0.4, 0.0, 0.7),        // Purple
-        "FE" => Color::srgb(0.9, 0.5, 0.0),       // Orange
-        "CA" => Color::srgb(0.2, 0.8, 0.2),       // Green
-        "MG" => Color::srgb(0.0, 0.5, 0.0),       // Dark green
-        "ZN" => Color::srgb(0.5, 0.5, 0.6),       // Slate gray
-        _ => Color::srgb(1.0, 0.5, 1.0),          // Pink for unknown
+impl Default for AmbientOcclusionSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            quality: ScreenSpaceAmbientOcclusionQualityLevel::Medium,
+            strength: 0.25,
+        }
     }
 }
 
-/// Van der Waals radii (scaled for visualization)
-fn get_atom_radius(element: &str) -> f32 {
-    let scale = 0.4;
-    let radius = match element.to_uppercase().as_str() {
-        "H" => 1.20,
-        "C" => 1.70,
-        "N" => 1.55,
-        "O" => 1.52,
-        "S" => 1.80,
-        "P" => 1.80,
-        "F" => 1.47,
-        "CL" => 1.75,
-        "BR" => 1.85,
-        "I" => 1.98,
-        "FE" => 2.00,
-        "CA" => 2.31,
-        "MG" => 1.73,
-        "ZN" => 1.39,
-        _ => 1.50,
-    };
-    radius * scale
+/// Multisample antialiasing level for the scene cameras
+///
+/// SSAO cannot run with MSAA, so the level only takes effect while ambient
+/// occlusion is off.
+#[derive(Resource)]
+struct AntialiasSettings {
+    msaa: Msaa,
 }
 
-fn setup(
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-    molecule: Res<Molecule>,
-    mut controller: ResMut<CameraController>,
-) {
-    // Calculate molecule center for initial camera target
-    let center = if !molecule.atoms.is_empty() {
-        molecule
-            .atoms
-            .iter()
-            .map(|a| a.position)
-            .reduce(|a, b| a + b)
-            .unwrap()
-            / molecule.atoms.len() as f32
-    } else {
-        Vec3::ZERO
-    };
+impl Default for AntialiasSettings {
+    fn default() -> Self {
+        Self { msaa: Msaa::Sample4 }
+    }
+}
 
-    controller.target = center;
+/// Whether the main camera blends transparent atoms per pixel instead of
+/// sorting whole meshes, so overlapping transparent spheres render correctly
+///
+/// Order-independent transparency needs MSAA off and applies to the main
+/// camera only; the stereo eye cameras keep sorted blending.
+#[derive(Resource, Default)]
+struct TransparencySettings {
+    order_independent: bool,
+}
 
-    // Create molecule parent entity
-    let molecule_root = commands
-        .spawn((
-            Transform::default(),
-            Visibility::default(),
-            MoleculeRoot,
-        ))
-        .id();
+/// Whether the point light casts shadows; off is much faster for large structures
+#[derive(Resource)]
+struct ShadowSettings {
+    enabled: bool,
+}
 
-    // Create atoms as spheres
-    for atom in &molecule.atoms {
-        let color = get_atom_color(&atom.element);
-        let radius = get_atom_radius(&atom.element);
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
 
-        let atom_entity = commands
-            .spawn((
-                Mesh3d(meshes.add(Sphere::new(radius))),
-                MeshMaterial3d(materials.add(StandardMaterial {
-                    base_color: color,
-                    perceptual_roughness: 0.5,
-                    metallic: 0.1,
-                    ..default()
-                })),
-                Transform::from_translation(atom.position),
-            ))
-            .id();
+/// Whether left-drag orbits the point light around the camera target
+/// instead of rotating the view
+#[derive(Resource, Default)]
+struct LightDrag {
+    enabled: bool,
+}
+
+/// Turntable auto-rotation about the vertical axis, for demos
+#[derive(Resource)]
+struct Turntable {
+    enabled: bool,
+    /// Spin rate in radians per second
+    rate: f32,
+    /// Seconds since the last mouse or keyboard input; spinning pauses
+    /// until this reaches `TURNTABLE_RESUME_DELAY`
+    idle: f32,
+}
 
-        commands.entity(molecule_root).add_child(atom_entity);
+impl Default for Turntable {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            rate: 20.0_f32.to_radians(),
+            idle: TURNTABLE_RESUME_DELAY,
+        }
     }
+}
 
-    // Point light
-    commands.spawn((
-        PointLight {
-            intensity: 2_000_000.0,
-            shadows_enabled: true,
-            ..default()
-        },
-        Transform::from_xyz(10.0, 10.0, 10.0),
-    ));
+/// Seconds without input before the turntable resumes spinning
+const TURNTABLE_RESUME_DELAY: f32 = 2.0;
 
-    // Ambient light
-    commands.spawn(AmbientLight {
-        color: Color::WHITE,
-        brightness: 200.0,
-        ..default()
-    });
+/// Full turn of the view captured to numbered PNGs by `--render-turntable`
+#[derive(Resource)]
+struct TurntableCapture {
+    dir: PathBuf,
+    frames: usize,
+    /// Frames whose screenshot was requested
+    requested: usize,
+    /// Frames written to disk
+    saved: usize,
+    /// View rotation of the first frame
+    start: Quat,
+}
 
-    // Camera
-    let camera_pos = calculate_camera_position(&controller, controller.target);
-    commands.spawn((
-        Camera3d::default(),
-        Transform::from_translation(camera_pos).with_rotation(controller.rotation),
-    ));
+/// Frames rendered before the first turntable capture, giving shaders time
+/// to compile so early images aren't missing atoms
+const TURNTABLE_WARMUP_FRAMES: u32 = 30;
 
-    println!("Molecular Viewer Controls:");
-    println!("  Left mouse drag: Rotate view");
-    println!("  Scroll wheel: Zoom in/out");
-    println!("  Arrow keys: Pan view");
-    println!("\nLoaded {} atoms", molecule.atoms.len());
+/// Frames captured by `--render-turntable` unless `--frames` says otherwise
+const DEFAULT_TURNTABLE_FRAMES: usize = 120;
+
+/// Whether hydrogen atoms are hidden for a heavy-atom view
+#[derive(Resource, Default)]
+struct HydrogenSettings {
+    hidden: bool,
 }
 
-fn calculate_camera_position(controller: &CameraController, target: Vec3) -> Vec3 {
-    let direction = controller.rotation * Vec3::Z;
-    target + direction * controller.distance
+/// Atoms picked by box selection, as (molecule, atom) indices
+#[derive(Resource, Default)]
+struct Selected(BTreeSet<(usize, usize)>);
+
+/// Query from --box-filter that atoms must also match to be box selected,
+/// such as `element C` to pick only the carbons inside the box
+#[derive(Resource, Default)]
+struct BoxFilter(Option<Selection>);
+
+/// Rectangle drawn while shift-dragging a box selection, visible only
+/// during the drag
+#[derive(Component)]
+struct SelectionBox {
+    /// Cursor position where the drag started
+    start: Vec2,
 }
 
-fn camera_rotation(
-    mouse_button: Res<ButtonInput<MouseButton>>,
-    mouse_motion: Res<AccumulatedMouseMotion>,
-    mut controller: ResMut<CameraController>,
-) {
-    // VMD-style: left mouse button for rotation
-    if mouse_button.pressed(MouseButton::Left) {
-        let delta = mouse_motion.delta;
+/// Glow added to selected atoms
+const SELECTION_GLOW: LinearRgba = LinearRgba::rgb(0.6, 0.6, 0.1);
 
-        // Rotate around camera's local Y axis for horizontal movement
-        let up = controller.rotation * Vec3::Y;
-        let yaw = Quat::from_axis_angle(up, -delta.x * controller.rotate_sensitivity);
+/// Distance, angle, or dihedral between the selected atoms, shown while
+/// two to four atoms of one molecule are selected
+#[derive(Component)]
+struct MeasurementLabel;
 
-        // Rotate around camera's local X axis for vertical movement
-        let right = controller.rotation * Vec3::X;
-        let pitch = Quat::from_axis_angle(right, -delta.y * controller.rotate_sensitivity);
+/// Cartoon-style atom outline settings
+#[derive(Resource)]
+struct OutlineSettings {
+    enabled: bool,
+    /// Outline width in scene units, added to each atom's radius
+    thickness: f32,
+}
 
-        controller.rotation = (pitch * yaw * controller.rotation).normalize();
+impl Default for OutlineSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            thickness: 0.05,
+        }
     }
 }
 
-fn camera_pan(
-    keyboard: Res<ButtonInput<KeyCode>>,
-    time: Res<Time>,
-    mut controller: ResMut<CameraController>,
-) {
-    let mut pan = Vec3::ZERO;
+/// Inverted-hull outline drawn around an atom of the given radius
+#[derive(Component)]
+struct AtomOutline {
+    radius: f32,
+}
 
-    if keyboard.pressed(KeyCode::ArrowLeft) || keyboard.pressed(KeyCode::KeyA) {
-        pan.x -= 1.0;
+/// Most undoable edits kept before the oldest is forgotten
+const HISTORY_DEPTH: usize = 50;
+
+/// State of one molecule before or after an edit
+struct Snapshot {
+    molecule: usize,
+    structure: parser::Molecule,
+    /// Normal modes, which are edited along with the first molecule
+    modes: Option<Vec<NormalMode>>,
+}
+
+/// Undo and redo stacks for interactive edits
+///
+/// Mirroring with X and deleting atoms with Delete push an entry, which
+/// also clears the redo stack. Frames streamed from stdin replace the molecule outright
+/// and clear both stacks.
+#[derive(Resource, Default)]
+struct EditHistory {
+    undo: VecDeque<Snapshot>,
+    redo: Vec<Snapshot>,
+}
+
+impl EditHistory {
+    /// Record the state of `index` before an edit
+    fn push(&mut self, index: usize, molecules: &Molecules, animation: &ModeAnimation) {
+        if self.undo.len() == HISTORY_DEPTH {
+            self.undo.pop_front();
+        }
+        self.undo.push_back(Snapshot::capture(index, molecules, animation));
+        self.redo.clear();
     }
-    if keyboard.pressed(KeyCode::ArrowRight) || keyboard.pressed(KeyCode::KeyD) {
-        pan.x += 1.0;
+}
+
+impl Snapshot {
+    fn capture(index: usize, molecules: &Molecules, animation: &ModeAnimation) -> Self {
+        Self {
+            molecule: index,
+            structure: molecules.molecules[index].structure.clone(),
+            modes: (index == 0).then(|| animation.modes.clone()),
+        }
     }
-    if keyboard.pressed(KeyCode::ArrowUp) || keyboard.pressed(KeyCode::KeyW) {
-        pan.y += 1.0;
+}
+
+/// Normal-mode vibration animation applied to the first loaded molecule
+#[derive(Resource)]
+struct ModeAnimation {
+    modes: Vec<NormalMode>,
+    /// Mode being animated, or `None` to hold atoms at their equilibrium positions
+    selected: Option<usize>,
+    /// Scale applied to the displacement vectors at the peak of the oscillation
+    amplitude: f32,
+    /// Oscillations per second
+    speed: f32,
+}
+
+impl Default for ModeAnimation {
+    fn default() -> Self {
+        Self {
+            modes: Vec::new(),
+            selected: None,
+            amplitude: 1.0,
+            speed: 1.0,
+        }
     }
-    if keyboard.pressed(KeyCode::ArrowDown) || keyboard.pressed(KeyCode::KeyS) {
-        pan.y -= 1.0;
+}
+
+/// How input files are read before display
+#[derive(Debug, Default)]
+struct LoadOptions {
+    verbose: bool,
+    /// Read and write a binary sidecar of the parsed XYZ frames; off unless
+    /// --cache is given, so nothing is written beside the data uninvited
+    cache: bool,
+    /// Frame to show from a multi-frame file; negative values count from the end
+    frame: Option<i64>,
+    /// Elements to keep, dropping all other atoms
+    elements: Option<Vec<String>>,
+    /// Atoms to remove
+    delete: Option<Selection>,
+    /// Tolerance for removing duplicate atoms, if enabled
+    dedup: Option<f64>,
+    /// Axis to negate, for data written in the other handedness
+    mirror: Option<Axis>,
+    /// Unit of the input coordinates, converted to Angstrom on load
+    units: Option<LengthUnit>,
+    /// Options passed to the XYZ parser
+    parse: ParseOptions,
+}
+
+/// Axis the X key mirrors the targeted molecule along
+#[derive(Resource)]
+struct MirrorAxis(Axis);
+
+/// Mode named by the first argument, such as `chemgdb info water.xyz`;
+/// without one the viewer opens
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Subcommand {
+    View,
+    /// Print structure summaries, like --info
+    Info,
+    /// Read one structure file and write it in another format
+    Convert,
+}
+
+/// Frames between progress updates when loading a trajectory with --verbose
+const PROGRESS_INTERVAL: usize = 100;
+
+/// Most atoms the viewer renders from one structure or frame unless
+/// --max-atoms raises it; every atom is two entities with their own material
+const DEFAULT_MAX_ATOMS: usize = 100_000;
+
+/// Display style for a selection of atoms; unset fields keep the default look
+#[derive(Debug, Clone, Default, PartialEq)]
+struct AtomStyle {
+    color: Option<Color>,
+    /// Alpha from 0.0 (invisible) to 1.0 (opaque)
+    opacity: Option<f32>,
+    /// Factor applied to the atom radius
+    radius_scale: Option<f32>,
+}
+
+impl AtomStyle {
+    /// Combine with a later style, whose set fields take precedence
+    fn merge(&self, other: &AtomStyle) -> AtomStyle {
+        AtomStyle {
+            color: other.color.or(self.color),
+            opacity: other.opacity.or(self.opacity),
+            radius_scale: other.radius_scale.or(self.radius_scale),
+        }
     }
+}
+
+/// Styles applied to atom selections in every molecule, later entries winning
+#[derive(Resource, Default)]
+struct StyleOverride {
+    entries: Vec<(Selection, AtomStyle)>,
+}
 
-    if pan != Vec3::ZERO {
-        // Transform pan direction based on camera orientation
-        let right = controller.rotation * Vec3::X;
-        let up = controller.rotation * Vec3::Y;
-        let pan_delta = (right * pan.x + up * pan.y) * controller.pan_speed * time.delta_secs();
-        controller.target += pan_delta;
+impl StyleOverride {
+    /// Effective style of each atom in the molecule
+    fn resolve(&self, molecule: &parser::Molecule) -> Vec<AtomStyle> {
+        let mut styles = vec![AtomStyle::default(); molecule.atoms.len()];
+        for (selection, style) in &self.entries {
+            for index in selection.evaluate(molecule) {
+                styles[index] = styles[index].merge(style);
+            }
+        }
+        styles
     }
 }
 
-fn camera_zoom(
-    scroll: Res<AccumulatedMouseScroll>,
-    mut controller: ResMut<CameraController>,
-) {
-    controller.distance -= scroll.delta.y * controller.zoom_speed;
-    controller.distance = controller.distance.clamp(2.0, 100.0);
+/// Shared mesh and material for the inverted-hull atom outlines
+#[derive(Resource, Clone)]
+struct OutlineAssets {
+    mesh: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+}
+
+/// Frames read from stdin by a background thread in `--stream` mode
+#[derive(Resource)]
+struct FrameStream(Mutex<Receiver<parser::Molecule>>);
+
+/// Cameras that view the molecule, excluding the stereo compositing camera
+type SceneCameraFilter = (With<Camera3d>, Without<StereoComposite>);
+
+/// Camera orbit controller (VMD-style)
+#[derive(Resource)]
+struct CameraController {
+    distance: f32,
+    rotation: Quat,
+    target: Vec3,
+    rotate_sensitivity: f32,
+    /// Drag the molecule itself (PyMOL/Chimera style) instead of orbiting
+    /// the camera (VMD style); flips the sign of mouse yaw and pitch
+    invert_rotation: bool,
+    /// Angle in radians applied per key press for keyboard rotation
+    rotate_step: f32,
+    /// Continuous pan speed at the reference zoom distance
+    pan_speed: f32,
+    /// Distance panned per key press; keys pan continuously when `None`
+    pan_step: Option<f32>,
+    zoom_speed: f32,
+    /// Vertical field of view in radians
+    fov: f32,
+    /// Distances of the near and far clipping planes from the camera
+    near: f32,
+    far: f32,
+}
+
+impl CameraController {
+    /// Perspective projection with the configured field of view and clip planes
+    fn projection(&self) -> Projection {
+        Projection::Perspective(PerspectiveProjection {
+            fov: self.fov,
+            near: self.near,
+            far: self.far,
+            ..default()
+        })
+    }
+}
+
+impl Default for CameraController {
+    fn default() -> Self {
+        Self {
+            distance: 15.0,
+            rotation: Quat::from_rotation_x(-0.3),
+            target: Vec3::ZERO,
+            rotate_sensitivity: 0.005,
+            invert_rotation: false,
+            rotate_step: 15.0_f32.to_radians(),
+            pan_speed: 5.0,
+            pan_step: None,
+            zoom_speed: 1.0,
+            fov: PerspectiveProjection::default().fov,
+            near: PerspectiveProjection::default().near,
+            far: PerspectiveProjection::default().far,
+        }
+    }
+}
+
+fn main() {
+    // Parse command line arguments to find -mdi option
+    let args: Vec<String> = std::env::args().collect();
+    let mut mdi_options: Option<String> = None;
+    let mut ao_settings = AmbientOcclusionSettings::default();
+    let mut shadow_settings = ShadowSettings::default();
+    let mut antialias_settings = AntialiasSettings::default();
+    let mut transparency_settings = TransparencySettings::default();
+    let mut controller = CameraController::default();
+    let mut turntable = Turntable::default();
+    let mut hydrogen_settings = HydrogenSettings::default();
+    let mut box_filter = BoxFilter::default();
+    let mut turntable_dir: Option<PathBuf> = None;
+    let mut turntable_frames = DEFAULT_TURNTABLE_FRAMES;
+    let mut stereo_settings = StereoSettings::default();
+    let mut palette = Palette::default();
+    let mut mode_animation = ModeAnimation::default();
+    let mut style_override = StyleOverride::default();
+    let mut color_settings = ColorSettings::default();
+    let mut radius_settings = RadiusSettings::default();
+    let mut outline_settings = OutlineSettings::default();
+    let mut startup_view = StartupView::default();
+    let mut palette_path: Option<String> = None;
+    let mut load_options = LoadOptions::default();
+    let mut input_paths: Vec<String> = Vec::new();
+    let mut contacts: Option<(Vec<usize>, Vec<usize>)> = None;
+    let mut stream_stdin = false;
+    let mut json_errors = false;
+    let mut show_info = false;
+    let mut serve_port: Option<u16> = None;
+    // Only this machine can connect unless --serve-addr says otherwise
+    let mut serve_addr = IpAddr::from([127, 0, 0, 1]);
+    let mut contact_cutoff = 3.5;
+    let mut max_atoms = DEFAULT_MAX_ATOMS;
+    let mut overlay_path: Option<String> = None;
+
+    // A scene gives the starting settings, which other flags then override
+    let mut scene = SceneConfig::default();
+    if let Some(pos) = args.iter().position(|a| a == "--scene") {
+        let path = args.get(pos + 1).unwrap_or_else(|| exit_with_usage_error("Invalid --scene (expected a scene file)"));
+        let (loaded, unknown) = load_scene(path).unwrap_or_else(|e| panic!("Failed to load scene {}: {}", path, e));
+        for key in unknown {
+            eprintln!("Ignoring unknown scene setting '{}' in {}", key, path);
+        }
+        scene = loaded;
+    }
+    if let Some(path) = &scene.palette {
+        palette = load_palette(path).expect("Failed to load palette file");
+        palette_path = Some(path.clone());
+    }
+    // parse_scene has already checked these values
+    if let Some(hex) = &scene.uniform_color {
+        color_settings.uniform_color = Srgba::hex(hex).expect("uniform_color checked by parse_scene").into();
+    }
+    if let Some(name) = &scene.color_scheme {
+        color_settings.scheme = parse_color_scheme(name).expect("color_scheme checked by parse_scene");
+    }
+    if let Some(name) = &scene.radii {
+        radius_settings.source = parse_radius_source(name).expect("radii checked by parse_scene");
+    }
+    if let Some(radius) = scene.uniform_radius {
+        radius_settings.uniform_radius = radius;
+    }
+    if let Some(camera) = &scene.camera {
+        controller.rotation = Quat::from_array(camera.rotation).normalize();
+        controller.distance = camera.distance.clamp(camera::MIN_DISTANCE, camera::MAX_DISTANCE);
+        startup_view.target = Some(Vec3::from_array(camera.target));
+    }
+    ao_settings.enabled = scene.ambient_occlusion.unwrap_or(ao_settings.enabled);
+    ao_settings.strength = scene.ambient_occlusion_strength.unwrap_or(ao_settings.strength);
+    outline_settings.enabled = scene.outlines.unwrap_or(outline_settings.enabled);
+    outline_settings.thickness = scene.outline_thickness.unwrap_or(outline_settings.thickness);
+    shadow_settings.enabled = scene.shadows.unwrap_or(shadow_settings.enabled);
+    stereo_settings.enabled = scene.stereo.unwrap_or(stereo_settings.enabled);
+    startup_view.ground_plane = scene.ground_plane.unwrap_or(false);
+    startup_view.scale_bar = scene.scale_bar.unwrap_or(false);
+
+    // Files may follow a subcommand directly instead of through --input
+    let subcommand = args.get(1).and_then(|name| parse_subcommand(name));
+    let mut positional: Vec<String> = Vec::new();
+    if let Some(first) = args.get(1)
+        && subcommand.is_none()
+        && !first.starts_with('-')
+    {
+        exit_with_usage_error(&format!("unknown subcommand '{}' (expected view, info, or convert)", first));
+    }
+
+    let mut i = if subcommand.is_some() { 2 } else { 1 };
+    while i < args.len() {
+        if args[i] == "--mdi" && i + 1 < args.len() {
+            mdi_options = Some(args[i + 1].clone());
+            i += 2;
+        } else if args[i] == "--ssao" && i + 1 < args.len() {
+            ao_settings.quality = parse_ssao_quality(&args[i + 1])
+                .unwrap_or_else(|| exit_with_usage_error("Invalid --ssao quality (expected low, medium, high, or ultra)"));
+            ao_settings.enabled = true;
+            i += 2;
+        } else if args[i] == "--ssao-strength" && i + 1 < args.len() {
+            ao_settings.strength = args[i + 1]
+                .parse::<f32>()
+                .ok()
+                .filter(|v| v.is_finite() && *v > 0.0)
+                .unwrap_or_else(|| exit_with_usage_error("Invalid --ssao-strength (expected a positive number)"));
+            ao_settings.enabled = true;
+            i += 2;
+        } else if args[i] == "--aa" && i + 1 < args.len() {
+            antialias_settings.msaa = parse_msaa(&args[i + 1]).unwrap_or_else(|| exit_with_usage_error("Invalid --aa (expected off, 2, 4, or 8)"));
+            i += 2;
+        } else if args[i] == "--no-hydrogens" {
+            hydrogen_settings.hidden = true;
+            i += 1;
+        } else if args[i] == "--spin" {
+            turntable.enabled = true;
+            i += 1;
+        } else if args[i] == "--spin-rate" && i + 1 < args.len() {
+            turntable.rate = args[i + 1]
+                .parse::<f32>()
+                .ok()
+                .filter(|v| v.is_finite())
+                .unwrap_or_else(|| exit_with_usage_error("Invalid --spin-rate (expected degrees per second)"))
+                .to_radians();
+            i += 2;
+        } else if args[i] == "--render-turntable" && i + 1 < args.len() {
+            turntable_dir = Some(PathBuf::from(&args[i + 1]));
+            i += 2;
+        } else if args[i] == "--frames" && i + 1 < args.len() {
+            turntable_frames = args[i + 1]
+                .parse::<usize>()
+                .ok()
+                .filter(|n| *n > 0)
+                .unwrap_or_else(|| exit_with_usage_error("Invalid --frames (expected a positive number of frames)"));
+            i += 2;
+        } else if args[i] == "--pan-step" && i + 1 < args.len() {
+            controller.pan_step = Some(
+                args[i + 1]
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|v| v.is_finite() && *v > 0.0)
+                    .unwrap_or_else(|| exit_with_usage_error("Invalid --pan-step (expected a positive distance in Angstrom)")),
+            );
+            i += 2;
+        } else if args[i] == "--invert-rotation" {
+            controller.invert_rotation = true;
+            i += 1;
+        } else if args[i] == "--fov" && i + 1 < args.len() {
+            controller.fov = args[i + 1]
+                .parse::<f32>()
+                .ok()
+                .filter(|v| *v > 0.0 && *v < 180.0)
+                .unwrap_or_else(|| exit_with_usage_error("Invalid --fov (expected an angle in degrees between 0 and 180)"))
+                .to_radians();
+            i += 2;
+        } else if args[i] == "--near" && i + 1 < args.len() {
+            controller.near = args[i + 1]
+                .parse::<f32>()
+                .ok()
+                .filter(|v| v.is_finite() && *v > 0.0)
+                .unwrap_or_else(|| exit_with_usage_error("Invalid --near (expected a positive distance)"));
+            i += 2;
+        } else if args[i] == "--far" && i + 1 < args.len() {
+            controller.far = args[i + 1]
+                .parse::<f32>()
+                .ok()
+                .filter(|v| v.is_finite() && *v > 0.0)
+                .unwrap_or_else(|| exit_with_usage_error("Invalid --far (expected a positive distance)"));
+            i += 2;
+        } else if args[i] == "--oit" {
+            transparency_settings.order_independent = true;
+            i += 1;
+        } else if args[i] == "--no-shadows" {
+            shadow_settings.enabled = false;
+            i += 1;
+        } else if args[i] == "--rotation" && i + 1 < args.len() {
+            controller.rotation = parse_rotation(&args[i + 1])
+                .unwrap_or_else(|| exit_with_usage_error("Invalid --rotation (expected X,Y,Z angles in degrees)"));
+            i += 2;
+        } else if args[i] == "--palette" && i + 1 < args.len() {
+            palette = load_palette(&args[i + 1]).expect("Failed to load palette file");
+            palette_path = Some(args[i + 1].clone());
+            i += 2;
+        } else if args[i] == "--scene" && i + 1 < args.len() {
+            // Already applied before the other flags
+            i += 2;
+        } else if args[i] == "--modes" && i + 1 < args.len() {
+            mode_animation.modes = load_modes(&args[i + 1]).expect("Failed to load modes file");
+            mode_animation.selected = (!mode_animation.modes.is_empty()).then_some(0);
+            i += 2;
+        } else if args[i] == "--style" && i + 1 < args.len() {
+            let entry = parse_style(&args[i + 1])
+                .unwrap_or_else(|e| exit_with_usage_error(&format!("Invalid --style (expected 'SELECTION: color=#rrggbb opacity=A scale=S'): {}", e)));
+            style_override.entries.push(entry);
+            i += 2;
+        } else if args[i] == "--coordination-cutoff" && i + 1 < args.len() {
+            color_settings.coordination_cutoff = args[i + 1]
+                .parse::<f32>()
+                .ok()
+                .filter(|c| c.is_finite() && *c > 0.0)
+                .unwrap_or_else(|| exit_with_usage_error("Invalid --coordination-cutoff (expected a positive distance in Angstrom)"));
+            i += 2;
+        } else if args[i] == "--uniform-color" && i + 1 < args.len() {
+            color_settings.uniform_color = Srgba::hex(&args[i + 1])
+                .unwrap_or_else(|_| exit_with_usage_error("Invalid --uniform-color (expected a hex color such as #ffffff)"))
+                .into();
+            i += 2;
+        } else if args[i] == "--radii" && i + 1 < args.len() {
+            radius_settings.source = parse_radius_source(&args[i + 1])
+                .unwrap_or_else(|| exit_with_usage_error("Invalid --radii (expected vdw, covalent, or uniform)"));
+            i += 2;
+        } else if args[i] == "--uniform-radius" && i + 1 < args.len() {
+            radius_settings.uniform_radius = args[i + 1]
+                .parse::<f32>()
+                .ok()
+                .filter(|v| v.is_finite() && *v > 0.0)
+                .unwrap_or_else(|| exit_with_usage_error("Invalid --uniform-radius (expected a positive number)"));
+            i += 2;
+        } else if args[i] == "--input" && i + 1 < args.len() {
+            input_paths.push(args[i + 1].clone());
+            i += 2;
+        } else if args[i] == "--contacts" && i + 1 < args.len() {
+            contacts = Some(
+                parse_fragments(&args[i + 1])
+                    .unwrap_or_else(|| exit_with_usage_error("Invalid --contacts (expected two fragments such as 0-2:3-5)")),
+            );
+            i += 2;
+        } else if args[i] == "--cutoff" && i + 1 < args.len() {
+            contact_cutoff = args[i + 1]
+                .parse::<f64>()
+                .ok()
+                .filter(|v| v.is_finite() && *v > 0.0)
+                .unwrap_or_else(|| exit_with_usage_error("Invalid --cutoff (expected a positive number)"));
+            i += 2;
+        } else if args[i] == "--max-atoms" && i + 1 < args.len() {
+            max_atoms = args[i + 1]
+                .parse::<usize>()
+                .unwrap_or_else(|_| exit_with_usage_error("Invalid --max-atoms (expected a non-negative integer)"));
+            i += 2;
+        } else if args[i] == "--frame" && i + 1 < args.len() {
+            load_options.frame = Some(
+                args[i + 1]
+                    .parse::<i64>()
+                    .unwrap_or_else(|_| exit_with_usage_error("Invalid --frame (expected an integer frame index)")),
+            );
+            i += 2;
+        } else if args[i] == "--box-filter" && i + 1 < args.len() {
+            box_filter.0 = Some(
+                Selection::parse(&args[i + 1]).unwrap_or_else(|e| exit_with_usage_error(&format!("Invalid --box-filter selection: {}", e))),
+            );
+            i += 2;
+        } else if args[i] == "--delete" && i + 1 < args.len() {
+            load_options.delete = Some(
+                Selection::parse(&args[i + 1]).unwrap_or_else(|e| exit_with_usage_error(&format!("Invalid --delete selection: {}", e))),
+            );
+            i += 2;
+        } else if args[i] == "--elements-only" && i + 1 < args.len() {
+            let elements: Vec<String> = args[i + 1]
+                .split(',')
+                .map(|e| e.trim().to_string())
+                .filter(|e| !e.is_empty())
+                .collect();
+            if elements.is_empty() {
+                exit_with_usage_error("Invalid --elements-only (expected element symbols such as Fe,Cu)");
+            }
+            load_options.elements = Some(elements);
+            i += 2;
+        } else if args[i] == "--dedup" {
+            load_options.dedup = Some(DEFAULT_DEDUP_TOLERANCE);
+            i += 1;
+        } else if args[i] == "--overlay" && i + 1 < args.len() {
+            overlay_path = Some(args[i + 1].clone());
+            i += 2;
+        } else if args[i] == "--units" && i + 1 < args.len() {
+            load_options.units = Some(
+                LengthUnit::from_name(&args[i + 1]).unwrap_or_else(|| exit_with_usage_error("Invalid --units (expected nm, angstrom, or bohr)")),
+            );
+            i += 2;
+        } else if args[i] == "--mirror" && i + 1 < args.len() {
+            load_options.mirror = Some(
+                Axis::from_name(&args[i + 1]).unwrap_or_else(|| exit_with_usage_error("Invalid --mirror (expected x, y, or z)")),
+            );
+            i += 2;
+        } else if args[i] == "--charge-multiplicity" {
+            load_options.parse.charge_multiplicity = true;
+            i += 1;
+        } else if args[i] == "--tolerant-count" {
+            load_options.parse.tolerant_count_line = true;
+            i += 1;
+        } else if args[i] == "--charge-suffixes" {
+            load_options.parse.charge_suffixes = true;
+            i += 1;
+        } else if args[i] == "--infer-elements" {
+            load_options.parse.infer_elements = true;
+            i += 1;
+        } else if args[i] == "--frozen-column" {
+            load_options.parse.frozen_column = true;
+            i += 1;
+        } else if args[i] == "--mass-column" && i + 1 < args.len() {
+            load_options.parse.mass_column = Some(
+                args[i + 1]
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|c| *c > 4)
+                    .unwrap_or_else(|| exit_with_usage_error("Invalid --mass-column (expected a column after the coordinates, 5 or more)")),
+            );
+            i += 2;
+        } else if args[i] == "--cache" {
+            load_options.cache = true;
+            i += 1;
+        } else if args[i] == "--no-cache" {
+            load_options.cache = false;
+            i += 1;
+        } else if args[i] == "--stream" {
+            stream_stdin = true;
+            i += 1;
+        } else if args[i] == "--serve" && i + 1 < args.len() {
+            serve_port = Some(
+                args[i + 1]
+                    .parse::<u16>()
+                    .unwrap_or_else(|_| exit_with_usage_error("Invalid --serve (expected a port number)")),
+            );
+            i += 2;
+        } else if args[i] == "--serve-addr" && i + 1 < args.len() {
+            serve_addr = args[i + 1]
+                .parse::<IpAddr>()
+                .unwrap_or_else(|_| exit_with_usage_error("Invalid --serve-addr (expected an IP address such as 0.0.0.0)"));
+            i += 2;
+        } else if args[i] == "--info" {
+            show_info = true;
+            i += 1;
+        } else if args[i] == "--json-errors" {
+            json_errors = true;
+            i += 1;
+        } else if args[i] == "--verbose" {
+            load_options.verbose = true;
+            i += 1;
+        } else if args[i] == "--stereo" {
+            stereo_settings.enabled = true;
+            i += 1;
+        } else if args[i] == "--eye-separation" && i + 1 < args.len() {
+            stereo_settings.eye_separation = args[i + 1]
+                .parse::<f32>()
+                .ok()
+                .filter(|v| v.is_finite() && *v >= 0.0)
+                .unwrap_or_else(|| exit_with_usage_error("Invalid --eye-separation (expected a non-negative number)"));
+            i += 2;
+        } else if subcommand.is_some() && !args[i].starts_with('-') {
+            positional.push(args[i].clone());
+            i += 1;
+        } else {
+            exit_with_usage_error(&format!("unrecognized argument '{}' (unknown, or missing its value)", args[i]));
+        }
+    }
+    if controller.near >= controller.far {
+        exit_with_usage_error("Invalid --near/--far (the near plane must be closer than the far plane)");
+    }
+
+    // Conversion is a batch mode of its own
+    if subcommand == Some(Subcommand::Convert) {
+        let [input, output] = positional.as_slice() else {
+            exit_with_usage_error("convert needs an input and an output file");
+        };
+        let molecule = load_structure_file(input, &load_options)
+            .unwrap_or_else(|e| exit_with_load_error(input, e.as_ref(), json_errors));
+        write_structure_file(output, &molecule.structure).unwrap_or_else(|e| panic!("Failed to write {}: {}", output, e));
+        println!("Wrote {} atoms from {} to {}", molecule.atoms.len(), input, output);
+        return;
+    }
+    input_paths.extend(positional);
+    if subcommand == Some(Subcommand::Info) {
+        show_info = true;
+    }
+
+    if input_paths.is_empty() {
+        input_paths = scene.inputs.clone();
+    }
+    // Only the viewer falls back to the demo structure
+    if input_paths.is_empty() && show_info {
+        exit_with_usage_error("info needs at least one structure file");
+    }
+    if input_paths.is_empty() {
+        input_paths.push("water_dimer.xyz".to_string());
+    }
+
+    // Uniform settings are sized once all arguments are read, since
+    // --uniform-radius and --uniform-color may follow the scheme
+    if let RadiusSource::Uniform(_) = radius_settings.source {
+        radius_settings.source = RadiusSource::Uniform(radius_settings.uniform_radius);
+    }
+    if let ColorScheme::Uniform(_) = color_settings.scheme {
+        color_settings.scheme = ColorScheme::Uniform(color_settings.uniform_color);
+    }
+    // Bevy panics if an OIT camera uses MSAA, whatever --aa asked for
+    if transparency_settings.order_independent {
+        antialias_settings.msaa = Msaa::Off;
+    }
+    let scene_sources = SceneSources {
+        inputs: input_paths.clone(),
+        palette: palette_path,
+    };
+
+    // Contact listing is a batch mode; print and exit without opening a window
+    if let Some((frag_a, frag_b)) = contacts {
+        // Loaded like the viewer's molecule, so indices and coordinates match what it shows
+        let path = &input_paths[0];
+        let molecule = load_structure_file(path, &load_options)
+            .unwrap_or_else(|e| exit_with_load_error(path, e.as_ref(), json_errors));
+        print_contacts(path, &molecule.structure, &frag_a, &frag_b, contact_cutoff);
+        return;
+    }
+
+    // Structure summaries are also a batch mode
+    if show_info {
+        for path in &input_paths {
+            let molecule = load_structure_file(path, &load_options)
+                .unwrap_or_else(|e| exit_with_load_error(path, e.as_ref(), json_errors));
+            print_info(path, &molecule.structure);
+        }
+        return;
+    }
+
+    // Serving frames to a browser is headless too
+    if let Some(port) = serve_port {
+        if stream_stdin {
+            let frames = FrameReader::new(BufReader::new(std::io::stdin()), load_options.parse.clone());
+            serve_frames((serve_addr, port), skip_oversized_frames(frames, max_atoms));
+        } else {
+            let path = &input_paths[0];
+            let molecule = load_structure_file(path, &load_options)
+                .unwrap_or_else(|e| exit_with_load_error(path, e.as_ref(), json_errors));
+            serve_frames((serve_addr, port), std::iter::once(Ok::<_, parser::ParseError>(molecule.structure)));
+        }
+    }
+
+    let mut frame_stream = None;
+    let molecules: Vec<Molecule> = if stream_stdin {
+        // Show the first frame before starting the window, then keep reading in the background
+        let mut frames = FrameReader::new(BufReader::new(std::io::stdin()), load_options.parse.clone());
+        let first = frames
+            .next()
+            .expect("No frames on stdin")
+            .unwrap_or_else(|e| panic!("Failed to parse frame from stdin: {}", e));
+        check_atom_limit("the first frame on stdin", first.atoms.len(), max_atoms);
+        frame_stream = Some(spawn_frame_reader(frames, max_atoms));
+        vec![render_molecule(first)]
+    } else {
+        input_paths
+            .iter()
+            .map(|path| {
+                let molecule = load_structure_file(path, &load_options)
+                    .unwrap_or_else(|e| exit_with_load_error(path, e.as_ref(), json_errors));
+                check_atom_limit(path, molecule.atoms.len(), max_atoms);
+                molecule
+            })
+            .collect()
+    };
+    let offsets = layout_molecules(&molecules, &palette, radius_settings.source);
+
+    // The reference goes through the same load options so atoms still line up
+    let reference = overlay_path.map(|path| {
+        let reference = load_structure_file(&path, &load_options)
+            .unwrap_or_else(|e| exit_with_load_error(&path, e.as_ref(), json_errors))
+            .structure;
+        let moved = molecules[0]
+            .structure
+            .displacement(&reference)
+            .unwrap_or_else(|e| exit_with_usage_error(&format!("Invalid --overlay {} for {}: {}", path, input_paths[0], e)));
+        let max = moved.iter().copied().fold(0.0, f64::max);
+        println!("Largest displacement from {}: {:.3} Angstrom", path, max);
+        reference
+    });
+
+    // Displacements must be flipped along with the structure they belong to
+    if let Some(axis) = load_options.mirror {
+        for mode in &mut mode_animation.modes {
+            mode.mirror(axis);
+        }
+    }
+
+    if let Some(mode) = mode_animation.modes.first() {
+        let atom_count = molecules[0].atoms.len();
+        if mode.displacements.len() != atom_count {
+            panic!(
+                "Modes file has {} displacements per mode but {} has {} atoms",
+                mode.displacements.len(),
+                input_paths[0],
+                atom_count
+            );
+        }
+    }
+
+    let options = mdi_options.expect("Must provide -mdi option");
+    //let c_options = CString::new(options).expect("Invalid options string");
+
+    /*
+    let ret = unsafe { Mdi::init_with_options(c_options.as_ptr()) };
+    if ret != 0 {
+        panic!("MDI_Init_with_options failed");
+    }
+    */
+    Mdi::init_with_options(&options);
+
+
+    let controller_rotation = controller.rotation;
+    let mut app = App::new();
+    app.add_plugins(DefaultPlugins)
+        .insert_resource(Molecules {
+            molecules,
+            offsets,
+            focused: 0,
+        })
+        .insert_resource(controller)
+        .insert_resource(turntable)
+        .insert_resource(ao_settings)
+        .insert_resource(antialias_settings)
+        .insert_resource(transparency_settings)
+        .insert_resource(shadow_settings)
+        .insert_resource(LightDrag::default())
+        .insert_resource(Selected::default())
+        .insert_resource(box_filter)
+        .insert_resource(hydrogen_settings)
+        .insert_resource(stereo_settings)
+        .insert_resource(palette)
+        .insert_resource(mode_animation)
+        .insert_resource(style_override)
+        .insert_resource(color_settings)
+        .insert_resource(ReferenceStructure(reference))
+        .insert_resource(CuttingPlane::default())
+        .insert_resource(radius_settings)
+        .insert_resource(MirrorAxis(load_options.mirror.unwrap_or(Axis::X)))
+        .insert_resource(outline_settings)
+        .insert_resource(startup_view)
+        .insert_resource(scene_sources)
+        .insert_resource(EditHistory::default())
+        .insert_resource(ClearColor(Color::srgb(0.1, 0.1, 0.15)))
+        .add_systems(Startup, (setup, stereo::setup_stereo))
+        .add_systems(Update, (toggle_invert_rotation, camera_rotation, camera_key_rotation, camera_pan, camera_zoom, update_camera, toggle_ground_plane, molecule_focus_controls))
+        .add_systems(Update, (toggle_turntable, spin_turntable).chain().before(update_camera))
+        .add_systems(Update, (mirror_controls, delete_selected_atoms, undo_controls).chain())
+        .add_systems(Update, (toggle_scale_bar, update_scale_bar).chain())
+        .add_systems(Update, (toggle_dipole, draw_dipoles).chain())
+        .add_systems(Update, (toggle_axis_ticks, draw_axis_ticks).chain())
+        .add_systems(Update, (toggle_element_legend, update_element_legend).chain())
+        .add_systems(Update, update_energy_plot)
+        .add_systems(Update, (box_select, prune_selection, apply_selection, update_measurement).chain())
+        .add_systems(Update, (toggle_hydrogens, apply_hydrogens).chain())
+        .add_systems(Update, (save_scene_on_key, save_molecule_on_key))
+        .add_systems(Update, (toggle_ambient_occlusion, cycle_antialiasing, apply_ambient_occlusion).chain())
+        .add_systems(Update, (toggle_shadows, apply_shadows).chain())
+        .add_systems(Update, (toggle_light_drag, light_rotation).chain())
+        .add_systems(Update, (toggle_outlines, apply_outlines).chain())
+        .add_systems(Update, (mode_animation_controls, animate_modes).chain())
+        .add_systems(Update, (toggle_color_scheme, cutting_plane_controls, coordination_cutoff_controls, apply_color_scheme).chain())
+        .add_systems(Update, (toggle_radius_source, apply_radius_source).chain())
+        .add_systems(Update, (reload_palette, apply_palette).chain())
+        .add_systems(
+            Update,
+            (stereo::toggle_stereo, stereo::apply_stereo, stereo::resize_stereo_images).chain(),
+        )
+        .add_systems(Update, receive_stream_frames.run_if(resource_exists::<FrameStream>));
+
+    if let Some(frame_stream) = frame_stream {
+        app.insert_resource(frame_stream);
+    }
+    if let Some(dir) = turntable_dir {
+        std::fs::create_dir_all(&dir).unwrap_or_else(|e| panic!("Failed to create {}: {}", dir.display(), e));
+        app.insert_resource(TurntableCapture {
+            dir,
+            frames: turntable_frames,
+            requested: 0,
+            saved: 0,
+            start: controller_rotation,
+        })
+        .add_systems(Update, capture_turntable.after(spin_turntable).before(update_camera));
+    }
+    app.run();
+}
+
+/// Parse the subcommand named by the first argument
+fn parse_subcommand(name: &str) -> Option<Subcommand> {
+    match name {
+        "view" => Some(Subcommand::View),
+        "info" => Some(Subcommand::Info),
+        "convert" => Some(Subcommand::Convert),
+        _ => None,
+    }
+}
+
+/// Parse a color scheme name as used by scene files
+fn parse_color_scheme(name: &str) -> Option<ColorScheme> {
+    match name.to_lowercase().as_str() {
+        "element" => Some(ColorScheme::Element),
+        // The color is filled in once all settings are read
+        "uniform" => Some(ColorScheme::Uniform(Color::WHITE)),
+        "cross_section" => Some(ColorScheme::CrossSection),
+        "coordination" => Some(ColorScheme::Coordination),
+        "displacement" => Some(ColorScheme::Displacement),
+        _ => None,
+    }
+}
+
+/// Parse a radius source name for --radii and scene files
+fn parse_radius_source(name: &str) -> Option<RadiusSource> {
+    match name.to_lowercase().as_str() {
+        "vdw" => Some(RadiusSource::VanDerWaals),
+        "covalent" => Some(RadiusSource::Covalent),
+        // The radius is filled in once all settings are read
+        "uniform" => Some(RadiusSource::Uniform(0.0)),
+        _ => None,
+    }
+}
+
+/// Parse an MSAA sample count for --aa
+fn parse_msaa(level: &str) -> Option<Msaa> {
+    match level.to_lowercase().as_str() {
+        "off" | "1" => Some(Msaa::Off),
+        "2" => Some(Msaa::Sample2),
+        "4" => Some(Msaa::Sample4),
+        "8" => Some(Msaa::Sample8),
+        _ => None,
+    }
+}
+
+fn parse_ssao_quality(name: &str) -> Option<ScreenSpaceAmbientOcclusionQualityLevel> {
+    match name.to_lowercase().as_str() {
+        "low" => Some(ScreenSpaceAmbientOcclusionQualityLevel::Low),
+        "medium" => Some(ScreenSpaceAmbientOcclusionQualityLevel::Medium),
+        "high" => Some(ScreenSpaceAmbientOcclusionQualityLevel::High),
+        "ultra" => Some(ScreenSpaceAmbientOcclusionQualityLevel::Ultra),
+        _ => None,
+    }
+}
+
+/// Parse "X,Y,Z" Euler angles in degrees into a camera rotation
+fn parse_rotation(text: &str) -> Option<Quat> {
+    let angles: Vec<f32> = text
+        .split(',')
+        .map(|a| a.trim().parse::<f32>().ok().filter(|v| v.is_finite()))
+        .collect::<Option<Vec<_>>>()?;
+
+    match angles.as_slice() {
+        [x, y, z] => Some(Quat::from_euler(
+            EulerRot::XYZ,
+            x.to_radians(),
+            y.to_radians(),
+            z.to_radians(),
+        )),
+        _ => None,
+    }
+}
+
+/// Parse two fragments separated by ':', each a comma-separated list of
+/// 0-based atom indices and inclusive ranges, e.g. "0-2:3,5"
+fn parse_fragments(text: &str) -> Option<(Vec<usize>, Vec<usize>)> {
+    let parse_fragment = |fragment: &str| -> Option<Vec<usize>> {
+        let mut indices = Vec::new();
+        for part in fragment.split(',') {
+            match part.trim().split_once('-') {
+                Some((start, end)) => {
+                    let start = start.trim().parse::<usize>().ok()?;
+                    let end = end.trim().parse::<usize>().ok()?;
+                    if start > end {
+                        return None;
+                    }
+                    indices.extend(start..=end);
+                }
+                None => indices.push(part.trim().parse::<usize>().ok()?),
+            }
+        }
+        Some(indices)
+    };
+
+    let (a, b) = text.split_once(':')?;
+    Some((parse_fragment(a)?, parse_fragment(b)?))
+}
+
+/// Print inter-fragment atom pairs within `cutoff` of each other
+fn print_contacts(path: &str, molecule: &parser::Molecule, frag_a: &[usize], frag_b: &[usize], cutoff: f64) {
+    if let Some(&index) = frag_a.iter().chain(frag_b).find(|&&i| i >= molecule.atoms.len()) {
+        panic!("Atom index {} is out of range for {} ({} atoms)", index, path, molecule.atoms.len());
+    }
+
+    // A periodic cell wraps contacts across its faces, so every pair is checked
+    let contacts = match molecule.lattice() {
+        Some(lattice) => {
+            let mut contacts: Vec<(usize, usize, f64)> = frag_a
+                .iter()
+                .flat_map(|&a| frag_b.iter().map(move |&b| (a, b)))
+                .filter(|(a, b)| a != b)
+                .map(|(a, b)| (a, b, molecule.min_image_distance(a, b, Some(&lattice))))
+                .filter(|&(_, _, distance)| distance <= cutoff)
+                .collect();
+            contacts.sort_by_key(|&(a, b, _)| (a, b));
+            contacts.dedup_by_key(|&mut (a, b, _)| (a, b));
+            println!("Using minimum-image distances in the periodic cell of {}", path);
+            contacts
+        }
+        None => molecule.contacts_between(frag_a, frag_b, cutoff),
+    };
+    println!("{} contacts within {} Angstrom in {}", contacts.len(), cutoff, path);
+    for (a, b, distance) in contacts {
+        println!(
+            "{:>6} {:<3} {:>6} {:<3} {:8.3}",
+            a, molecule.atoms[a].element, b, molecule.atoms[b].element, distance
+        );
+    }
+}
+
+/// Print the composition and size descriptors of a structure
+fn print_info(path: &str, molecule: &parser::Molecule) {
+    let mut counts: Vec<(&str, usize)> = Vec::new();
+    for atom in &molecule.atoms {
+        match counts.iter_mut().find(|(element, _)| *element == atom.element) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((&atom.element, 1)),
+        }
+    }
+    counts.sort();
+    let composition: Vec<String> = counts.iter().map(|(element, count)| format!("{} {}", element, count)).collect();
+    println!("{}: {} atoms ({})", path, molecule.atoms.len(), composition.join(", "));
+
+    if let (Some(charge), Some(multiplicity)) = (molecule.charge, molecule.multiplicity) {
+        println!("  Charge: {}, multiplicity: {}", charge, multiplicity);
+    }
+    let frozen = molecule.atoms.iter().filter(|a| a.frozen).count();
+    if frozen > 0 {
+        println!("  Frozen: {} atoms", frozen);
+    }
+
+    if let Some((min, max)) = molecule.bounding_box() {
+        println!(
+            "  Bounding box: {:.3} x {:.3} x {:.3} Angstrom",
+            max[0] - min[0],
+            max[1] - min[1],
+            max[2] - min[2]
+        );
+    }
+    if let Some(diameter) = molecule.diameter() {
+        println!("  Diameter: {:.3} Angstrom", diameter);
+    }
+    if let (Some(net), Some(means)) = (molecule.net_charge(), molecule.mean_charge_by_element()) {
+        let means: Vec<String> = means.iter().map(|(element, q)| format!("{} {:+.4}", element, q)).collect();
+        println!("  Net partial charge: {:+.4} e (mean by element: {})", net, means.join(", "));
+    }
+    if let Some(dipole) = molecule.dipole() {
+        println!(
+            "  Dipole: {:.3} D ({:.3}, {:.3}, {:.3})",
+            dipole.iter().map(|d| d * d).sum::<f64>().sqrt(),
+            dipole[0],
+            dipole[1],
+            dipole[2]
+        );
+    }
+    match molecule.radius_of_gyration() {
+        Some(rg) => println!("  Radius of gyration: {:.3} Angstrom", rg),
+        None => println!("  Radius of gyration: unknown (no mass for some elements)"),
+    }
+}
+
+/// Parse a style such as "element O: color=#ff0000 opacity=0.5 scale=0.8"
+fn parse_style(text: &str) -> Result<(Selection, AtomStyle), String> {
+    let (query, settings) = text.split_once(':').ok_or("missing ':' after the selection")?;
+    let selection = Selection::parse(query).map_err(|e| e.to_string())?;
+
+    let mut style = AtomStyle::default();
+    for setting in settings.split_whitespace() {
+        let (key, value) = setting
+            .split_once('=')
+            .ok_or_else(|| format!("'{}' is not a key=value setting", setting))?;
+        let number = || {
+            value
+                .parse::<f32>()
+                .ok()
+                .filter(|v| v.is_finite() && *v >= 0.0)
+                .ok_or_else(|| format!("'{}' is not a valid {}", value, key))
+        };
+
+        match key {
+            "color" => {
+                let color = Srgba::hex(value).map_err(|_| format!("'{}' is not a hex color", value))?;
+                style.color = Some(color.into());
+            }
+            "opacity" => style.opacity = Some(number()?.min(1.0)),
+            "scale" => style.radius_scale = Some(number()?),
+            _ => return Err(format!("unknown style setting '{}'", key)),
+        }
+    }
+
+    Ok((selection, style))
+}
+
+/// Exit before opening the window if a structure has more than `max_atoms`
+/// atoms, since spawning millions of entities can exhaust memory
+fn check_atom_limit(source: &str, atom_count: usize, max_atoms: usize) {
+    if atom_count > max_atoms {
+        eprintln!(
+            "Refusing to render {} atoms from {} (limit {}). Use --info for a summary, \
+             --serve to stream it to a browser, --elements-only to keep fewer atoms, \
+             or --max-atoms to raise the limit.",
+            atom_count, source, max_atoms
+        );
+        std::process::exit(1);
+    }
+}
+
+/// Report a malformed command line and exit
+fn exit_with_usage_error(message: &str) -> ! {
+    eprintln!("Error: {}", message);
+    eprintln!("Usage: chemgdb [view FILE... | info FILE... | convert INPUT OUTPUT] [OPTIONS]");
+    std::process::exit(2);
+}
+
+/// Report a file that failed to load and exit
+///
+/// With `--json-errors` the error is printed to stderr as a single JSON object,
+/// `{"error":{"kind":...,"message":...,"path":...}}`, for tools and CI to consume.
+fn exit_with_load_error(path: &str, error: &(dyn std::error::Error + 'static), json_errors: bool) -> ! {
+    if !json_errors {
+        panic!("Failed to load {}: {}", path, error);
+    }
+
+    let mut detail = match error.downcast_ref::<parser::ParseError>() {
+        Some(parse_error) => serde_json::to_value(parse_error).expect("Parse errors always serialize"),
+        None => serde_json::json!({ "kind": "Other", "message": error.to_string() }),
+    };
+    detail["path"] = path.into();
+    eprintln!("{}", serde_json::json!({ "error": detail }));
+    std::process::exit(1);
+}
+
+/// Load an XYZ file, or a Mol2 file if the extension is `.mol2`
+///
+/// Molecules in a Mol2 file are picked with `--frame` like trajectory
+/// frames, defaulting to the first.
+///
+/// With `--cache`, parsed XYZ frames are cached in a `.cache` sidecar next
+/// to the file and reused while the file is unchanged.
+fn load_structure_file(path: &str, options: &LoadOptions) -> Result<Molecule, Box<dyn std::error::Error>> {
+  let file = File::open(path)?;
+  let file_size = file.metadata()?.len() as usize;
+  let is_mol2 = path.to_lowercase().ends_with(".mol2");
+
+  let start = Instant::now();
+  // Frames are cached as parsed with the default options, and a cached
+  // trajectory still needs --frame, as when parsing
+  let use_cache = options.cache && !is_mol2 && options.parse == ParseOptions::default();
+  let cached = if use_cache {
+    load_cache(Path::new(path)).filter(|frames| options.frame.is_some() || frames.len() == 1)
+  } else {
+    None
+  };
+  let from_cache = cached.is_some();
+
+  let mut frames = match cached {
+    Some(frames) => frames,
+    None => {
+      let frames = match (is_mol2, options.frame) {
+        (false, None) => vec![parse_xyz_with_options(file, &options.parse)?],
+        (true, _) => parse_mol2(file)?.into_iter().map(|m| m.molecule).collect(),
+        (false, Some(_)) => parse_xyz_frames_with_progress(file, &options.parse, |frames| {
+          if options.verbose && frames % PROGRESS_INTERVAL == 0 {
+            eprint!("\rParsed {} frames", frames);
+          }
+        })?,
+      };
+      if options.verbose && frames.len() >= PROGRESS_INTERVAL {
+        eprintln!("\rParsed {} frames", frames.len());
+      }
+      if use_cache
+        && let Err(e) = write_cache(Path::new(path), &frames)
+      {
+        eprintln!("Not caching {}: {}", path, e);
+      }
+      frames
+    }
+  };
+
+  let frame = options.frame.unwrap_or(0);
+  let parsed_bytes = frames.iter().map(|f| f.heap_size()).sum::<usize>();
+  let count = frames.len() as i64;
+  let index = if frame < 0 { count + frame } else { frame };
+  if !(0..count).contains(&index) {
+    return Err(format!("frame {} is out of range for {} frames", frame, count).into());
+  }
+  let frame_energies: Vec<Option<f64>> = frames.iter().map(|f| f.energy()).collect();
+  let mut parsed = frames.swap_remove(index as usize);
+  let elapsed = start.elapsed();
+
+  // Everything downstream, including --dedup tolerances, works in Angstrom
+  if let Some(units) = options.units {
+    parsed.scale(units.to_angstrom());
+    println!("Read {} coordinates in {}, scaled by {} to Angstrom", path, units, units.to_angstrom());
+  }
+
+  if let Some(elements) = &options.elements {
+    let elements: Vec<&str> = elements.iter().map(String::as_str).collect();
+    let total = parsed.atoms.len();
+    parsed = parsed.filter_elements(&elements);
+    if parsed.atoms.is_empty() {
+      return Err(format!("no {} atoms in {}", elements.join(", "), path).into());
+    }
+    println!("Kept {} of {} atoms ({}) from {}", parsed.atoms.len(), total, elements.join(", "), path);
+  }
+
+  if let Some(selection) = &options.delete {
+    let removed = parsed.remove_atoms(&selection.evaluate(&parsed));
+    if parsed.atoms.is_empty() {
+      return Err(format!("--delete removes every atom from {}", path).into());
+    }
+    println!("Deleted {} atoms from {}", removed, path);
+  }
+
+  if let Some(tol) = options.dedup {
+    let removed = parsed.deduplicate(tol);
+    if removed > 0 {
+      println!("Removed {} duplicate atoms from {}", removed, path);
+    }
+  }
+
+  if let Some(axis) = options.mirror {
+    parsed.mirror(axis);
+    println!("Mirrored {} along the {} axis; chirality is inverted", path, axis);
+  }
+
+  if options.verbose {
+    if from_cache {
+      println!("Read {} from {}", path, cache_path(Path::new(path)).display());
+    }
+    // The parser holds the whole file in memory alongside the parsed atoms
+    let peak_bytes = file_size + parsed_bytes;
+    println!("Parsed {} atoms from {} in {:.2?}", parsed.atoms.len(), path, elapsed);
+    println!("Approximate peak memory: {:.1} KiB", peak_bytes as f64 / 1024.0);
+  }
+
+  let warnings = parsed.validate();
+  if !warnings.is_empty() {
+    eprintln!("{}: {} validation warnings", path, warnings.len());
+    if options.verbose {
+      for warning in &warnings {
+        eprintln!("  {}", warning);
+      }
+    }
+  }
+
+  let mut molecule = render_molecule(parsed);
+  molecule.frame_energies = frame_energies;
+  molecule.frame = index as usize;
+  Ok(molecule)
+}
+
+/// Write a structure in the format named by the file extension
+///
+/// XYZ is the only format with a writer so far.
+fn write_structure_file(path: &str, molecule: &parser::Molecule) -> Result<(), Box<dyn std::error::Error>> {
+  if !path.to_lowercase().ends_with(".xyz") {
+    return Err("only XYZ output (.xyz) is supported".into());
+  }
+  let file = File::create(path)?;
+  write_xyz(molecule, BufWriter::new(file))?;
+  Ok(())
+}
+
+/// Build the viewer's molecule from a parsed structure
+fn render_molecule(parsed: parser::Molecule) -> Molecule {
+  let atoms = parsed
+    .atoms
+    .iter()
+    .map(|a| Atom {
+      element: a.element.clone(),
+      position: Vec3::new(a.x as f32, a.y as f32, a.z as f32),
+      frozen: a.frozen,
+    })
+    .collect();
+
+  Molecule {
+    atoms,
+    frame_energies: vec![parsed.energy()],
+    frame: 0,
+    structure: parsed,
+  }
+}
+
+/// Push each frame to WebSocket clients on `addr` without opening a window,
+/// then keep serving the last one to clients that connect later
+fn serve_frames<E: std::fmt::Display>(
+    addr: (IpAddr, u16),
+    frames: impl IntoIterator<Item = Result<parser::Molecule, E>>,
+) -> ! {
+    let server = FrameServer::bind(addr).unwrap_or_else(|e| panic!("Failed to serve on {}:{}: {}", addr.0, addr.1, e));
+    println!("Serving frames on ws://{}", server.local_addr());
+
+    for frame in frames {
+        match frame {
+            Ok(molecule) => server.publish(&molecule),
+            Err(e) => {
+                eprintln!("Stopped reading frames: {}", e);
+                break;
+            }
+        }
+    }
+
+    loop {
+        std::thread::park();
+    }
+}
+
+/// Read the remaining frames on a background thread so the viewer never
+/// blocks waiting for input, skipping frames over the atom limit
+fn spawn_frame_reader<R: BufRead + Send + 'static>(frames: FrameReader<R>, max_atoms: usize) -> FrameStream {
+    let (sender, receiver) = mpsc::channel();
+
+    std::thread::spawn(move || {
+        for frame in skip_oversized_frames(frames, max_atoms) {
+            match frame {
+                Ok(molecule) => {
+                    // The viewer has closed
+                    if sender.send(molecule).is_err() {
+                        break;
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Stopped reading frames from stdin: {}", e);
+                    break;
+                }
+            }
+        }
+    });
+
+    FrameStream(Mutex::new(receiver))
+}
+
+/// Drop frames from stdin with more than `max_atoms` atoms, noting each on stderr
+fn skip_oversized_frames<I>(frames: I, max_atoms: usize) -> impl Iterator<Item = I::Item>
+where
+    I: Iterator<Item = Result<parser::Molecule, parser::ParseError>>,
+{
+    frames.filter(move |frame| match frame {
+        Ok(molecule) if molecule.atoms.len() > max_atoms => {
+            eprintln!(
+                "Skipping a frame with {} atoms from stdin (limit {}; see --max-atoms)",
+                molecule.atoms.len(),
+                max_atoms
+            );
+            false
+        }
+        _ => true,
+    })
+}
+
+/// Pink marks elements with no palette, built-in, or fallback color
+const UNKNOWN_ELEMENT_COLOR: Color = Color::srgb(1.0, 0.5, 1.0);
+
+/// Drawn radius for elements with no palette, built-in, or fallback radius
+const UNKNOWN_ELEMENT_RADIUS: f32 = 0.6;
+
+/// Atom color, preferring the loaded palette over the CPK scheme
+fn get_atom_color(element: &str, palette: &Palette) -> Color {
+    palette
+        .color(element)
+        .or_else(|| get_cpk_color(element))
+        .or_else(|| palette.fallback_color())
+        .unwrap_or(UNKNOWN_ELEMENT_COLOR)
+}
+
+/// CPK coloring scheme for atoms
+fn get_cpk_color(element: &str) -> Option<Color> {
+    let color = match element.to_uppercase().as_str() {
+        "H" => Color::srgb(1.0, 1.0, 1.0),        // White
+        "C" => Color::srgb(0.3, 0.3, 0.3),        // Dark gray
+        "N" => Color::srgb(0.2, 0.2, 1.0),        // Blue
+        "O" => Color::srgb(1.0, 0.2, 0.2),        // Red
+        "S" => Color::srgb(1.0, 1.0, 0.2),        // Yellow
+        "P" => Color::srgb(1.0, 0.5, 0.0),        // Orange
+        "F" | "CL" => Color::srgb(0.2, 1.0, 0.2), // Green
+        "BR" => Color::srgb(0.6, 0.1, 0.1),       // Dark red
+        "I" => Color::srgb(0.4, 0.0, 0.7),        // Purple
+        "FE" => Color::srgb(0.9, 0.5, 0.0),       // Orange
+        "CA" => Color::srgb(0.2, 0.8, 0.2),       // Green
+        "MG" => Color::srgb(0.0, 0.5, 0.0),       // Dark green
+        "ZN" => Color::srgb(0.5, 0.5, 0.6),       // Slate gray
+        _ => return None,
+    };
+    Some(color)
+}
+
+/// Atom radius for the given source, preferring the loaded palette over the
+/// built-in radii
+///
+/// The uniform source ignores the palette and gives every atom the same radius.
+fn get_atom_radius(element: &str, palette: &Palette, source: RadiusSource) -> f32 {
+    let table_radius = match source {
+        RadiusSource::VanDerWaals => get_vdw_radius(element),
+        RadiusSource::Covalent => get_covalent_radius(element),
+        RadiusSource::Uniform(radius) => return radius,
+    };
+    palette
+        .radius(element)
+        .or(table_radius)
+        .or_else(|| palette.fallback_radius())
+        .unwrap_or(UNKNOWN_ELEMENT_RADIUS)
+}
+
+/// Van der Waals radii (scaled for visualization)
+fn get_vdw_radius(element: &str) -> Option<f32> {
+    periodic_table::vdw_radius(element).map(|r| r as f32 * 0.4)
+}
+
+/// Covalent radii, halved so bonded neighbours stay visibly apart
+fn get_covalent_radius(element: &str) -> Option<f32> {
+    periodic_table::covalent_radius(element).map(|r| r as f32 * 0.5)
+}
+
+/// Axis-aligned bounds of the molecule including atom radii
+fn molecule_bounds(molecule: &Molecule, palette: &Palette, source: RadiusSource) -> (Vec3, Vec3) {
+    if molecule.atoms.is_empty() {
+        return (Vec3::ZERO, Vec3::ZERO);
+    }
+
+    let mut min = Vec3::splat(f32::MAX);
+    let mut max = Vec3::splat(f32::MIN);
+    for atom in &molecule.atoms {
+        let radius = get_atom_radius(&atom.element, palette, source);
+        min = min.min(atom.position - Vec3::splat(radius));
+        max = max.max(atom.position + Vec3::splat(radius));
+    }
+    (min, max)
+}
+
+/// Geometric center of the molecule's atoms
+fn molecule_center(molecule: &Molecule) -> Vec3 {
+    if !molecule.atoms.is_empty() {
+        molecule
+            .atoms
+            .iter()
+            .map(|a| a.position)
+            .reduce(|a, b| a + b)
+            .unwrap()
+            / molecule.atoms.len() as f32
+    } else {
+        Vec3::ZERO
+    }
+}
+
+/// Offsets that line molecules up along the x-axis without overlapping
+///
+/// The first molecule stays in place; each following one is placed to the
+/// right of the previous bounding box and centered on the first in y and z.
+fn layout_molecules(molecules: &[Molecule], palette: &Palette, source: RadiusSource) -> Vec<Vec3> {
+    let mut offsets = Vec::with_capacity(molecules.len());
+    let mut anchor = Vec3::ZERO;
+    let mut next_min_x = 0.0;
+
+    for (i, molecule) in molecules.iter().enumerate() {
+        let (min, max) = molecule_bounds(molecule, palette, source);
+        let center = (min + max) / 2.0;
+
+        let offset = if i == 0 {
+            anchor = center;
+            Vec3::ZERO
+        } else {
+            Vec3::new(next_min_x - min.x, anchor.y - center.y, anchor.z - center.z)
+        };
+
+        next_min_x = max.x + offset.x + MOLECULE_SPACING;
+        offsets.push(offset);
+    }
+
+    offsets
+}
+
+/// Everything needed to spawn the entities for a molecule
+#[derive(SystemParam)]
+struct MoleculeSpawner<'w, 's> {
+    commands: Commands<'w, 's>,
+    meshes: ResMut<'w, Assets<Mesh>>,
+    materials: ResMut<'w, Assets<StandardMaterial>>,
+    palette: Res<'w, Palette>,
+    radius_settings: Res<'w, RadiusSettings>,
+    outline_settings: Res<'w, OutlineSettings>,
+    style_override: Res<'w, StyleOverride>,
+}
+
+impl MoleculeSpawner<'_, '_> {
+    /// Spawn a molecule root at `offset` with a sphere and an outline per atom
+    fn spawn(&mut self, index: usize, molecule: &Molecule, offset: Vec3, outline: &OutlineAssets) -> Entity {
+        let outline_visibility = if self.outline_settings.enabled {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+
+        // Create molecule parent entity
+        let molecule_root = self
+            .commands
+            .spawn((
+                Transform::from_translation(offset),
+                Visibility::default(),
+                MoleculeRoot(index),
+            ))
+            .id();
+
+        // Create atoms as spheres
+        let styles = self.style_override.resolve(&molecule.structure);
+        for (atom_index, (atom, style)) in molecule.atoms.iter().zip(&styles).enumerate() {
+            let mut color = style.color.unwrap_or_else(|| get_atom_color(&atom.element, &self.palette));
+            if atom.frozen {
+                color = frozen_color(color);
+            }
+            let color = color.with_alpha(style.opacity.unwrap_or(1.0));
+            let radius = get_atom_radius(&atom.element, &self.palette, self.radius_settings.source)
+                * style.radius_scale.unwrap_or(1.0);
+
+            let atom_entity = self
+                .commands
+                .spawn((
+                    Mesh3d(self.meshes.add(Sphere::new(radius))),
+                    MeshMaterial3d(self.materials.add(StandardMaterial {
+                        base_color: color,
+                        alpha_mode: if color.alpha() < 1.0 {
+                            AlphaMode::Blend
+                        } else {
+                            AlphaMode::Opaque
+                        },
+                        perceptual_roughness: self.palette.roughness(&atom.element),
+                        metallic: self.palette.metallic(&atom.element),
+                        ..default()
+                    })),
+                    Transform::from_translation(atom.position),
+                    AtomBaseColor(color),
+                    AtomIndex {
+                        molecule: index,
+                        atom: atom_index,
+                    },
+                ))
+                .id();
+
+            self.commands.entity(molecule_root).add_child(atom_entity);
+
+            self.commands.entity(atom_entity).with_child((
+                Mesh3d(outline.mesh.clone()),
+                MeshMaterial3d(outline.material.clone()),
+                Transform::from_scale(Vec3::splat(radius + self.outline_settings.thickness)),
+                outline_visibility,
+                NotShadowCaster,
+                AtomOutline { radius },
+            ));
+        }
+
+        molecule_root
+    }
+}
+
+fn setup(
+    mut spawner: MoleculeSpawner,
+    molecules: Res<Molecules>,
+    shadow_settings: Res<ShadowSettings>,
+    startup_view: Res<StartupView>,
+    transparency: Res<TransparencySettings>,
+    mut controller: ResMut<CameraController>,
+    mut plane: ResMut<CuttingPlane>,
+) {
+    // Target the center of the focused molecule initially, unless a scene placed the camera
+    let focused = molecules.focused;
+    controller.target = startup_view
+        .target
+        .unwrap_or_else(|| molecule_center(&molecules.molecules[focused]) + molecules.offsets[focused]);
+    plane.point = controller.target;
+
+    // Outlines are back-face-only dark spheres slightly larger than each atom
+    let outline = OutlineAssets {
+        mesh: spawner.meshes.add(Sphere::new(1.0)),
+        material: spawner.materials.add(StandardMaterial {
+            base_color: Color::BLACK,
+            unlit: true,
+            cull_mode: Some(Face::Front),
+            ..default()
+        }),
+    };
+
+    let mut scene_min = Vec3::splat(f32::MAX);
+    let mut scene_max = Vec3::splat(f32::MIN);
+
+    for (index, (molecule, offset)) in molecules.molecules.iter().zip(&molecules.offsets).enumerate() {
+        let (min, max) = molecule_bounds(molecule, &spawner.palette, spawner.radius_settings.source);
+        scene_min = scene_min.min(min + *offset);
+        scene_max = scene_max.max(max + *offset);
+
+        spawner.spawn(index, molecule, *offset, &outline);
+    }
+
+    let MoleculeSpawner {
+        mut commands,
+        mut meshes,
+        mut materials,
+        ..
+    } = spawner;
+    commands.insert_resource(outline);
+
+    // Ground plane just below the lowest atom, hidden until toggled on
+    let (min, max) = (scene_min, scene_max);
+    let center = (min + max) / 2.0;
+    let plane_size = (max - min).max_element().max(1.0) * 3.0;
+    commands.spawn((
+        Mesh3d(meshes.add(Plane3d::default().mesh().size(plane_size, plane_size))),
+        MeshMaterial3d(materials.add(StandardMaterial {
+            base_color: Color::srgba(0.8, 0.8, 0.85, 0.5),
+            alpha_mode: AlphaMode::Blend,
+            perceptual_roughness: 1.0,
+            ..default()
+        })),
+        Transform::from_xyz(center.x, min.y - GROUND_PLANE_OFFSET, center.z),
+        if startup_view.ground_plane {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
+        },
+        GroundPlane,
+    ));
+
+    // Scale bar in the bottom-left corner, hidden until toggled on
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                left: Val::Px(20.0),
+                bottom: Val::Px(20.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(4.0),
+                ..default()
+            },
+            if startup_view.scale_bar {
+                Visibility::Visible
+            } else {
+                Visibility::Hidden
+            },
+            ScaleBar,
+        ))
+        .with_children(|bar| {
+            bar.spawn((
+                Text::new(""),
+                TextFont {
+                    font_size: 16.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+                ScaleBarLabel,
+            ));
+            bar.spawn((
+                Node {
+                    width: Val::Px(SCALE_BAR_MAX_WIDTH),
+                    height: Val::Px(4.0),
+                    ..default()
+                },
+                BackgroundColor(Color::WHITE),
+                ScaleBarLine,
+            ));
+        });
+
+    // Dipole readout in the top-right corner, hidden until toggled on
+    commands.spawn((
+        Text::new(""),
+        TextFont {
+            font_size: 16.0,
+            ..default()
+        },
+        TextColor(DIPOLE_COLOR),
+        Node {
+            position_type: PositionType::Absolute,
+            right: Val::Px(20.0),
+            top: Val::Px(20.0),
+            ..default()
+        },
+        Visibility::Hidden,
+        DipoleLabel,
+    ));
+
+    // Element legend in the top-left corner, filled in once toggled on
+    commands.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            left: Val::Px(20.0),
+            top: Val::Px(20.0),
+            flex_direction: FlexDirection::Column,
+            row_gap: Val::Px(4.0),
+            ..default()
+        },
+        Visibility::Hidden,
+        ElementLegend,
+    ));
+
+    // Box selection rectangle, shown while shift-dragging
+    commands.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            border: UiRect::all(Val::Px(1.0)),
+            ..default()
+        },
+        BorderColor::all(Color::srgb(1.0, 1.0, 0.3)),
+        BackgroundColor(Color::srgba(1.0, 1.0, 0.3, 0.1)),
+        Visibility::Hidden,
+        SelectionBox { start: Vec2::ZERO },
+    ));
+
+    // Measurement of the selected atoms, centered along the top edge
+    commands
+        .spawn(Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(20.0),
+            width: Val::Percent(100.0),
+            justify_content: JustifyContent::Center,
+            ..default()
+        })
+        .with_child((
+            Text::new(""),
+            TextFont {
+                font_size: 18.0,
+                ..default()
+            },
+            TextColor(Color::srgb(1.0, 1.0, 0.6)),
+            Visibility::Hidden,
+            MeasurementLabel,
+        ));
+
+    // Energy plot in the bottom-right corner, filled in for trajectories with energies
+    commands.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            right: Val::Px(20.0),
+            bottom: Val::Px(20.0),
+            flex_direction: FlexDirection::Column,
+            row_gap: Val::Px(4.0),
+            padding: UiRect::all(Val::Px(8.0)),
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.5)),
+        Visibility::Hidden,
+        EnergyPlot,
+    ));
+
+    // Axis tick labels, spawned once the axes are toggled on
+    commands.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            ..default()
+        },
+        Visibility::Hidden,
+        AxisTicks,
+    ));
+
+    // Point light
+    commands.spawn((
+        PointLight {
+            intensity: 2_000_000.0,
+            shadows_enabled: shadow_settings.enabled,
+            ..default()
+        },
+        Transform::from_xyz(10.0, 10.0, 10.0),
+    ));
+
+    // Ambient light
+    commands.spawn(AmbientLight {
+        color: Color::WHITE,
+        brightness: 200.0,
+        ..default()
+    });
+
+    // Camera
+    let camera_pos = calculate_camera_position(controller.rotation, controller.target, controller.distance);
+    let mut camera = commands.spawn((
+        Camera3d::default(),
+        controller.projection(),
+        Transform::from_translation(camera_pos).with_rotation(controller.rotation),
+    ));
+    // Bevy sizes the depth texture for OIT only on cameras that have it when spawned
+    if transparency.order_independent {
+        camera.insert((OrderIndependentTransparencySettings::default(), Msaa::Off));
+    }
+
+    println!("Molecular Viewer Controls:");
+    println!("  Left mouse drag: Rotate view (\\ toggles orbiting the camera vs. rotating the molecule)");
+    println!("  Scroll wheel: Zoom in/out");
+    println!("  Arrow keys: Pan view");
+    println!("  I/K, J/L, Q/E: Rotate view in fixed steps about the x, y, z axes");
+    println!("  Space: Toggle turntable spin (pauses while you interact)");
+    println!("  G: Toggle ground plane");
+    println!("  B: Toggle scale bar");
+    println!("  Y: Toggle dipole arrows (needs partial charges)");
+    println!("  N: Toggle measurement axes with Angstrom ticks");
+    println!("  U: Toggle element color legend");
+    println!("  H: Show/hide hydrogen atoms");
+    println!("  O: Toggle ambient occlusion");
+    println!("  F3: Cycle antialiasing (off, 2x, 4x, 8x MSAA)");
+    println!("  P: Toggle shadows");
+    println!("  F: Toggle left drag between rotating the view and moving the light");
+    println!("  T: Toggle atom outlines (- and = adjust thickness)");
+    println!("  3: Toggle red/cyan anaglyph stereo ([ and ] adjust eye separation)");
+    println!("  Shift+left drag: Add the atoms inside a box to the selection (Escape clears it)");
+    println!("    --box-filter limits it to atoms matching a selection, such as \"element C\"");
+    println!("    Selecting 2, 3, or 4 atoms shows their distance, angle, or dihedral, updated every frame");
+    println!("  Tab: Cycle which molecule the camera targets");
+    println!("  V: Show/hide the targeted molecule");
+    println!("  X: Mirror the targeted molecule (inverts chirality)");
+    println!("  Delete: Remove the selected atoms of the targeted molecule");
+    println!("  Ctrl+Z / Ctrl+Shift+Z: Undo / redo the last edit");
+    println!("  C: Cycle color scheme (element, uniform, cross-section, coordination, displacement with --overlay)");
+    println!("  ; and ': Adjust the coordination cutoff");
+    println!("  PageUp/PageDown: Move the cross-section plane (Home/End, Shift+PageUp/PageDown tilt it)");
+    println!("  R: Cycle atom radii (van der Waals, covalent, uniform)");
+    println!("  M: Cycle normal-mode animation (, and . adjust amplitude)");
+    println!("  F2: Save the current scene to {}", SCENE_FILE);
+    println!("  F5: Reload the palette file");
+    println!("  F4: Save the targeted molecule to {}", EDITED_FILE);
+    println!();
+    for (index, molecule) in molecules.molecules.iter().enumerate() {
+        println!("Loaded molecule {} with {} atoms", index, molecule.atoms.len());
+    }
+}
+
+fn camera_rotation(
+    mouse_button: Res<ButtonInput<MouseButton>>,
+    mouse_motion: Res<AccumulatedMouseMotion>,
+    light_drag: Res<LightDrag>,
+    box_query: Query<&Visibility, With<SelectionBox>>,
+    mut controller: ResMut<CameraController>,
+) {
+    let selecting = box_query.iter().any(|v| *v != Visibility::Hidden);
+
+    // VMD-style: left mouse button for rotation
+    if mouse_button.pressed(MouseButton::Left) && !light_drag.enabled && !selecting {
+        let delta = if controller.invert_rotation {
+            -mouse_motion.delta
+        } else {
+            mouse_motion.delta
+        };
+        let rotation = drag_rotation(controller.rotation, delta, controller.rotate_sensitivity);
+        controller.rotation = rotation;
+    }
+}
+
+fn toggle_invert_rotation(keyboard: Res<ButtonInput<KeyCode>>, mut controller: ResMut<CameraController>) {
+    if keyboard.just_pressed(KeyCode::Backslash) {
+        controller.invert_rotation = !controller.invert_rotation;
+        if controller.invert_rotation {
+            println!("Dragging now rotates the molecule");
+        } else {
+            println!("Dragging now orbits the camera");
+        }
+    }
+}
+
+fn toggle_turntable(keyboard: Res<ButtonInput<KeyCode>>, mut turntable: ResMut<Turntable>) {
+    if keyboard.just_pressed(KeyCode::Space) {
+        turntable.enabled = !turntable.enabled;
+        // Start spinning right away rather than waiting out the toggle key press
+        turntable.idle = TURNTABLE_RESUME_DELAY;
+        println!("Turntable {}", if turntable.enabled { "on" } else { "off" });
+    }
+}
+
+/// Yaw the view about the vertical axis while the turntable is on, pausing
+/// while the mouse or keyboard is in use
+fn spin_turntable(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mouse_button: Res<ButtonInput<MouseButton>>,
+    scroll: Res<AccumulatedMouseScroll>,
+    time: Res<Time>,
+    mut turntable: ResMut<Turntable>,
+    mut controller: ResMut<CameraController>,
+) {
+    if !turntable.enabled {
+        return;
+    }
+
+    let busy = mouse_button.get_pressed().next().is_some()
+        || scroll.delta != Vec2::ZERO
+        || keyboard.get_pressed().any(|key| *key != KeyCode::Space);
+    if busy {
+        turntable.idle = 0.0;
+        return;
+    }
+    turntable.idle += time.delta_secs();
+    if turntable.idle < TURNTABLE_RESUME_DELAY {
+        return;
+    }
+
+    let rotation = turntable_rotation(controller.rotation, turntable.rate * time.delta_secs());
+    controller.rotation = rotation;
+}
+
+/// Step the view through a full turn for `--render-turntable`, saving one
+/// PNG per step, then exit
+///
+/// Each frame waits for the previous image to be written, and frame `i` is
+/// always at `i / frames` of a turn from the starting view, so the images
+/// loop seamlessly however fast they render.
+fn capture_turntable(
+    mut commands: Commands,
+    mut capture: ResMut<TurntableCapture>,
+    mut controller: ResMut<CameraController>,
+    mut warmup: Local<u32>,
+    mut exit: MessageWriter<AppExit>,
+) {
+    if *warmup < TURNTABLE_WARMUP_FRAMES {
+        *warmup += 1;
+        return;
+    }
+    if capture.saved == capture.frames {
+        println!("Wrote {} turntable frames to {}", capture.frames, capture.dir.display());
+        exit.write(AppExit::Success);
+        return;
+    }
+    if capture.requested > capture.saved {
+        return;
+    }
+
+    let frame = capture.requested;
+    let angle = std::f32::consts::TAU * frame as f32 / capture.frames as f32;
+    controller.rotation = turntable_rotation(capture.start, angle);
+    let path = capture.dir.join(format!("frame_{:04}.png", frame));
+    commands
+        .spawn(Screenshot::primary_window())
+        .observe(save_to_disk(path))
+        .observe(|_: On<ScreenshotCaptured>, mut capture: ResMut<TurntableCapture>| capture.saved += 1);
+    capture.requested += 1;
+}
+
+fn toggle_light_drag(keyboard: Res<ButtonInput<KeyCode>>, mut light_drag: ResMut<LightDrag>) {
+    if keyboard.just_pressed(KeyCode::KeyF) {
+        light_drag.enabled = !light_drag.enabled;
+        if light_drag.enabled {
+            println!("Left drag now moves the light; press F to rotate the view again");
+        } else {
+            println!("Left drag rotates the view");
+        }
+    }
+}
+
+/// Orbit the point light around the camera target while light drag is on,
+/// with the same screen axes as view rotation
+fn light_rotation(
+    mouse_button: Res<ButtonInput<MouseButton>>,
+    mouse_motion: Res<AccumulatedMouseMotion>,
+    light_drag: Res<LightDrag>,
+    controller: Res<CameraController>,
+    box_query: Query<&Visibility, With<SelectionBox>>,
+    mut light_query: Query<&mut Transform, With<PointLight>>,
+) {
+    let selecting = box_query.iter().any(|v| *v != Visibility::Hidden);
+    if !light_drag.enabled || !mouse_button.pressed(MouseButton::Left) || selecting {
+        return;
+    }
+
+    let delta = mouse_motion.delta;
+    let up = controller.rotation * Vec3::Y;
+    let right = controller.rotation * Vec3::X;
+    let orbit = Quat::from_axis_angle(right, delta.y * controller.rotate_sensitivity)
+        * Quat::from_axis_angle(up, delta.x * controller.rotate_sensitivity);
+
+    for mut transform in light_query.iter_mut() {
+        transform.translation = controller.target + orbit * (transform.translation - controller.target);
+    }
+}
+
+fn camera_key_rotation(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut controller: ResMut<CameraController>,
+) {
+    let step = controller.rotate_step;
+    let mut angles = Vec3::ZERO;
+
+    // Signs match the mouse: J/L behave like dragging left/right, I/K like up/down
+    if keyboard.just_pressed(KeyCode::KeyI) {
+        angles.x += step;
+    }
+    if keyboard.just_pressed(KeyCode::KeyK) {
+        angles.x -= step;
+    }
+    if keyboard.just_pressed(KeyCode::KeyJ) {
+        angles.y += step;
+    }
+    if keyboard.just_pressed(KeyCode::KeyL) {
+        angles.y -= step;
+    }
+    if keyboard.just_pressed(KeyCode::KeyQ) {
+        angles.z += step;
+    }
+    if keyboard.just_pressed(KeyCode::KeyE) {
+        angles.z -= step;
+    }
+
+    if angles != Vec3::ZERO {
+        let rotation = step_rotation(controller.rotation, angles);
+        controller.rotation = rotation;
+    }
+}
+
+/// Pan with the arrow keys or WASD, continuously at a speed that scales
+/// with zoom distance, or by `pan_step` per key press
+fn camera_pan(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    time: Res<Time>,
+    mut controller: ResMut<CameraController>,
+) {
+    // Fixed steps move on each press so scripted figures are reproducible
+    let held = |keys: [KeyCode; 2]| match controller.pan_step {
+        Some(_) => keyboard.any_just_pressed(keys),
+        None => keyboard.any_pressed(keys),
+    };
+    let mut pan = Vec2::ZERO;
+
+    if held([KeyCode::ArrowLeft, KeyCode::KeyA]) {
+        pan.x -= 1.0;
+    }
+    if held([KeyCode::ArrowRight, KeyCode::KeyD]) {
+        pan.x += 1.0;
+    }
+    if held([KeyCode::ArrowUp, KeyCode::KeyW]) {
+        pan.y += 1.0;
+    }
+    if held([KeyCode::ArrowDown, KeyCode::KeyS]) {
+        pan.y -= 1.0;
+    }
+
+    if pan != Vec2::ZERO {
+        let amount = match controller.pan_step {
+            Some(step) => pan * step,
+            None => pan * pan_rate(controller.pan_speed, controller.distance) * time.delta_secs(),
+        };
+        let offset = pan_offset(controller.rotation, amount);
+        controller.target += offset;
+    }
+}
+
+fn camera_zoom(
+    scroll: Res<AccumulatedMouseScroll>,
+    mut controller: ResMut<CameraController>,
+) {
+    let distance = zoom_distance(controller.distance, scroll.delta.y, controller.zoom_speed);
+    controller.distance = distance;
 }
 
 fn update_camera(
     controller: Res<CameraController>,
-    mut camera_query: Query<&mut Transform, With<Camera3d>>,
+    stereo: Res<StereoSettings>,
+    mut camera_query: Query<(&mut Transform, Option<&StereoEye>), SceneCameraFilter>,
+) {
+    for (mut transform, eye) in camera_query.iter_mut() {
+        let mut pos = calculate_camera_position(controller.rotation, controller.target, controller.distance);
+        // Offset each eye sideways by half the separation
+        if let Some(StereoEye(side)) = eye {
+            pos += controller.rotation * Vec3::X * (side * stereo.eye_separation * 0.5);
+        }
+        transform.translation = pos;
+        transform.rotation = controller.rotation;
+    }
+}
+
+fn toggle_ground_plane(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut plane_query: Query<&mut Visibility, With<GroundPlane>>,
+) {
+    if keyboard.just_pressed(KeyCode::KeyG) {
+        for mut visibility in plane_query.iter_mut() {
+            *visibility = match *visibility {
+                Visibility::Hidden => Visibility::Visible,
+                _ => Visibility::Hidden,
+            };
+        }
+    }
+}
+
+fn toggle_scale_bar(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut bar_query: Query<&mut Visibility, With<ScaleBar>>,
+) {
+    if keyboard.just_pressed(KeyCode::KeyB) {
+        for mut visibility in bar_query.iter_mut() {
+            *visibility = match *visibility {
+                Visibility::Hidden => Visibility::Visible,
+                _ => Visibility::Hidden,
+            };
+        }
+    }
+}
+
+fn toggle_dipole(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut label_query: Query<&mut Visibility, With<DipoleLabel>>,
+) {
+    if keyboard.just_pressed(KeyCode::KeyY) {
+        for mut visibility in label_query.iter_mut() {
+            *visibility = match *visibility {
+                Visibility::Hidden => Visibility::Visible,
+                _ => Visibility::Hidden,
+            };
+        }
+    }
+}
+
+/// Draw each molecule's dipole as an arrow from its center of mass, one
+/// Angstrom per Debye, and show the targeted molecule's magnitude
+fn draw_dipoles(
+    molecules: Res<Molecules>,
+    mut label_query: Query<(&mut Text, &Visibility), With<DipoleLabel>>,
+    mut gizmos: Gizmos,
+) {
+    let Ok((mut text, visibility)) = label_query.single_mut() else {
+        return;
+    };
+    if *visibility == Visibility::Hidden {
+        return;
+    }
+
+    for (molecule, offset) in molecules.molecules.iter().zip(&molecules.offsets) {
+        let structure = &molecule.structure;
+        if let (Some(center), Some(dipole)) = (structure.center_of_mass(), structure.dipole()) {
+            let start = Vec3::from_array(center.map(|c| c as f32)) + *offset;
+            gizmos.arrow(start, start + Vec3::from_array(dipole.map(|d| d as f32)), DIPOLE_COLOR);
+        }
+    }
+
+    let focused = &molecules.molecules[molecules.focused].structure;
+    let readout = match focused.dipole() {
+        Some(dipole) => format!("Dipole: {:.2} D", dipole.iter().map(|d| d * d).sum::<f64>().sqrt()),
+        None => "Dipole: no partial charges".to_string(),
+    };
+    if text.0 != readout {
+        text.0 = readout;
+    }
+}
+
+/// Size the scale bar to a round length at the camera target's depth
+///
+/// Orthographic projections have the same scale at every depth; with a
+/// perspective projection the bar is exact only in the plane of the target.
+fn update_scale_bar(
+    controller: Res<CameraController>,
+    window: Single<&Window, With<PrimaryWindow>>,
+    camera_query: Query<&Projection, (SceneCameraFilter, Without<StereoEye>)>,
+    bar_query: Query<&Visibility, With<ScaleBar>>,
+    mut line_query: Query<&mut Node, With<ScaleBarLine>>,
+    mut label_query: Query<&mut Text, With<ScaleBarLabel>>,
+) {
+    if bar_query.iter().all(|v| *v == Visibility::Hidden) {
+        return;
+    }
+    let Some(projection) = camera_query.iter().next() else {
+        return;
+    };
+
+    // World-space height of the view at the target
+    let view_height = match projection {
+        Projection::Perspective(perspective) => 2.0 * controller.distance * (perspective.fov / 2.0).tan(),
+        Projection::Orthographic(orthographic) => orthographic.area.height(),
+        _ => return,
+    };
+    if view_height <= 0.0 || window.height() <= 0.0 {
+        return;
+    }
+    let pixels_per_angstrom = window.height() / view_height;
+
+    let (length, decimals) = round_length(SCALE_BAR_MAX_WIDTH / pixels_per_angstrom);
+
+    for mut node in line_query.iter_mut() {
+        node.width = Val::Px(length * pixels_per_angstrom);
+    }
+    for mut text in label_query.iter_mut() {
+        text.0 = format!("{:.*} Å", decimals, length);
+    }
+}
+
+/// Largest 1, 2, or 5 times a power of ten no longer than `max_length`,
+/// with the number of decimals needed to print it
+fn round_length(max_length: f32) -> (f32, usize) {
+    let exponent = max_length.log10().floor() as i32;
+    let magnitude = 10f32.powi(exponent);
+    let step = [5.0, 2.0, 1.0]
+        .into_iter()
+        .find(|&m| m * magnitude <= max_length)
+        .unwrap_or(1.0);
+    (step * magnitude, (-exponent).max(0) as usize)
+}
+
+fn toggle_element_legend(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut legend_query: Query<&mut Visibility, With<ElementLegend>>,
+) {
+    if keyboard.just_pressed(KeyCode::KeyU) {
+        for mut visibility in legend_query.iter_mut() {
+            *visibility = match *visibility {
+                Visibility::Hidden => Visibility::Visible,
+                _ => Visibility::Hidden,
+            };
+        }
+    }
+}
+
+/// Rebuild the legend when it is shown or when the targeted molecule, the
+/// palette, or the color scheme changes
+///
+/// Each element present is listed once, in order of first appearance, with
+/// the color it is drawn in. The coordination scheme does not color by
+/// element, so the legend names the scheme instead.
+fn update_element_legend(
+    mut commands: Commands,
+    settings: Res<ColorSettings>,
+    palette: Res<Palette>,
+    molecules: Res<Molecules>,
+    legend_query: Query<(Entity, Ref<Visibility>), With<ElementLegend>>,
+) {
+    let Ok((legend, visibility)) = legend_query.single() else {
+        return;
+    };
+    if *visibility == Visibility::Hidden
+        || !(visibility.is_changed() || settings.is_changed() || palette.is_changed() || molecules.is_changed())
+    {
+        return;
+    }
+
+    let mut elements: Vec<&str> = Vec::new();
+    for atom in &molecules.molecules[molecules.focused].atoms {
+        if !elements.contains(&atom.element.as_str()) {
+            elements.push(&atom.element);
+        }
+    }
+
+    let font = TextFont {
+        font_size: 16.0,
+        ..default()
+    };
+    commands.entity(legend).despawn_related::<Children>().with_children(|legend| {
+        if settings.scheme == ColorScheme::Coordination {
+            legend.spawn((Text::new("Colored by coordination number"), font.clone()));
+            return;
+        }
+        if settings.scheme == ColorScheme::Displacement {
+            legend.spawn((Text::new("Colored by displacement from the overlay"), font.clone()));
+            return;
+        }
+        for element in elements {
+            let color = match settings.scheme {
+                ColorScheme::Uniform(color) => color,
+                _ => get_atom_color(element, &palette),
+            };
+            legend
+                .spawn(Node {
+                    align_items: AlignItems::Center,
+                    column_gap: Val::Px(8.0),
+                    ..default()
+                })
+                .with_children(|row| {
+                    row.spawn((
+                        Node {
+                            width: Val::Px(14.0),
+                            height: Val::Px(14.0),
+                            ..default()
+                        },
+                        BackgroundColor(color),
+                    ));
+                    row.spawn((Text::new(element), font.clone(), TextColor(Color::WHITE)));
+                });
+        }
+    });
+}
+
+/// Redraw the energy plot when the targeted molecule or its frame changes
+///
+/// Frames are spread across the plot's width and joined by line segments,
+/// thinned to one point per pixel column for long trajectories. Frames
+/// without an energy break the line, and the frame shown gets a marker.
+fn update_energy_plot(
+    mut commands: Commands,
+    molecules: Res<Molecules>,
+    mut plot_query: Query<(Entity, &mut Visibility), With<EnergyPlot>>,
+) {
+    if !molecules.is_changed() {
+        return;
+    }
+    let Ok((plot, mut visibility)) = plot_query.single_mut() else {
+        return;
+    };
+
+    let molecule = &molecules.molecules[molecules.focused];
+    let energies = &molecule.frame_energies;
+    let known = || energies.iter().flatten().copied();
+    if known().count() < 2 {
+        *visibility = Visibility::Hidden;
+        return;
+    }
+    *visibility = Visibility::Visible;
+
+    let min = known().fold(f64::INFINITY, f64::min);
+    let max = known().fold(f64::NEG_INFINITY, f64::max);
+    // A flat trace is drawn through the middle of the plot
+    let range = if max > min { max - min } else { 1.0 };
+    let point = |frame: usize, energy: f64| {
+        let x = frame as f32 / (energies.len() - 1).max(1) as f32 * ENERGY_PLOT_SIZE.x;
+        let y = if max > min {
+            ((energy - min) / range) as f32 * ENERGY_PLOT_SIZE.y
+        } else {
+            ENERGY_PLOT_SIZE.y / 2.0
+        };
+        Vec2::new(x, ENERGY_PLOT_SIZE.y - y)
+    };
+    let dot = |center: Vec2, size: f32, color: Color| {
+        (
+            Node {
+                position_type: PositionType::Absolute,
+                left: Val::Px(center.x - size / 2.0),
+                top: Val::Px(center.y - size / 2.0),
+                width: Val::Px(size),
+                height: Val::Px(size),
+                ..default()
+            },
+            BackgroundColor(color),
+        )
+    };
+
+    // A thin node turned to run from one point to the next
+    let segment = |from: Vec2, to: Vec2| {
+        let (center, delta) = ((from + to) / 2.0, to - from);
+        let length = delta.length();
+        (
+            Node {
+                position_type: PositionType::Absolute,
+                left: Val::Px(center.x - length / 2.0),
+                top: Val::Px(center.y - 1.0),
+                width: Val::Px(length),
+                height: Val::Px(2.0),
+                ..default()
+            },
+            UiTransform::from_rotation(Rot2::radians(delta.y.atan2(delta.x))),
+            BackgroundColor(ENERGY_LINE_COLOR),
+        )
+    };
+
+    let font = TextFont {
+        font_size: 12.0,
+        ..default()
+    };
+    let step = (energies.len() as f32 / ENERGY_PLOT_SIZE.x).ceil().max(1.0) as usize;
+    commands.entity(plot).despawn_related::<Children>().with_children(|plot| {
+        plot.spawn((Text::new(format!("E max {:.4}", max)), font.clone()));
+        plot.spawn(Node {
+            width: Val::Px(ENERGY_PLOT_SIZE.x),
+            height: Val::Px(ENERGY_PLOT_SIZE.y),
+            ..default()
+        })
+        .with_children(|area| {
+            let sampled: Vec<(usize, Option<f64>)> = energies.iter().copied().enumerate().step_by(step).collect();
+            for pair in sampled.windows(2) {
+                if let [(a, Some(energy_a)), (b, Some(energy_b))] = *pair {
+                    area.spawn(segment(point(a, energy_a), point(b, energy_b)));
+                }
+            }
+            // Frames between two gaps have no segment, so each point gets a dot too
+            for (frame, energy) in sampled {
+                if let Some(energy) = energy {
+                    area.spawn(dot(point(frame, energy), 2.0, ENERGY_LINE_COLOR));
+                }
+            }
+            if let Some(energy) = energies[molecule.frame] {
+                area.spawn(dot(point(molecule.frame, energy), 8.0, ENERGY_MARKER_COLOR));
+            }
+        });
+        plot.spawn((Text::new(format!("E min {:.4}", min)), font.clone()));
+        plot.spawn((
+            Text::new(format!("Frame {} of {}", molecule.frame + 1, energies.len())),
+            font,
+        ));
+    });
+}
+
+fn toggle_hydrogens(keyboard: Res<ButtonInput<KeyCode>>, mut settings: ResMut<HydrogenSettings>) {
+    if keyboard.just_pressed(KeyCode::KeyH) {
+        settings.hidden = !settings.hidden;
+        println!("Hydrogens {}", if settings.hidden { "hidden" } else { "shown" });
+    }
+}
+
+/// Hide or show hydrogen atoms, including atoms respawned by edits or
+/// streamed frames; the atoms stay in the data
+fn apply_hydrogens(
+    settings: Res<HydrogenSettings>,
+    molecules: Res<Molecules>,
+    mut atom_query: Query<(Ref<AtomIndex>, &mut Visibility)>,
+) {
+    let visibility = if settings.hidden {
+        Visibility::Hidden
+    } else {
+        Visibility::Inherited
+    };
+
+    for (index, mut atom_visibility) in atom_query.iter_mut() {
+        if !settings.is_changed() && !index.is_added() {
+            continue;
+        }
+        // Atoms of a replaced molecule may linger until their despawn is applied
+        let Some(atom) = molecules.molecules.get(index.molecule).and_then(|m| m.atoms.get(index.atom)) else {
+            continue;
+        };
+        if atom.element.eq_ignore_ascii_case("H") {
+            atom_visibility.set_if_neq(visibility);
+        }
+    }
+}
+
+/// Atoms as placed in the scene, and the camera that projects them to the
+/// screen
+#[derive(SystemParam)]
+struct ScreenAtoms<'w, 's> {
+    molecules: Res<'w, Molecules>,
+    camera_query: Query<'w, 's, (&'static Camera, &'static GlobalTransform), (SceneCameraFilter, Without<StereoEye>)>,
+    atom_query: Query<'w, 's, (&'static AtomIndex, &'static GlobalTransform, &'static InheritedVisibility)>,
+}
+
+/// Shift-drag a rectangle to add the visible atoms inside it to the
+/// selection; Escape clears the selection
+///
+/// Atoms are tested by the screen position of their centers, and with
+/// --box-filter only atoms matching it are added. Each drag reports how
+/// many atoms of each element it added.
+fn box_select(
+    mouse_button: Res<ButtonInput<MouseButton>>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    window: Single<&Window, With<PrimaryWindow>>,
+    screen: ScreenAtoms,
+    mut selected: ResMut<Selected>,
+    filter: Res<BoxFilter>,
+    mut box_query: Query<(&mut SelectionBox, &mut Node, &mut Visibility)>,
+) {
+    if keyboard.just_pressed(KeyCode::Escape) && !selected.0.is_empty() {
+        selected.0.clear();
+        println!("Cleared the selection");
+    }
+    let Ok((mut selection_box, mut node, mut visibility)) = box_query.single_mut() else {
+        return;
+    };
+    let cursor = window.cursor_position();
+
+    let shift = keyboard.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    if let Some(cursor) = cursor
+        && shift
+        && mouse_button.just_pressed(MouseButton::Left)
+    {
+        selection_box.start = cursor;
+        *visibility = Visibility::Visible;
+    }
+    if *visibility == Visibility::Hidden {
+        return;
+    }
+
+    // A drag released outside the window selects nothing
+    let Some(cursor) = cursor else {
+        if !mouse_button.pressed(MouseButton::Left) {
+            *visibility = Visibility::Hidden;
+        }
+        return;
+    };
+    let rect = Rect::from_corners(selection_box.start, cursor);
+    node.left = Val::Px(rect.min.x);
+    node.top = Val::Px(rect.min.y);
+    node.width = Val::Px(rect.width());
+    node.height = Val::Px(rect.height());
+
+    if mouse_button.pressed(MouseButton::Left) {
+        return;
+    }
+    *visibility = Visibility::Hidden;
+    let Some((camera, camera_transform)) = screen.camera_query.iter().next() else {
+        return;
+    };
+
+    // Matching atoms of each molecule, in ascending order
+    let matching: Option<Vec<Vec<usize>>> = filter
+        .0
+        .as_ref()
+        .map(|filter| screen.molecules.molecules.iter().map(|m| filter.evaluate(&m.structure)).collect());
+    let passes = |index: &AtomIndex| {
+        matching
+            .as_ref()
+            .is_none_or(|m| m.get(index.molecule).is_some_and(|atoms| atoms.binary_search(&index.atom).is_ok()))
+    };
+
+    let mut added: BTreeMap<&str, usize> = BTreeMap::new();
+    for (index, transform, inherited) in screen.atom_query.iter() {
+        if !inherited.get() || !passes(index) {
+            continue;
+        }
+        let Ok(point) = camera.world_to_viewport(camera_transform, transform.translation()) else {
+            continue;
+        };
+        if rect.contains(point) && selected.0.insert((index.molecule, index.atom)) {
+            let element = &screen.molecules.molecules[index.molecule].atoms[index.atom].element;
+            *added.entry(element).or_default() += 1;
+        }
+    }
+
+    let counts: Vec<String> = added.iter().map(|(element, count)| format!("{} {}", element, count)).collect();
+    println!(
+        "Selected {} more atoms ({}), {} in total",
+        added.values().sum::<usize>(),
+        if counts.is_empty() { "none".to_string() } else { counts.join(", ") },
+        selected.0.len()
+    );
+}
+
+/// Make selected atoms glow, including atoms respawned by edits or streamed
+/// frames
+fn apply_selection(
+    selected: Res<Selected>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    atom_query: Query<(Ref<AtomIndex>, &MeshMaterial3d<StandardMaterial>)>,
+) {
+    for (index, material) in atom_query.iter() {
+        if !selected.is_changed() && !index.is_added() {
+            continue;
+        }
+        let emissive = if selected.0.contains(&(index.molecule, index.atom)) {
+            SELECTION_GLOW
+        } else {
+            LinearRgba::BLACK
+        };
+        // Only touch materials that change, so new atoms outside the selection aren't re-uploaded
+        if materials.get(&material.0).is_some_and(|m| m.emissive != emissive)
+            && let Some(material) = materials.get_mut(&material.0)
+        {
+            material.emissive = emissive;
+        }
+    }
+}
+
+/// Drop selected atoms that a new frame or an edit removed; the rest keep
+/// their selection, since it follows atom indices
+fn prune_selection(molecules: Res<Molecules>, mut selected: ResMut<Selected>) {
+    if !molecules.is_changed() {
+        return;
+    }
+    let exists = |&(molecule, atom): &(usize, usize)| molecules.molecules.get(molecule).is_some_and(|m| atom < m.atoms.len());
+    if !selected.0.iter().all(exists) {
+        selected.0.retain(exists);
+    }
+}
+
+/// Show the distance, angle, or dihedral between two, three, or four
+/// selected atoms of one molecule, recomputed as frames arrive
+///
+/// Box selection records no order, so atoms are measured in index order,
+/// except that an angle is taken at the atom nearest the other two. In a
+/// periodic cell each bond runs to the nearest image of the next atom.
+fn update_measurement(
+    selected: Res<Selected>,
+    molecules: Res<Molecules>,
+    mut label_query: Query<(&mut Text, &mut Visibility), With<MeasurementLabel>>,
+) {
+    if !selected.is_changed() && !molecules.is_changed() {
+        return;
+    }
+    let Ok((mut text, mut visibility)) = label_query.single_mut() else {
+        return;
+    };
+
+    let atoms: Vec<usize> = selected.0.iter().map(|&(_, atom)| atom).collect();
+    // Atoms of a replaced molecule may still be selected until they are pruned
+    let structure = match selected.0.first() {
+        Some(&(first, _)) if selected.0.iter().all(|&(molecule, _)| molecule == first) => molecules
+            .molecules
+            .get(first)
+            .map(|m| &m.structure)
+            .filter(|s| atoms.iter().all(|&atom| atom < s.atoms.len())),
+        _ => None,
+    };
+    let measurement = structure.and_then(|structure| {
+        let name = |indices: &[usize]| {
+            let names: Vec<String> = indices.iter().map(|&i| format!("{}{}", structure.atoms[i].element, i + 1)).collect();
+            names.join("-")
+        };
+        let lattice = structure.lattice();
+        let lattice = lattice.as_ref();
+        let distance = |i, j| structure.min_image_distance(i, j, lattice);
+        let measurement = match *atoms.as_slice() {
+            [i, j] => Some(format!("{}: {:.3} Angstrom", name(&[i, j]), distance(i, j))),
+            [a, b, c] => {
+                let span = |[i, j, k]: [usize; 3]| distance(i, j) + distance(j, k);
+                let [i, j, k] = [[b, a, c], [a, b, c], [a, c, b]]
+                    .into_iter()
+                    .min_by(|x, y| span(*x).total_cmp(&span(*y)))?;
+                let angle = structure.angle(i, j, k, lattice)?;
+                Some(format!("{}: {:.2} degrees", name(&[i, j, k]), angle))
+            }
+            [i, j, k, l] => {
+                let dihedral = structure.dihedral(i, j, k, l, lattice)?;
+                Some(format!("{} dihedral: {:.2} degrees", name(&[i, j, k, l]), dihedral))
+            }
+            _ => None,
+        }?;
+        Some(match lattice {
+            Some(_) => format!("{} (minimum-image in the periodic cell)", measurement),
+            None => measurement,
+        })
+    });
+
+    // Noted once per selection rather than for every streamed frame
+    if let Some(&(molecule, _)) = selected.0.first()
+        && selected.is_changed()
+        && measurement.is_some()
+        && structure.is_some_and(|s| s.lattice().is_some())
+    {
+        println!("Using minimum-image distances in the periodic cell of molecule {}", molecule);
+    }
+    match measurement {
+        Some(measurement) => {
+            text.0 = measurement;
+            *visibility = Visibility::Inherited;
+        }
+        None => *visibility = Visibility::Hidden,
+    }
+}
+
+fn toggle_axis_ticks(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut ticks_query: Query<&mut Visibility, With<AxisTicks>>,
+) {
+    if keyboard.just_pressed(KeyCode::KeyN) {
+        for mut visibility in ticks_query.iter_mut() {
+            *visibility = match *visibility {
+                Visibility::Hidden => Visibility::Visible,
+                _ => Visibility::Hidden,
+            };
+        }
+    }
+}
+
+/// Draw x, y, and z axes through the targeted molecule's centroid, with
+/// ticks at a round spacing labeled by their distance from the centroid
+fn draw_axis_ticks(
+    mut commands: Commands,
+    molecules: Res<Molecules>,
+    camera_query: Query<(&Camera, &GlobalTransform), (SceneCameraFilter, Without<StereoEye>)>,
+    ticks_query: Query<(Entity, &Visibility), With<AxisTicks>>,
+    mut label_query: Query<AxisTickLabelData, (With<AxisTickLabel>, Without<AxisTicks>)>,
+    mut gizmos: Gizmos,
+) {
+    let Ok((overlay, visibility)) = ticks_query.single() else {
+        return;
+    };
+    if *visibility == Visibility::Hidden {
+        return;
+    }
+    let Some((camera, camera_transform)) = camera_query.iter().next() else {
+        return;
+    };
+    let molecule = &molecules.molecules[molecules.focused];
+    if molecule.atoms.is_empty() {
+        return;
+    }
+
+    // Each axis reaches just past the farthest atom along it
+    let offset = molecules.offsets[molecules.focused];
+    let center = molecule_center(molecule) + offset;
+    let half_lengths = molecule
+        .atoms
+        .iter()
+        .map(|a| (a.position + offset - center).abs())
+        .fold(Vec3::ZERO, Vec3::max)
+        + Vec3::splat(AXIS_MARGIN);
+    let (spacing, decimals) = round_length(half_lengths.max_element() / AXIS_TICKS_PER_SIDE);
+
+    let mut ticks = vec![(center, "0".to_string())];
+    for (direction, half_length) in [(Vec3::X, half_lengths.x), (Vec3::Y, half_lengths.y), (Vec3::Z, half_lengths.z)] {
+        gizmos.line(center - direction * half_length, center + direction * half_length, AXIS_TICK_COLOR);
+        let across = direction.any_orthonormal_vector() * spacing * 0.1;
+        let count = (half_length / spacing).floor() as i32;
+        for step in (-count..=count).filter(|&s| s != 0) {
+            let distance = step as f32 * spacing;
+            let point = center + direction * distance;
+            gizmos.line(point - across, point + across, AXIS_TICK_COLOR);
+            ticks.push((point, format!("{:.*}", decimals, distance)));
+        }
+    }
+
+    // Labels are respawned when the tick count changes and placed next frame
+    if label_query.iter().len() != ticks.len() {
+        commands.entity(overlay).despawn_related::<Children>().with_children(|parent| {
+            for _ in &ticks {
+                parent.spawn((
+                    Text::new(""),
+                    TextFont {
+                        font_size: 12.0,
+                        ..default()
+                    },
+                    TextColor(AXIS_TICK_COLOR),
+                    Node {
+                        position_type: PositionType::Absolute,
+                        ..default()
+                    },
+                    AxisTickLabel,
+                ));
+            }
+        });
+        return;
+    }
+
+    for ((mut text, mut node, mut label_visibility), (point, label)) in label_query.iter_mut().zip(ticks) {
+        match camera.world_to_viewport(camera_transform, point) {
+            Ok(position) => {
+                node.left = Val::Px(position.x + 4.0);
+                node.top = Val::Px(position.y);
+                *label_visibility = Visibility::Inherited;
+            }
+            Err(_) => *label_visibility = Visibility::Hidden,
+        }
+        if text.0 != label {
+            text.0 = label;
+        }
+    }
+}
+
+/// Settings recorded when the scene is saved
+#[derive(SystemParam)]
+struct SceneState<'w> {
+    sources: Res<'w, SceneSources>,
+    controller: Res<'w, CameraController>,
+    color_settings: Res<'w, ColorSettings>,
+    radius_settings: Res<'w, RadiusSettings>,
+    ao_settings: Res<'w, AmbientOcclusionSettings>,
+    outline_settings: Res<'w, OutlineSettings>,
+    shadow_settings: Res<'w, ShadowSettings>,
+    stereo_settings: Res<'w, StereoSettings>,
+}
+
+/// Write the current view and settings to the scene file, to reload with --scene
+fn save_scene_on_key(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    state: SceneState,
+    plane_query: Query<&Visibility, With<GroundPlane>>,
+    bar_query: Query<&Visibility, With<ScaleBar>>,
+) {
+    if !keyboard.just_pressed(KeyCode::F2) {
+        return;
+    }
+
+    let shown = |visibility: Option<&Visibility>| visibility.is_some_and(|v| *v != Visibility::Hidden);
+    let scene = SceneConfig {
+        inputs: state.sources.inputs.clone(),
+        palette: state.sources.palette.clone(),
+        color_scheme: Some(
+            match state.color_settings.scheme {
+                ColorScheme::Element => "element",
+                ColorScheme::Uniform(_) => "uniform",
+                ColorScheme::CrossSection => "cross_section",
+                ColorScheme::Coordination => "coordination",
+                ColorScheme::Displacement => "displacement",
+            }
+            .to_string(),
+        ),
+        uniform_color: Some(Srgba::from(state.color_settings.uniform_color).to_hex()),
+        radii: Some(
+            match state.radius_settings.source {
+                RadiusSource::VanDerWaals => "vdw",
+                RadiusSource::Covalent => "covalent",
+                RadiusSource::Uniform(_) => "uniform",
+            }
+            .to_string(),
+        ),
+        uniform_radius: Some(state.radius_settings.uniform_radius),
+        camera: Some(SceneCamera {
+            rotation: state.controller.rotation.to_array(),
+            distance: state.controller.distance,
+            target: state.controller.target.to_array(),
+        }),
+        ambient_occlusion: Some(state.ao_settings.enabled),
+        ambient_occlusion_strength: Some(state.ao_settings.strength),
+        outlines: Some(state.outline_settings.enabled),
+        outline_thickness: Some(state.outline_settings.thickness),
+        shadows: Some(state.shadow_settings.enabled),
+        ground_plane: Some(shown(plane_query.iter().next())),
+        scale_bar: Some(shown(bar_query.iter().next())),
+        stereo: Some(state.stereo_settings.enabled),
+    };
+
+    match save_scene(SCENE_FILE, &scene) {
+        Ok(()) => println!("Saved scene to {}", SCENE_FILE),
+        Err(e) => eprintln!("Failed to save scene: {}", e),
+    }
+}
+
+/// Write the targeted molecule, with any edits, as XYZ
+fn save_molecule_on_key(keyboard: Res<ButtonInput<KeyCode>>, molecules: Res<Molecules>) {
+    if !keyboard.just_pressed(KeyCode::F4) {
+        return;
+    }
+
+    let molecule = &molecules.molecules[molecules.focused].structure;
+    let result = File::create(EDITED_FILE).and_then(|file| write_xyz(molecule, BufWriter::new(file)));
+    match result {
+        Ok(()) => println!("Saved {} atoms to {}", molecule.atoms.len(), EDITED_FILE),
+        Err(e) => eprintln!("Failed to save {}: {}", EDITED_FILE, e),
+    }
+}
+
+fn toggle_ambient_occlusion(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut settings: ResMut<AmbientOcclusionSettings>,
+) {
+    if keyboard.just_pressed(KeyCode::KeyO) {
+        settings.enabled = !settings.enabled;
+    }
+}
+
+fn cycle_antialiasing(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    ao_settings: Res<AmbientOcclusionSettings>,
+    transparency: Res<TransparencySettings>,
+    mut settings: ResMut<AntialiasSettings>,
+) {
+    if keyboard.just_pressed(KeyCode::F3) && transparency.order_independent {
+        println!("Antialiasing is unavailable with order-independent transparency (--oit)");
+    } else if keyboard.just_pressed(KeyCode::F3) {
+        settings.msaa = match settings.msaa {
+            Msaa::Off => Msaa::Sample2,
+            Msaa::Sample2 => Msaa::Sample4,
+            Msaa::Sample4 => Msaa::Sample8,
+            Msaa::Sample8 => Msaa::Off,
+        };
+        let level = match settings.msaa {
+            Msaa::Off => "off".to_string(),
+            msaa => format!("{}x MSAA", msaa.samples()),
+        };
+        if ao_settings.enabled {
+            println!("Antialiasing set to {}; it applies once ambient occlusion is off", level);
+        } else {
+            println!("Antialiasing set to {}", level);
+        }
+    }
+}
+
+/// Sync the camera's SSAO and MSAA components with the current settings
+fn apply_ambient_occlusion(
+    mut commands: Commands,
+    settings: Res<AmbientOcclusionSettings>,
+    antialias: Res<AntialiasSettings>,
+    camera_query: Query<Entity, SceneCameraFilter>,
+) {
+    if !settings.is_changed() && !antialias.is_changed() {
+        return;
+    }
+
+    for camera in camera_query.iter() {
+        if settings.enabled {
+            // SSAO is incompatible with MSAA
+            commands.entity(camera).insert((
+                ScreenSpaceAmbientOcclusion {
+                    quality_level: settings.quality,
+                    constant_object_thickness: settings.strength,
+                },
+                Msaa::Off,
+            ));
+        } else {
+            commands
+                .entity(camera)
+                .remove::<ScreenSpaceAmbientOcclusion>()
+                .insert(antialias.msaa);
+        }
+    }
+}
+
+fn toggle_shadows(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut settings: ResMut<ShadowSettings>,
+) {
+    if keyboard.just_pressed(KeyCode::KeyP) {
+        settings.enabled = !settings.enabled;
+    }
+}
+
+/// Sync shadow casting on the scene lights with the current settings
+fn apply_shadows(settings: Res<ShadowSettings>, mut light_query: Query<&mut PointLight>) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    for mut light in light_query.iter_mut() {
+        light.shadows_enabled = settings.enabled;
+    }
+}
+
+fn toggle_outlines(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut settings: ResMut<OutlineSettings>,
+) {
+    if keyboard.just_pressed(KeyCode::KeyT) {
+        settings.enabled = !settings.enabled;
+    }
+    if keyboard.just_pressed(KeyCode::Minus) {
+        settings.thickness = (settings.thickness - 0.01).max(0.0);
+    }
+    if keyboard.just_pressed(KeyCode::Equal) {
+        settings.thickness += 0.01;
+    }
+}
+
+/// Sync outline visibility and width with the current settings
+fn apply_outlines(
+    settings: Res<OutlineSettings>,
+    mut outline_query: Query<(&AtomOutline, &mut Transform, &mut Visibility)>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    for (outline, mut transform, mut visibility) in outline_query.iter_mut() {
+        transform.scale = Vec3::splat(outline.radius + settings.thickness);
+        *visibility = if settings.enabled {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
+fn molecule_focus_controls(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut molecules: ResMut<Molecules>,
+    mut controller: ResMut<CameraController>,
+    mut root_query: Query<(&MoleculeRoot, &mut Visibility)>,
+) {
+    if keyboard.just_pressed(KeyCode::Tab) {
+        let focused = (molecules.focused + 1) % molecules.molecules.len();
+        molecules.focused = focused;
+        controller.target = molecule_center(&molecules.molecules[focused]) + molecules.offsets[focused];
+    }
+
+    if keyboard.just_pressed(KeyCode::KeyV) {
+        for (root, mut visibility) in root_query.iter_mut() {
+            if root.0 == molecules.focused {
+                *visibility = match *visibility {
+                    Visibility::Hidden => Visibility::Inherited,
+                    _ => Visibility::Hidden,
+                };
+            }
+        }
+    }
+}
+
+/// Reflect the targeted molecule through the plane through its center normal
+/// to the mirror axis
+fn mirror_controls(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mirror_axis: Res<MirrorAxis>,
+    mut molecules: ResMut<Molecules>,
+    mut animation: ResMut<ModeAnimation>,
+    mut history: ResMut<EditHistory>,
+    mut atom_query: Query<(&AtomIndex, &mut Transform)>,
+) {
+    if !keyboard.just_pressed(KeyCode::KeyX) {
+        return;
+    }
+
+    history.push(molecules.focused, &molecules, &animation);
+    let axis = mirror_axis.0;
+    let normal = match axis {
+        Axis::X => Vec3::X,
+        Axis::Y => Vec3::Y,
+        Axis::Z => Vec3::Z,
+    };
+    let focused = molecules.focused;
+    let molecule = &mut molecules.molecules[focused];
+    let center = molecule_center(molecule);
+
+    // Mirror about the center rather than the origin so the molecule stays in place
+    let shift = (2.0 * center.dot(normal) * normal).as_dvec3();
+    molecule.structure.mirror(axis);
+    molecule.structure.translate(shift.to_array());
+    for atom in &mut molecule.atoms {
+        atom.position -= 2.0 * (atom.position - center).dot(normal) * normal;
+    }
+
+    for (index, mut transform) in atom_query.iter_mut() {
+        if index.molecule == focused {
+            transform.translation = molecule.atoms[index.atom].position;
+        }
+    }
+
+    // Modes only animate the first molecule
+    if focused == 0 {
+        for mode in &mut animation.modes {
+            mode.mirror(axis);
+        }
+    }
+
+    println!("Mirrored molecule {} along the {} axis; chirality is inverted", focused, axis);
+}
+
+/// Remove the selected atoms of the targeted molecule with Delete,
+/// respawning it; Ctrl+Z brings them back
+fn delete_selected_atoms(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut edits: EditState,
+    mut selected: ResMut<Selected>,
+    mut spawner: MoleculeSpawner,
+    outline: Res<OutlineAssets>,
+    mut color_settings: ResMut<ColorSettings>,
+    root_query: Query<(Entity, &MoleculeRoot)>,
+) {
+    if !keyboard.just_pressed(KeyCode::Delete) {
+        return;
+    }
+
+    let EditState { history, molecules, animation } = &mut edits;
+    let index = molecules.focused;
+    let atoms: Vec<usize> = selected.0.iter().filter(|(molecule, _)| *molecule == index).map(|(_, atom)| *atom).collect();
+    if atoms.is_empty() {
+        println!("No atoms of molecule {} are selected", index);
+        return;
+    }
+
+    history.push(index, molecules, animation);
+    let mut structure = molecules.molecules[index].structure.clone();
+    let removed = structure.remove_atoms(&atoms);
+    // Modes only animate the first molecule
+    if index == 0 {
+        for mode in &mut animation.modes {
+            mode.remove_atoms(&atoms);
+        }
+    }
+    selected.0.retain(|(molecule, _)| *molecule != index);
+
+    for (entity, root) in root_query.iter() {
+        if root.0 == index {
+            spawner.commands.entity(entity).despawn();
+        }
+    }
+    let mut molecule = render_molecule(structure);
+    // Edits don't change which frame is shown
+    molecule.frame_energies = std::mem::take(&mut molecules.molecules[index].frame_energies);
+    molecule.frame = molecules.molecules[index].frame;
+    spawner.spawn(index, &molecule, molecules.offsets[index], &outline);
+    molecules.molecules[index] = molecule;
+    // New materials start with element colors
+    color_settings.set_changed();
+
+    println!("Deleted {} atoms from molecule {}", removed, index);
+}
+
+/// Resources an undo or redo restores
+#[derive(SystemParam)]
+struct EditState<'w> {
+    history: ResMut<'w, EditHistory>,
+    molecules: ResMut<'w, Molecules>,
+    animation: ResMut<'w, ModeAnimation>,
+}
+
+/// Step back (Ctrl+Z) or forward (Ctrl+Shift+Z) through the edit history,
+/// respawning the restored molecule
+fn undo_controls(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut edits: EditState,
+    mut spawner: MoleculeSpawner,
+    outline: Res<OutlineAssets>,
+    mut color_settings: ResMut<ColorSettings>,
+    root_query: Query<(Entity, &MoleculeRoot)>,
+) {
+    let control = keyboard.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    if !control || !keyboard.just_pressed(KeyCode::KeyZ) {
+        return;
+    }
+
+    let redo = keyboard.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    let EditState { history, molecules, animation } = &mut edits;
+    let restored = if redo { history.redo.pop() } else { history.undo.pop_back() };
+    let Some(snapshot) = restored else {
+        println!("Nothing to {}", if redo { "redo" } else { "undo" });
+        return;
+    };
+
+    // The current state goes on the opposite stack so the step can be reversed
+    let current = Snapshot::capture(snapshot.molecule, molecules, animation);
+    if redo {
+        history.undo.push_back(current);
+    } else {
+        history.redo.push(current);
+    }
+
+    let index = snapshot.molecule;
+    for (entity, root) in root_query.iter() {
+        if root.0 == index {
+            spawner.commands.entity(entity).despawn();
+        }
+    }
+    let mut molecule = render_molecule(snapshot.structure);
+    // Edits don't change which frame is shown
+    molecule.frame_energies = std::mem::take(&mut molecules.molecules[index].frame_energies);
+    molecule.frame = molecules.molecules[index].frame;
+    spawner.spawn(index, &molecule, molecules.offsets[index], &outline);
+    molecules.molecules[index] = molecule;
+    if let Some(modes) = snapshot.modes {
+        animation.modes = modes;
+    }
+    // New materials start with element colors
+    color_settings.set_changed();
+
+    println!("{} the last edit to molecule {}", if redo { "Redid" } else { "Undid" }, index);
+}
+
+fn toggle_color_scheme(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    reference: Res<ReferenceStructure>,
+    mut settings: ResMut<ColorSettings>,
+) {
+    if keyboard.just_pressed(KeyCode::KeyC) {
+        settings.scheme = match settings.scheme {
+            ColorScheme::Element => ColorScheme::Uniform(settings.uniform_color),
+            ColorScheme::Uniform(_) => ColorScheme::CrossSection,
+            ColorScheme::CrossSection => ColorScheme::Coordination,
+            // Displacement needs a reference to measure from
+            ColorScheme::Coordination if reference.0.is_some() => ColorScheme::Displacement,
+            ColorScheme::Coordination | ColorScheme::Displacement => ColorScheme::Element,
+        };
+    }
+}
+
+fn coordination_cutoff_controls(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut settings: ResMut<ColorSettings>,
+) {
+    let mut cutoff = settings.coordination_cutoff;
+    if keyboard.just_pressed(KeyCode::Semicolon) {
+        cutoff = (cutoff - COORDINATION_CUTOFF_STEP).max(COORDINATION_CUTOFF_STEP);
+    }
+    if keyboard.just_pressed(KeyCode::Quote) {
+        cutoff += COORDINATION_CUTOFF_STEP;
+    }
+    if cutoff != settings.coordination_cutoff {
+        settings.coordination_cutoff = cutoff;
+        println!("Coordination cutoff: {:.1} Angstrom", cutoff);
+    }
+}
+
+/// Move the cutting plane along its normal and tilt it relative to the view
+///
+/// PageUp/PageDown move the plane, or with Shift held tilt it about the
+/// view's x axis; Home/End tilt it about the view's y axis.
+fn cutting_plane_controls(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    controller: Res<CameraController>,
+    mut plane: ResMut<CuttingPlane>,
 ) {
-    for mut transform in camera_query.iter_mut() {
-        let pos = calculate_camera_position(&controller, controller.target);
-        transform.translation = pos;
-        transform.rotation = controller.rotation;
+    let step = controller.rotate_step;
+    let shift = keyboard.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    let mut rotation = Quat::IDENTITY;
+    let mut offset = 0.0;
+
+    if keyboard.just_pressed(KeyCode::PageUp) {
+        if shift {
+            rotation = Quat::from_axis_angle(controller.rotation * Vec3::X, step) * rotation;
+        } else {
+            offset += CUTTING_PLANE_STEP;
+        }
+    }
+    if keyboard.just_pressed(KeyCode::PageDown) {
+        if shift {
+            rotation = Quat::from_axis_angle(controller.rotation * Vec3::X, -step) * rotation;
+        } else {
+            offset -= CUTTING_PLANE_STEP;
+        }
+    }
+    if keyboard.just_pressed(KeyCode::Home) {
+        rotation = Quat::from_axis_angle(controller.rotation * Vec3::Y, step) * rotation;
+    }
+    if keyboard.just_pressed(KeyCode::End) {
+        rotation = Quat::from_axis_angle(controller.rotation * Vec3::Y, -step) * rotation;
+    }
+
+    if offset != 0.0 || rotation != Quat::IDENTITY {
+        let normal = (rotation * plane.normal).normalize();
+        plane.normal = normal;
+        plane.point += normal * offset;
+        println!(
+            "Cutting plane through ({:.2}, {:.2}, {:.2}) with normal ({:.2}, {:.2}, {:.2})",
+            plane.point.x, plane.point.y, plane.point.z, normal.x, normal.y, normal.z
+        );
+    }
+}
+
+/// Recolor the existing atom materials for the current color scheme
+fn apply_color_scheme(
+    settings: Res<ColorSettings>,
+    plane: Res<CuttingPlane>,
+    molecules: Res<Molecules>,
+    reference: Res<ReferenceStructure>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    atom_query: Query<(&AtomIndex, &AtomBaseColor, &MeshMaterial3d<StandardMaterial>)>,
+) {
+    let plane_moved = plane.is_changed() && settings.scheme == ColorScheme::CrossSection;
+    // Streamed frames and edits move atoms relative to the reference
+    let atoms_moved = molecules.is_changed() && settings.scheme == ColorScheme::Displacement;
+    if !settings.is_changed() && !plane_moved && !atoms_moved {
+        return;
+    }
+
+    // Displacements of the first molecule and the largest one, if the
+    // reference still matches it
+    let displacement = match &reference.0 {
+        Some(reference) if settings.scheme == ColorScheme::Displacement => {
+            molecules.molecules[0].structure.displacement(reference).ok().map(|moved| {
+                let max = moved.iter().copied().fold(0.0, f64::max);
+                (moved, max)
+            })
+        }
+        _ => None,
+    };
+
+    // Counts and the largest count for each molecule
+    let coordination: Vec<(Vec<usize>, usize)> = if settings.scheme == ColorScheme::Coordination {
+        molecules
+            .molecules
+            .iter()
+            .map(|m| {
+                let numbers = m.structure.coordination_numbers(settings.coordination_cutoff as f64);
+                let max = numbers.iter().copied().max().unwrap_or(0);
+                (numbers, max)
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    for (index, base, material) in atom_query.iter() {
+        if let Some(material) = materials.get_mut(&material.0) {
+            material.base_color = match settings.scheme {
+                ColorScheme::Element => base.0,
+                // Keep any opacity set by a style override
+                ColorScheme::Uniform(color) => {
+                    let color = if molecules.molecules[index.molecule].atoms[index.atom].frozen {
+                        frozen_color(color)
+                    } else {
+                        color
+                    };
+                    color.with_alpha(base.0.alpha())
+                }
+                ColorScheme::CrossSection => {
+                    let position = molecules.molecules[index.molecule].atoms[index.atom].position
+                        + molecules.offsets[index.molecule];
+                    if (position - plane.point).dot(plane.normal) < 0.0 {
+                        base.0.mix(&CROSS_SECTION_TINT, 0.6).with_alpha(base.0.alpha())
+                    } else {
+                        base.0
+                    }
+                }
+                ColorScheme::Coordination => {
+                    let (numbers, max) = &coordination[index.molecule];
+                    let fraction = if *max > 0 {
+                        numbers[index.atom] as f32 / *max as f32
+                    } else {
+                        0.0
+                    };
+                    Color::hsl(240.0 * fraction, 0.8, 0.5).with_alpha(base.0.alpha())
+                }
+                ColorScheme::Displacement => match &displacement {
+                    Some((moved, max)) if index.molecule == 0 => {
+                        let fraction = if *max > 0.0 { moved[index.atom] / max } else { 0.0 };
+                        STILL_COLOR.mix(&MOVED_COLOR, fraction as f32).with_alpha(base.0.alpha())
+                    }
+                    _ => base.0,
+                },
+            };
+        }
+    }
+}
+
+fn toggle_radius_source(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut settings: ResMut<RadiusSettings>,
+) {
+    if keyboard.just_pressed(KeyCode::KeyR) {
+        settings.source = match settings.source {
+            RadiusSource::VanDerWaals => RadiusSource::Covalent,
+            RadiusSource::Covalent => RadiusSource::Uniform(settings.uniform_radius),
+            RadiusSource::Uniform(_) => RadiusSource::VanDerWaals,
+        };
+    }
+}
+
+/// Resize the existing atom spheres and their outlines for the current radius source
+/// Re-read the palette file given with --palette or by the scene
+fn reload_palette(keyboard: Res<ButtonInput<KeyCode>>, sources: Res<SceneSources>, mut palette: ResMut<Palette>) {
+    if !keyboard.just_pressed(KeyCode::F5) {
+        return;
+    }
+    let Some(path) = &sources.palette else {
+        println!("No palette file to reload");
+        return;
+    };
+    match load_palette(path) {
+        Ok(loaded) => {
+            *palette = loaded;
+            println!("Reloaded palette from {}", path);
+        }
+        Err(e) => eprintln!("Failed to reload palette {}: {}", path, e),
+    }
+}
+
+/// Update existing atoms after the palette changes
+///
+/// Materials take the new roughness and metalness here, and the new colors
+/// become each atom's base color. The color scheme and radius systems then
+/// repaint and resize the atoms.
+fn apply_palette(
+    palette: Res<Palette>,
+    molecules: Res<Molecules>,
+    style_override: Res<StyleOverride>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut color_settings: ResMut<ColorSettings>,
+    mut radius_settings: ResMut<RadiusSettings>,
+    mut atom_query: Query<(&AtomIndex, &mut AtomBaseColor, &MeshMaterial3d<StandardMaterial>)>,
+) {
+    // Atoms are spawned with the starting palette
+    if !palette.is_changed() || palette.is_added() {
+        return;
+    }
+
+    let styles: Vec<Vec<AtomStyle>> = molecules
+        .molecules
+        .iter()
+        .map(|m| style_override.resolve(&m.structure))
+        .collect();
+
+    for (index, mut base, material) in atom_query.iter_mut() {
+        let atom = &molecules.molecules[index.molecule].atoms[index.atom];
+        let style = &styles[index.molecule][index.atom];
+        let mut color = style.color.unwrap_or_else(|| get_atom_color(&atom.element, &palette));
+        if atom.frozen {
+            color = frozen_color(color);
+        }
+        base.0 = color.with_alpha(style.opacity.unwrap_or(1.0));
+
+        if let Some(material) = materials.get_mut(&material.0) {
+            material.perceptual_roughness = palette.roughness(&atom.element);
+            material.metallic = palette.metallic(&atom.element);
+        }
+    }
+
+    color_settings.set_changed();
+    radius_settings.set_changed();
+}
+
+fn apply_radius_source(
+    mut spawner: MoleculeSpawner,
+    molecules: Res<Molecules>,
+    mut atom_query: Query<(&AtomIndex, &mut Mesh3d, &Children)>,
+    mut outline_query: Query<(&mut AtomOutline, &mut Transform)>,
+) {
+    // Atoms are spawned at the starting size
+    let settings = &spawner.radius_settings;
+    if !settings.is_changed() || settings.is_added() {
+        return;
+    }
+    let source = settings.source;
+
+    let styles: Vec<Vec<AtomStyle>> = molecules
+        .molecules
+        .iter()
+        .map(|m| spawner.style_override.resolve(&m.structure))
+        .collect();
+
+    for (index, mut mesh, children) in atom_query.iter_mut() {
+        let element = &molecules.molecules[index.molecule].atoms[index.atom].element;
+        let radius = get_atom_radius(element, &spawner.palette, source)
+            * styles[index.molecule][index.atom].radius_scale.unwrap_or(1.0);
+        mesh.0 = spawner.meshes.add(Sphere::new(radius));
+
+        for child in children.iter() {
+            if let Ok((mut outline, mut transform)) = outline_query.get_mut(child) {
+                outline.radius = radius;
+                transform.scale = Vec3::splat(radius + spawner.outline_settings.thickness);
+            }
+        }
+    }
+}
+
+fn mode_animation_controls(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut animation: ResMut<ModeAnimation>,
+) {
+    if keyboard.just_pressed(KeyCode::KeyM) && !animation.modes.is_empty() {
+        // Step through each mode, then back to rest
+        animation.selected = match animation.selected {
+            Some(i) if i + 1 < animation.modes.len() => Some(i + 1),
+            Some(_) => None,
+            None => Some(0),
+        };
+
+        match animation.selected {
+            Some(i) => match animation.modes[i].frequency {
+                Some(frequency) => println!("Animating mode {} ({:.1} cm^-1)", i + 1, frequency),
+                None => println!("Animating mode {}", i + 1),
+            },
+            None => println!("Mode animation off"),
+        }
+    }
+    if keyboard.just_pressed(KeyCode::Comma) {
+        animation.amplitude = (animation.amplitude - 0.1).max(0.0);
+    }
+    if keyboard.just_pressed(KeyCode::Period) {
+        animation.amplitude += 0.1;
+    }
+}
+
+/// Move atoms of the first molecule sinusoidally along the selected mode
+fn animate_modes(
+    time: Res<Time>,
+    animation: Res<ModeAnimation>,
+    molecules: Res<Molecules>,
+    mut atom_query: Query<(&AtomIndex, &mut Transform)>,
+) {
+    let phase = match animation.selected {
+        Some(_) => (time.elapsed_secs() * animation.speed * std::f32::consts::TAU).sin(),
+        // Only need to put atoms back once when the animation is switched off
+        None if animation.is_changed() => 0.0,
+        None => return,
+    };
+    let scale = phase * animation.amplitude;
+
+    for (index, mut transform) in atom_query.iter_mut() {
+        if index.molecule != 0 {
+            continue;
+        }
+
+        let rest = molecules.molecules[0].atoms[index.atom].position;
+        let displacement = animation
+            .selected
+            .map(|i| animation.modes[i].displacements[index.atom])
+            .map_or(Vec3::ZERO, |[dx, dy, dz]| Vec3::new(dx as f32, dy as f32, dz as f32));
+
+        transform.translation = rest + displacement * scale;
+    }
+}
+
+/// Show the newest frame from stdin, moving atoms in place when the
+/// composition is unchanged and respawning the molecule otherwise
+fn receive_stream_frames(
+    stream: Res<FrameStream>,
+    mut spawner: MoleculeSpawner,
+    outline: Res<OutlineAssets>,
+    mut molecules: ResMut<Molecules>,
+    mut color_settings: ResMut<ColorSettings>,
+    root_query: Query<(Entity, &MoleculeRoot)>,
+    mut atom_query: Query<(&AtomIndex, &mut Transform)>,
+) {
+    // Frames that arrived since the last update are skipped in favor of the newest
+    let Some(frame) = stream.0.lock().expect("frame stream lock poisoned").try_iter().last() else {
+        return;
+    };
+    let mut molecule = render_molecule(frame);
+
+    let current = &mut molecules.molecules[0];
+    // Skipped frames leave no energy, so the plot follows the frames shown
+    molecule.frame_energies = std::mem::take(&mut current.frame_energies);
+    molecule.frame_energies.push(molecule.structure.energy());
+    molecule.frame = molecule.frame_energies.len() - 1;
+
+    let same_atoms = current.atoms.len() == molecule.atoms.len()
+        && current.atoms.iter().zip(&molecule.atoms).all(|(a, b)| a.element == b.element);
+
+    if same_atoms {
+        for (index, mut transform) in atom_query.iter_mut() {
+            if index.molecule == 0 {
+                transform.translation = molecule.atoms[index.atom].position;
+            }
+        }
+    } else {
+        for (entity, root) in root_query.iter() {
+            if root.0 == 0 {
+                spawner.commands.entity(entity).despawn();
+            }
+        }
+        spawner.spawn(0, &molecule, molecules.offsets[0], &outline);
+        // New materials start with element colors
+        color_settings.set_changed();
     }
+
+    molecules.molecules[0] = molecule;
+    spawner.commands.insert_resource(EditHistory::default());
 }
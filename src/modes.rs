@@ -0,0 +1,194 @@
+use crate::geometry::Axis;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+
+/// One vibrational normal mode: a displacement vector for every atom
+#[derive(Debug, Clone, PartialEq)]
+pub struct NormalMode {
+  /// Frequency in cm^-1, if the file gives one
+  pub frequency: Option<f64>,
+  pub displacements: Vec<[f64; 3]>,
+}
+
+impl NormalMode {
+  /// Negate one component of every displacement, to match a structure
+  /// mirrored with `Molecule::mirror`
+  pub fn mirror(&mut self, axis: Axis) {
+    let k = match axis {
+      Axis::X => 0,
+      Axis::Y => 1,
+      Axis::Z => 2,
+    };
+    for displacement in &mut self.displacements {
+      displacement[k] = -displacement[k];
+    }
+  }
+
+  /// Drop the displacements of the atoms at `indices`, to match a structure
+  /// edited with `Molecule::remove_atoms`
+  ///
+  /// Indices refer to the atoms before any are removed; ones past the last
+  /// displacement are ignored.
+  pub fn remove_atoms(&mut self, indices: &[usize]) {
+    let mut atoms = 0..;
+    self.displacements.retain(|_| !indices.contains(&atoms.next().unwrap()));
+  }
+}
+
+/// Modes file error types
+#[derive(Debug, Clone, PartialEq)]
+pub enum ModesError {
+  Io(String),
+  InvalidLine(usize, String),
+  /// A mode whose displacement count differs from the first mode's
+  InconsistentAtomCount { mode: usize, expected: usize, actual: usize },
+}
+
+impl fmt::Display for ModesError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      ModesError::Io(msg) => write!(f, "could not read modes file: {}", msg),
+      ModesError::InvalidLine(line, msg) => {
+        write!(f, "invalid modes line at line {}: {}", line, msg)
+      }
+      ModesError::InconsistentAtomCount { mode, expected, actual } => write!(
+        f,
+        "mode {} has {} displacements, expected {}",
+        mode, actual, expected
+      ),
+    }
+  }
+}
+
+impl Error for ModesError {}
+
+/// Load a modes file from disk
+pub fn load_modes(path: &str) -> Result<Vec<NormalMode>, ModesError> {
+  let content = fs::read_to_string(path).map_err(|e| ModesError::Io(e.to_string()))?;
+  parse_modes(&content)
+}
+
+/// Parse normal modes from text
+///
+/// Each mode starts with a `mode` header, optionally followed by its
+/// frequency, and is followed by one `dx dy dz` line per atom in the same
+/// order as the geometry. Blank lines and `#` comments are ignored.
+///
+/// ```text
+/// mode 1595.3
+///  0.000  0.000 -0.071
+///  0.000  0.429  0.563
+///  0.000 -0.429  0.563
+/// ```
+pub fn parse_modes(content: &str) -> Result<Vec<NormalMode>, ModesError> {
+  let mut modes: Vec<NormalMode> = Vec::new();
+
+  for (i, line) in content.lines().enumerate() {
+    let line_num = i + 1;
+    let trimmed = line.trim();
+
+    if trimmed.is_empty() || trimmed.starts_with('#') {
+      continue;
+    }
+
+    let fields: Vec<&str> = trimmed.split_whitespace().collect();
+
+    if fields[0].eq_ignore_ascii_case("mode") {
+      let frequency = match fields.get(1) {
+        Some(text) => Some(text.parse::<f64>().map_err(|_| {
+          ModesError::InvalidLine(line_num, format!("'{}' is not a valid frequency", text))
+        })?),
+        None => None,
+      };
+      modes.push(NormalMode {
+        frequency,
+        displacements: Vec::new(),
+      });
+      continue;
+    }
+
+    let mode = modes
+      .last_mut()
+      .ok_or_else(|| ModesError::InvalidLine(line_num, "displacement before first 'mode' header".to_string()))?;
+
+    if fields.len() != 3 {
+      return Err(ModesError::InvalidLine(
+        line_num,
+        format!("expected 3 displacement components, found {}", fields.len()),
+      ));
+    }
+
+    let mut displacement = [0.0; 3];
+    for (component, field) in displacement.iter_mut().zip(&fields) {
+      *component = field
+        .parse::<f64>()
+        .ok()
+        .filter(|v| v.is_finite())
+        .ok_or_else(|| ModesError::InvalidLine(line_num, format!("'{}' is not a valid number", field)))?;
+    }
+    mode.displacements.push(displacement);
+  }
+
+  if let Some(first) = modes.first() {
+    let expected = first.displacements.len();
+    for (index, mode) in modes.iter().enumerate() {
+      if mode.displacements.len() != expected {
+        return Err(ModesError::InconsistentAtomCount {
+          mode: index + 1,
+          expected,
+          actual: mode.displacements.len(),
+        });
+      }
+    }
+  }
+
+  Ok(modes)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_modes() {
+    let content = "# water\nmode 1595.3\n0.0 0.0 -0.07\n0.0 0.43 0.56\n\nMODE\n0.0 0.0 0.05\n0.0 0.58 -0.40\n";
+    let modes = parse_modes(content).unwrap();
+
+    assert_eq!(modes.len(), 2);
+    assert_eq!(modes[0].frequency, Some(1595.3));
+    assert_eq!(modes[0].displacements[1], [0.0, 0.43, 0.56]);
+    assert_eq!(modes[1].frequency, None);
+    assert_eq!(modes[1].displacements.len(), 2);
+  }
+
+  #[test]
+  fn test_reject_displacement_before_header() {
+    let err = parse_modes("0.0 0.0 0.0\n").unwrap_err().to_string();
+
+    assert!(err.contains("invalid modes line at line 1"), "Error was: {}", err);
+  }
+
+  #[test]
+  fn test_reject_invalid_component() {
+    let err = parse_modes("mode\n0.0 abc 0.0\n").unwrap_err().to_string();
+
+    assert!(err.contains("line 2"), "Error was: {}", err);
+    assert!(err.contains("'abc'"), "Error was: {}", err);
+  }
+
+  #[test]
+  fn test_reject_inconsistent_atom_counts() {
+    let err = parse_modes("mode\n0 0 0\n0 0 0\nmode\n0 0 0\n").unwrap_err().to_string();
+
+    assert!(err.contains("mode 2 has 1 displacements, expected 2"), "Error was: {}", err);
+  }
+
+  #[test]
+  fn test_remove_atoms_keeps_remaining_displacements() {
+    let mut mode = parse_modes("mode\n1 0 0\n2 0 0\n3 0 0\n").unwrap().remove(0);
+    mode.remove_atoms(&[2, 0, 7]);
+
+    assert_eq!(mode.displacements, vec![[2.0, 0.0, 0.0]]);
+  }
+}
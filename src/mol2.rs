@@ -0,0 +1,341 @@
+use crate::parser::{Atom, Molecule};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::io::Read;
+
+/// Bond order as given in the `@<TRIPOS>BOND` section
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BondOrder {
+  Single,
+  Double,
+  Triple,
+  Aromatic,
+  Amide,
+  Dummy,
+  Unknown,
+  NotConnected,
+}
+
+impl BondOrder {
+  fn from_sybyl(code: &str) -> Option<BondOrder> {
+    match code.to_lowercase().as_str() {
+      "1" => Some(BondOrder::Single),
+      "2" => Some(BondOrder::Double),
+      "3" => Some(BondOrder::Triple),
+      "ar" => Some(BondOrder::Aromatic),
+      "am" => Some(BondOrder::Amide),
+      "du" => Some(BondOrder::Dummy),
+      "un" => Some(BondOrder::Unknown),
+      "nc" => Some(BondOrder::NotConnected),
+      _ => None,
+    }
+  }
+}
+
+/// Bond between two atoms, by 0-based index into `Molecule::atoms`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bond {
+  pub a: usize,
+  pub b: usize,
+  pub order: BondOrder,
+}
+
+/// One molecule from a Mol2 file with its explicit bonds
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mol2Molecule {
+  /// Atoms with `atom_type` set to the SYBYL type; the comment is the molecule name
+  pub molecule: Molecule,
+  pub bonds: Vec<Bond>,
+}
+
+/// Mol2 parser error types
+#[derive(Debug, Clone, PartialEq)]
+pub enum Mol2Error {
+  Io(String),
+  NoMolecules,
+  InvalidAtomLine(usize, String),
+  InvalidBondLine(usize, String),
+  UnknownAtomId(usize, String),
+}
+
+impl fmt::Display for Mol2Error {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Mol2Error::Io(msg) => write!(f, "could not read mol2 data: {}", msg),
+      Mol2Error::NoMolecules => write!(f, "no @<TRIPOS>MOLECULE records found"),
+      Mol2Error::InvalidAtomLine(line, msg) => write!(f, "invalid atom line at line {}: {}", line, msg),
+      Mol2Error::InvalidBondLine(line, msg) => write!(f, "invalid bond line at line {}: {}", line, msg),
+      Mol2Error::UnknownAtomId(line, id) => {
+        write!(f, "bond at line {} refers to unknown atom id '{}'", line, id)
+      }
+    }
+  }
+}
+
+impl Error for Mol2Error {}
+
+/// Record section currently being read
+#[derive(PartialEq)]
+enum Section {
+  Molecule,
+  Atom,
+  Bond,
+  Other,
+}
+
+/// Parse every molecule in a Tripos Mol2 file
+///
+/// Only the `MOLECULE`, `ATOM`, and `BOND` records are read; other records
+/// are skipped. The element is taken from the SYBYL atom type (`C.ar` gives
+/// `C`), and the full type is kept in `Atom::atom_type`. Partial charges are
+/// kept unless the molecule's charge type is `NO_CHARGES`.
+pub fn parse_mol2<R: Read>(mut reader: R) -> Result<Vec<Mol2Molecule>, Mol2Error> {
+  let mut content = String::new();
+  reader
+    .read_to_string(&mut content)
+    .map_err(|e| Mol2Error::Io(e.to_string()))?;
+  parse_mol2_str(&content)
+}
+
+/// Parse every molecule in Mol2 content
+pub fn parse_mol2_str(content: &str) -> Result<Vec<Mol2Molecule>, Mol2Error> {
+  let mut molecules: Vec<Mol2Molecule> = Vec::new();
+  // Maps the file's atom ids to indices, per molecule
+  let mut atom_ids: HashMap<String, usize> = HashMap::new();
+  let mut section = Section::Other;
+  // Non-empty lines read from the current MOLECULE record
+  let mut record_line = 0;
+  let mut has_charges = true;
+
+  for (i, line) in content.lines().enumerate() {
+    let line_num = i + 1;
+    let trimmed = line.trim();
+
+    if let Some(record) = trimmed.strip_prefix("@<TRIPOS>") {
+      section = match record.trim().to_uppercase().as_str() {
+        "MOLECULE" => {
+          molecules.push(Mol2Molecule {
+            molecule: Molecule {
+              atoms: Vec::new(),
+              comment: String::new(),
+              charge: None,
+              multiplicity: None,
+            },
+            bonds: Vec::new(),
+          });
+          atom_ids.clear();
+          record_line = 0;
+          has_charges = true;
+          Section::Molecule
+        }
+        "ATOM" => Section::Atom,
+        "BOND" => Section::Bond,
+        _ => Section::Other,
+      };
+      continue;
+    }
+
+    if trimmed.is_empty() || trimmed.starts_with('#') {
+      continue;
+    }
+
+    let Some(current) = molecules.last_mut() else {
+      continue;
+    };
+
+    match section {
+      Section::Molecule => {
+        record_line += 1;
+        match record_line {
+          1 => current.molecule.comment = trimmed.to_string(),
+          // Files without charges still fill the charge column, usually with zeros
+          4 => has_charges = !trimmed.eq_ignore_ascii_case("NO_CHARGES"),
+          _ => {}
+        }
+      }
+      Section::Atom => {
+        let fields: Vec<&str> = trimmed.split_whitespace().collect();
+        if fields.len() < 6 {
+          return Err(Mol2Error::InvalidAtomLine(
+            line_num,
+            format!("expected at least 6 fields, found {}", fields.len()),
+          ));
+        }
+
+        let mut coords = [0.0; 3];
+        for (coord, field) in coords.iter_mut().zip(&fields[2..5]) {
+          *coord = field
+            .parse::<f64>()
+            .ok()
+            .filter(|v| v.is_finite())
+            .ok_or_else(|| Mol2Error::InvalidAtomLine(line_num, format!("'{}' is not a valid coordinate", field)))?;
+        }
+
+        let atom_type = fields[5];
+        let element = atom_type.split('.').next().unwrap_or(atom_type);
+
+        let partial_charge = match fields.get(8) {
+          Some(field) if has_charges => Some(
+            field
+              .parse::<f64>()
+              .ok()
+              .filter(|v| v.is_finite())
+              .ok_or_else(|| Mol2Error::InvalidAtomLine(line_num, format!("'{}' is not a valid charge", field)))?,
+          ),
+          _ => None,
+        };
+
+        atom_ids.insert(fields[0].to_string(), current.molecule.atoms.len());
+        current.molecule.atoms.push(Atom {
+          element: element.to_string(),
+          x: coords[0],
+          y: coords[1],
+          z: coords[2],
+          isotope: None,
+          label: None,
+          extra: Vec::new(),
+          atom_type: Some(atom_type.to_string()),
+          frozen: false,
+          partial_charge,
+          formal_charge: None,
+          mass: None,
+        });
+      }
+      Section::Bond => {
+        let fields: Vec<&str> = trimmed.split_whitespace().collect();
+        if fields.len() < 4 {
+          return Err(Mol2Error::InvalidBondLine(
+            line_num,
+            format!("expected at least 4 fields, found {}", fields.len()),
+          ));
+        }
+
+        let index = |id: &str| {
+          atom_ids
+            .get(id)
+            .copied()
+            .ok_or_else(|| Mol2Error::UnknownAtomId(line_num, id.to_string()))
+        };
+        let order = BondOrder::from_sybyl(fields[3])
+          .ok_or_else(|| Mol2Error::InvalidBondLine(line_num, format!("unknown bond type '{}'", fields[3])))?;
+
+        current.bonds.push(Bond {
+          a: index(fields[1])?,
+          b: index(fields[2])?,
+          order,
+        });
+      }
+      _ => {}
+    }
+  }
+
+  if molecules.is_empty() {
+    return Err(Mol2Error::NoMolecules);
+  }
+
+  Ok(molecules)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const ETHENE: &str = "\
+# ethene
+@<TRIPOS>MOLECULE
+ethene
+ 6 5 1 0 0
+SMALL
+NO_CHARGES
+
+@<TRIPOS>ATOM
+  1 C1   0.0000  0.0000  0.0000 C.2  1 ETH 0.0
+  2 C2   1.3300  0.0000  0.0000 C.2  1 ETH 0.0
+  3 H1  -0.5700  0.9300  0.0000 H    1 ETH 0.0
+  4 H2  -0.5700 -0.9300  0.0000 H    1 ETH 0.0
+  5 H3   1.9000  0.9300  0.0000 H    1 ETH 0.0
+  6 H4   1.9000 -0.9300  0.0000 H    1 ETH 0.0
+@<TRIPOS>BOND
+  1 1 2 2
+  2 1 3 1
+  3 1 4 1
+  4 2 5 1
+  5 2 6 1
+@<TRIPOS>SUBSTRUCTURE
+  1 ETH 1
+";
+
+  #[test]
+  fn test_parse_atoms_and_bonds() {
+    let molecules = parse_mol2_str(ETHENE).unwrap();
+    assert_eq!(molecules.len(), 1);
+
+    let ethene = &molecules[0];
+    assert_eq!(ethene.molecule.comment, "ethene");
+    assert_eq!(ethene.molecule.atoms.len(), 6);
+    assert_eq!(ethene.molecule.atoms[0].element, "C");
+    assert_eq!(ethene.molecule.atoms[0].atom_type.as_deref(), Some("C.2"));
+    assert_eq!(ethene.molecule.atoms[1].x, 1.33);
+    assert_eq!(ethene.bonds.len(), 5);
+    assert_eq!(ethene.bonds[0], Bond { a: 0, b: 1, order: BondOrder::Double });
+    assert_eq!(ethene.bonds[4], Bond { a: 1, b: 5, order: BondOrder::Single });
+  }
+
+  #[test]
+  fn test_parse_multiple_molecules() {
+    let content = format!(
+      "{}@<TRIPOS>MOLECULE\nargon\n1 0\n@<TRIPOS>ATOM\n7 Ar 5.0 0.0 0.0 Ar\n",
+      ETHENE
+    );
+    let molecules = parse_mol2_str(&content).unwrap();
+
+    assert_eq!(molecules.len(), 2);
+    assert_eq!(molecules[1].molecule.comment, "argon");
+    assert_eq!(molecules[1].molecule.atoms[0].element, "Ar");
+    assert!(molecules[1].bonds.is_empty());
+  }
+
+  #[test]
+  fn test_partial_charges() {
+    let content = "@<TRIPOS>MOLECULE\nwater\n3 2\nSMALL\nUSER_CHARGES\n@<TRIPOS>ATOM\n\
+      1 O 0 0 0.117 O.3 1 WAT -0.834\n2 H1 0 0.757 -0.467 H 1 WAT 0.417\n3 H2 0 -0.757 -0.467 H\n";
+    let molecules = parse_mol2_str(content).unwrap();
+    let charges: Vec<Option<f64>> = molecules[0].molecule.atoms.iter().map(|a| a.partial_charge).collect();
+    assert_eq!(charges, vec![Some(-0.834), Some(0.417), None]);
+
+    // ETHENE declares NO_CHARGES despite its column of zeros
+    let molecules = parse_mol2_str(ETHENE).unwrap();
+    assert!(molecules[0].molecule.atoms.iter().all(|a| a.partial_charge.is_none()));
+  }
+
+  #[test]
+  fn test_aromatic_bond_type() {
+    let content = "@<TRIPOS>MOLECULE\nx\n@<TRIPOS>ATOM\n1 C 0 0 0 C.ar\n2 C 1.4 0 0 C.ar\n@<TRIPOS>BOND\n1 1 2 ar\n";
+    let molecules = parse_mol2_str(content).unwrap();
+
+    assert_eq!(molecules[0].bonds[0].order, BondOrder::Aromatic);
+  }
+
+  #[test]
+  fn test_reject_bond_to_unknown_atom() {
+    let content = "@<TRIPOS>MOLECULE\nx\n@<TRIPOS>ATOM\n1 C 0 0 0 C.3\n@<TRIPOS>BOND\n1 1 9 1\n";
+    let err = parse_mol2_str(content).unwrap_err().to_string();
+
+    assert!(err.contains("line 6"), "Error was: {}", err);
+    assert!(err.contains("unknown atom id '9'"), "Error was: {}", err);
+  }
+
+  #[test]
+  fn test_reject_invalid_coordinate() {
+    let content = "@<TRIPOS>MOLECULE\nx\n@<TRIPOS>ATOM\n1 C 0 nan 0 C.3\n";
+    let err = parse_mol2_str(content).unwrap_err().to_string();
+
+    assert!(err.contains("invalid atom line at line 4"), "Error was: {}", err);
+  }
+
+  #[test]
+  fn test_reject_file_without_molecules() {
+    assert_eq!(parse_mol2_str("# nothing here\n"), Err(Mol2Error::NoMolecules));
+  }
+}
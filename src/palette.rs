@@ -0,0 +1,336 @@
+use bevy::prelude::*;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+
+/// Atom surface roughness when the palette sets none
+pub const DEFAULT_ROUGHNESS: f32 = 0.5;
+
+/// Atom metalness when the palette sets none
+pub const DEFAULT_METALLIC: f32 = 0.1;
+
+/// User-supplied element appearance that overrides the built-in CPK scheme
+#[derive(Resource, Debug, Clone, Default, PartialEq)]
+pub struct Palette {
+  colors: HashMap<String, Color>,
+  radii: HashMap<String, f32>,
+  fallback_color: Option<Color>,
+  fallback_radius: Option<f32>,
+  roughness: HashMap<String, f32>,
+  metallic: HashMap<String, f32>,
+  default_roughness: Option<f32>,
+  default_metallic: Option<f32>,
+}
+
+impl Palette {
+  /// Color for an element, if the palette defines one (case-insensitive)
+  pub fn color(&self, element: &str) -> Option<Color> {
+    self.colors.get(&element.to_uppercase()).copied()
+  }
+
+  /// Drawn radius for an element, if the palette defines one (case-insensitive)
+  pub fn radius(&self, element: &str) -> Option<f32> {
+    self.radii.get(&element.to_uppercase()).copied()
+  }
+
+  /// Color for elements with no palette or built-in color
+  pub fn fallback_color(&self) -> Option<Color> {
+    self.fallback_color
+  }
+
+  /// Radius for elements with no palette or built-in radius
+  pub fn fallback_radius(&self) -> Option<f32> {
+    self.fallback_radius
+  }
+
+  /// Perceptual roughness of an element's material, from 0 (glossy) to 1 (matte)
+  pub fn roughness(&self, element: &str) -> f32 {
+    self
+      .roughness
+      .get(&element.to_uppercase())
+      .copied()
+      .or(self.default_roughness)
+      .unwrap_or(DEFAULT_ROUGHNESS)
+  }
+
+  /// Metalness of an element's material, from 0 (dielectric) to 1 (metal)
+  pub fn metallic(&self, element: &str) -> f32 {
+    self
+      .metallic
+      .get(&element.to_uppercase())
+      .copied()
+      .or(self.default_metallic)
+      .unwrap_or(DEFAULT_METALLIC)
+  }
+}
+
+/// Palette file error types
+#[derive(Debug, Clone, PartialEq)]
+pub enum PaletteError {
+  Io(String),
+  InvalidLine(usize, String),
+  InvalidColor(usize, String),
+  InvalidRadius(usize, String),
+  InvalidMaterial(usize, String),
+}
+
+impl fmt::Display for PaletteError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      PaletteError::Io(msg) => write!(f, "could not read palette: {}", msg),
+      PaletteError::InvalidLine(line, msg) => {
+        write!(f, "invalid palette line at line {}: {}", line, msg)
+      }
+      PaletteError::InvalidColor(line, msg) => {
+        write!(f, "invalid color at line {}: {}", line, msg)
+      }
+      PaletteError::InvalidRadius(line, msg) => {
+        write!(f, "invalid radius at line {}: {}", line, msg)
+      }
+      PaletteError::InvalidMaterial(line, msg) => {
+        write!(f, "invalid material value at line {}: {}", line, msg)
+      }
+    }
+  }
+}
+
+impl Error for PaletteError {}
+
+/// Load a palette file from disk
+pub fn load_palette(path: &str) -> Result<Palette, PaletteError> {
+  let content = fs::read_to_string(path).map_err(|e| PaletteError::Io(e.to_string()))?;
+  parse_palette(&content)
+}
+
+/// Parse palette content of the form `Element = "#rrggbb"`, one entry per line
+///
+/// This is the flat subset of TOML: blank lines and `#` comments are ignored,
+/// and keys may optionally be quoted. Optional sections follow the colors:
+///
+/// ```toml
+/// [radius]
+/// BB = 0.9          # drawn radius for a custom symbol
+///
+/// [fallback]
+/// color = "#808080" # used for symbols with no other color
+/// radius = 0.5
+///
+/// [material]
+/// roughness = 0.8   # every atom unless overridden below
+/// metallic = 0.0
+///
+/// [roughness]
+/// Fe = 0.3
+///
+/// [metallic]
+/// Fe = 0.9
+/// ```
+///
+/// Radii are in Angstrom as drawn, like the built-in radii after scaling.
+/// Roughness and metalness run from 0 to 1.
+pub fn parse_palette(content: &str) -> Result<Palette, PaletteError> {
+  let mut palette = Palette::default();
+  let mut section = "";
+
+  for (i, line) in content.lines().enumerate() {
+    let line_num = i + 1;
+    let trimmed = line.trim();
+
+    if trimmed.is_empty() || trimmed.starts_with('#') {
+      continue;
+    }
+
+    if let Some(name) = trimmed.strip_prefix('[') {
+      let name = name.split('#').next().unwrap_or(name).trim_end();
+      section = match name.strip_suffix(']').map(str::trim) {
+        Some("radius") => "radius",
+        Some("fallback") => "fallback",
+        Some("material") => "material",
+        Some("roughness") => "roughness",
+        Some("metallic") => "metallic",
+        _ => {
+          return Err(PaletteError::InvalidLine(
+            line_num,
+            format!(
+              "unknown section '{}', expected [radius], [fallback], [material], [roughness], or [metallic]",
+              trimmed
+            ),
+          ));
+        }
+      };
+      continue;
+    }
+
+    let (key, value) = trimmed.split_once('=').ok_or_else(|| {
+      PaletteError::InvalidLine(line_num, format!("expected 'Element = \"#rrggbb\"', found '{}'", trimmed))
+    })?;
+
+    let key = unquote(key.trim()).unwrap_or(key.trim());
+    if key.is_empty() {
+      return Err(PaletteError::InvalidLine(line_num, "missing element symbol".to_string()));
+    }
+
+    match (section, key) {
+      ("", element) => {
+        palette.colors.insert(element.to_uppercase(), parse_color(value, line_num)?);
+      }
+      ("radius", element) => {
+        palette.radii.insert(element.to_uppercase(), parse_radius(value, line_num)?);
+      }
+      ("fallback", "color") => palette.fallback_color = Some(parse_color(value, line_num)?),
+      ("fallback", "radius") => palette.fallback_radius = Some(parse_radius(value, line_num)?),
+      ("roughness", element) => {
+        palette.roughness.insert(element.to_uppercase(), parse_fraction(value, line_num)?);
+      }
+      ("metallic", element) => {
+        palette.metallic.insert(element.to_uppercase(), parse_fraction(value, line_num)?);
+      }
+      ("material", "roughness") => palette.default_roughness = Some(parse_fraction(value, line_num)?),
+      ("material", "metallic") => palette.default_metallic = Some(parse_fraction(value, line_num)?),
+      ("material", other) => {
+        return Err(PaletteError::InvalidLine(
+          line_num,
+          format!("unknown material setting '{}', expected roughness or metallic", other),
+        ));
+      }
+      (_, other) => {
+        return Err(PaletteError::InvalidLine(
+          line_num,
+          format!("unknown fallback setting '{}', expected color or radius", other),
+        ));
+      }
+    }
+  }
+
+  Ok(palette)
+}
+
+/// Parse a quoted hex color value
+fn parse_color(value: &str, line_num: usize) -> Result<Color, PaletteError> {
+  let hex = unquote(value.trim()).ok_or_else(|| {
+    PaletteError::InvalidColor(line_num, format!("'{}' is not a quoted string", value.trim()))
+  })?;
+
+  let color = Srgba::hex(hex)
+    .map_err(|_| PaletteError::InvalidColor(line_num, format!("'{}' is not a hex color", hex)))?;
+  Ok(color.into())
+}
+
+/// Parse a positive radius, allowing a trailing `#` comment
+fn parse_radius(value: &str, line_num: usize) -> Result<f32, PaletteError> {
+  let text = value.split('#').next().unwrap_or(value).trim();
+  text
+    .parse::<f32>()
+    .ok()
+    .filter(|r| r.is_finite() && *r > 0.0)
+    .ok_or_else(|| PaletteError::InvalidRadius(line_num, format!("'{}' is not a positive number", text)))
+}
+
+/// Parse a roughness or metalness from 0 to 1, allowing a trailing `#` comment
+fn parse_fraction(value: &str, line_num: usize) -> Result<f32, PaletteError> {
+  let text = value.split('#').next().unwrap_or(value).trim();
+  text
+    .parse::<f32>()
+    .ok()
+    .filter(|v| (0.0..=1.0).contains(v))
+    .ok_or_else(|| PaletteError::InvalidMaterial(line_num, format!("'{}' is not a number from 0 to 1", text)))
+}
+
+/// Strip surrounding double quotes, allowing a trailing `#` comment after the closing quote
+fn unquote(s: &str) -> Option<&str> {
+  let rest = s.strip_prefix('"')?;
+  let end = rest.find('"')?;
+  let trailing = rest[end + 1..].trim();
+
+  if trailing.is_empty() || trailing.starts_with('#') {
+    Some(&rest[..end])
+  } else {
+    None
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_palette_entries() {
+    let content = "# house style\nO = \"#ff0000\"\n\"Cl\" = \"00ff00\" # chlorine\n\n";
+    let palette = parse_palette(content).unwrap();
+
+    assert_eq!(palette.color("O"), Some(Color::srgb(1.0, 0.0, 0.0)));
+    assert_eq!(palette.color("cl"), Some(Color::srgb(0.0, 1.0, 0.0)));
+    assert_eq!(palette.color("C"), None);
+  }
+
+  #[test]
+  fn test_parse_radius_and_fallback_sections() {
+    let content = "BB = \"#00ff00\"\n\n[radius]\nBB = 0.9 # bead\n\n[fallback]\ncolor = \"#808080\"\nradius = 0.5\n";
+    let palette = parse_palette(content).unwrap();
+
+    assert_eq!(palette.color("bb"), Some(Color::srgb(0.0, 1.0, 0.0)));
+    assert_eq!(palette.radius("BB"), Some(0.9));
+    assert_eq!(palette.radius("C"), None);
+    assert_eq!(palette.fallback_color(), Some(Srgba::hex("808080").unwrap().into()));
+    assert_eq!(palette.fallback_radius(), Some(0.5));
+  }
+
+  #[test]
+  fn test_parse_material_sections() {
+    let content = "[material]\nroughness = 0.8\n\n[roughness]\nFe = 0.3\n\n[metallic]\nfe = 0.9 # shiny\n";
+    let palette = parse_palette(content).unwrap();
+
+    assert_eq!(palette.roughness("Fe"), 0.3);
+    assert_eq!(palette.metallic("FE"), 0.9);
+    assert_eq!(palette.roughness("C"), 0.8);
+    assert_eq!(palette.metallic("C"), DEFAULT_METALLIC);
+    assert_eq!(Palette::default().roughness("C"), DEFAULT_ROUGHNESS);
+  }
+
+  #[test]
+  fn test_reject_invalid_material_value() {
+    let err = parse_palette("[metallic]\nFe = 1.5\n").unwrap_err().to_string();
+    assert!(err.contains("invalid material value at line 2"), "Error was: {}", err);
+
+    let err = parse_palette("[material]\nshine = 0.5\n").unwrap_err().to_string();
+    assert!(err.contains("unknown material setting 'shine'"), "Error was: {}", err);
+  }
+
+  #[test]
+  fn test_reject_invalid_radius() {
+    let err = parse_palette("[radius]\nBB = -1\n").unwrap_err().to_string();
+
+    assert!(err.contains("invalid radius at line 2"), "Error was: {}", err);
+  }
+
+  #[test]
+  fn test_reject_unknown_section_and_fallback_key() {
+    let err = parse_palette("[colors]\n").unwrap_err().to_string();
+    assert!(err.contains("unknown section"), "Error was: {}", err);
+
+    let err = parse_palette("[fallback]\nsize = 1.0\n").unwrap_err().to_string();
+    assert!(err.contains("unknown fallback setting 'size'"), "Error was: {}", err);
+  }
+
+  #[test]
+  fn test_reject_line_without_equals() {
+    let err = parse_palette("O #ff0000\n").unwrap_err().to_string();
+
+    assert!(err.contains("invalid palette line at line 1"), "Error was: {}", err);
+  }
+
+  #[test]
+  fn test_reject_unquoted_color() {
+    let err = parse_palette("O = #ff0000\n").unwrap_err().to_string();
+
+    assert!(err.contains("invalid color at line 1"), "Error was: {}", err);
+  }
+
+  #[test]
+  fn test_reject_invalid_hex_color() {
+    let err = parse_palette("\nO = \"#ff00zz\"\n").unwrap_err().to_string();
+
+    assert!(err.contains("invalid color at line 2"), "Error was: {}", err);
+  }
+}
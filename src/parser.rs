@@ -1,21 +1,174 @@
+use crate::periodic_table::{element_symbol, infer_element};
+use std::collections::HashMap;
 use std::error::Error;
 use std::fmt;
-use std::io::{BufRead, BufReader, Read};
+use std::io::{BufRead, Read};
+
+/// Element given to labels that `ParseOptions::infer_elements` cannot map
+pub const UNKNOWN_ELEMENT: &str = "X";
 
 /// Atom data parsed from XYZ file
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Atom {
   pub element: String,
   pub x: f64,
   pub y: f64,
   pub z: f64,
+  /// Mass number for isotope labels such as `D` (2) or `T` (3)
+  pub isotope: Option<u16>,
+  /// Original symbol from the file when aliasing, a charge suffix, or
+  /// element inference replaced it
+  pub label: Option<String>,
+  /// Fields after the coordinates, kept only when requested
+  pub extra: Vec<String>,
+  /// Force-field atom type such as SYBYL `C.ar`, for formats that carry one
+  pub atom_type: Option<String>,
+  /// Held fixed during optimization, from a trailing flag column
+  pub frozen: bool,
+  /// Partial charge in units of e, for formats that carry one
+  pub partial_charge: Option<f64>,
+  /// Formal charge from a symbol suffix such as `Fe2+` or `Cl-`
+  pub formal_charge: Option<i32>,
+  /// Atomic mass in g/mol from a mass column, overriding the periodic table
+  pub mass: Option<f64>,
 }
 
 /// Molecule containing parsed atoms
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Molecule {
   pub atoms: Vec<Atom>,
   pub comment: String,
+  /// Total charge from a leading `charge multiplicity` pair in the comment,
+  /// when parsed with `ParseOptions::charge_multiplicity`
+  pub charge: Option<i32>,
+  /// Spin multiplicity from the same comment pair
+  pub multiplicity: Option<u32>,
+}
+
+/// Atom borrowing its element symbol from the parsed input
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AtomRef<'a> {
+  pub element: &'a str,
+  pub x: f64,
+  pub y: f64,
+  pub z: f64,
+}
+
+/// Molecule borrowing its strings from the parsed input, from `parse_xyz_borrowed`
+#[derive(Debug, Clone, PartialEq)]
+pub struct MoleculeRef<'a> {
+  pub atoms: Vec<AtomRef<'a>>,
+  pub comment: &'a str,
+}
+
+impl MoleculeRef<'_> {
+  /// Copy into an owned `Molecule`, as `parse_xyz_str` would have returned it
+  pub fn to_molecule(&self) -> Molecule {
+    Molecule {
+      atoms: self
+        .atoms
+        .iter()
+        .map(|a| Atom {
+          element: a.element.to_string(),
+          x: a.x,
+          y: a.y,
+          z: a.z,
+          isotope: None,
+          label: None,
+          extra: Vec::new(),
+          atom_type: None,
+          frozen: false,
+          partial_charge: None,
+          formal_charge: None,
+          mass: None,
+        })
+        .collect(),
+      comment: self.comment.to_string(),
+      charge: None,
+      multiplicity: None,
+    }
+  }
+}
+
+/// Location of a token in the parsed input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+  /// 1-indexed line number
+  pub line: usize,
+  /// 1-indexed byte column within the line
+  pub column: usize,
+  /// 0-indexed byte offset from the start of the input
+  pub offset: usize,
+}
+
+impl Molecule {
+  /// Approximate heap memory held by the molecule, in bytes
+  pub fn heap_size(&self) -> usize {
+    let atom_strings: usize = self
+      .atoms
+      .iter()
+      .map(|a| {
+        a.element.capacity()
+          + a.label.as_ref().map_or(0, |l| l.capacity())
+          + a.atom_type.as_ref().map_or(0, |t| t.capacity())
+          + a.extra.capacity() * std::mem::size_of::<String>()
+          + a.extra.iter().map(|e| e.capacity()).sum::<usize>()
+      })
+      .sum();
+
+    self.comment.capacity() + self.atoms.capacity() * std::mem::size_of::<Atom>() + atom_strings
+  }
+
+  /// Parse `key=value` and `key="quoted value"` tokens from the comment line
+  ///
+  /// Tokens without an `=` are skipped, so a free-form comment yields an
+  /// empty map. An unterminated quote runs to the end of the line.
+  pub fn comment_fields(&self) -> HashMap<String, String> {
+    let mut fields = HashMap::new();
+    let mut chars = self.comment.trim().chars().peekable();
+
+    while chars.peek().is_some() {
+      // Skip separating whitespace
+      while chars.next_if(|c| c.is_whitespace()).is_some() {}
+
+      let mut key = String::new();
+      while let Some(c) = chars.next_if(|c| !c.is_whitespace() && *c != '=') {
+        key.push(c);
+      }
+
+      if chars.next_if_eq(&'=').is_none() {
+        continue;
+      }
+
+      let mut value = String::new();
+      if chars.next_if_eq(&'"').is_some() {
+        for c in chars.by_ref() {
+          if c == '"' {
+            break;
+          }
+          value.push(c);
+        }
+      } else {
+        while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+          value.push(c);
+        }
+      }
+
+      if !key.is_empty() {
+        fields.insert(key, value);
+      }
+    }
+
+    fields
+  }
+
+  /// Energy from an `energy=` comment field, or `None` if it is missing or
+  /// not a finite number
+  pub fn energy(&self) -> Option<f64> {
+    self.comment_fields().get("energy")?.parse().ok().filter(|e: &f64| e.is_finite())
+  }
 }
 
 /// Parser error types
@@ -24,8 +177,8 @@ pub enum ParseError {
   EmptyFile,
   InvalidAtomCount(String),
   MissingCommentLine,
-  InvalidAtomLine(usize, String),
-  InvalidCoordinate(usize, String),
+  InvalidAtomLine(Position, String),
+  InvalidCoordinate(Position, String),
   AtomCountMismatch { expected: usize, actual: usize },
 }
 
@@ -35,11 +188,11 @@ impl fmt::Display for ParseError {
       ParseError::EmptyFile => write!(f, "empty file"),
       ParseError::InvalidAtomCount(msg) => write!(f, "invalid atom count: {}", msg),
       ParseError::MissingCommentLine => write!(f, "missing comment line"),
-      ParseError::InvalidAtomLine(line, msg) => {
-        write!(f, "invalid atom line at line {}: {}", line, msg)
+      ParseError::InvalidAtomLine(pos, msg) => {
+        write!(f, "invalid atom line at line {}, column {}: {}", pos.line, pos.column, msg)
       }
-      ParseError::InvalidCoordinate(line, msg) => {
-        write!(f, "invalid coordinate at line {}: {}", line, msg)
+      ParseError::InvalidCoordinate(pos, msg) => {
+        write!(f, "invalid coordinate at line {}, column {}: {}", pos.line, pos.column, msg)
       }
       ParseError::AtomCountMismatch { expected, actual } => {
         write!(
@@ -54,22 +207,463 @@ impl fmt::Display for ParseError {
 
 impl Error for ParseError {}
 
+/// Serializes as a map with a `kind` tag naming the variant, the error
+/// location or counts where the variant has them, and the full `message`
+#[cfg(feature = "serde")]
+impl serde::Serialize for ParseError {
+  fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    use serde::ser::SerializeMap;
+
+    let kind = match self {
+      ParseError::EmptyFile => "EmptyFile",
+      ParseError::InvalidAtomCount(_) => "InvalidAtomCount",
+      ParseError::MissingCommentLine => "MissingCommentLine",
+      ParseError::InvalidAtomLine(..) => "InvalidAtomLine",
+      ParseError::InvalidCoordinate(..) => "InvalidCoordinate",
+      ParseError::AtomCountMismatch { .. } => "AtomCountMismatch",
+    };
+
+    let mut map = serializer.serialize_map(None)?;
+    map.serialize_entry("kind", kind)?;
+    match self {
+      ParseError::InvalidAtomLine(pos, _) | ParseError::InvalidCoordinate(pos, _) => {
+        map.serialize_entry("line", &pos.line)?;
+        map.serialize_entry("column", &pos.column)?;
+        map.serialize_entry("offset", &pos.offset)?;
+      }
+      ParseError::AtomCountMismatch { expected, actual } => {
+        map.serialize_entry("expected", expected)?;
+        map.serialize_entry("actual", actual)?;
+      }
+      _ => {}
+    }
+    map.serialize_entry("message", &self.to_string())?;
+    map.end()
+  }
+}
+
+/// Suggested bound for `ParseOptions::max_coordinate`, in Angstrom
+pub const DEFAULT_MAX_COORDINATE: f64 = 1e6;
+
+/// Optional parser behaviors, all disabled by default
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParseOptions {
+  /// Map `D`/`T` to hydrogen isotopes and strip leading `@` tags from symbols
+  pub resolve_aliases: bool,
+  /// Keep fields after the three coordinates in `Atom::extra`
+  pub keep_extra_columns: bool,
+  /// Reject coordinates whose magnitude exceeds this bound
+  pub max_coordinate: Option<f64>,
+  /// Read a leading `charge multiplicity` pair such as `0 1` from the
+  /// comment into `Molecule::charge` and `Molecule::multiplicity`
+  pub charge_multiplicity: bool,
+  /// Read a trailing `0`/`1` or `F`/`T` column after the coordinates into
+  /// `Atom::frozen`; atoms without the column are not frozen
+  pub frozen_column: bool,
+  /// Replace symbols that are not elements, such as coarse-grained `CG1`,
+  /// with the element their leading letters name, or `X` if none; the
+  /// original symbol is kept in `Atom::label`
+  pub infer_elements: bool,
+  /// Take the first field of the atom count line as the count, ignoring
+  /// trailing text such as `2 atoms` or `2  # water`
+  pub tolerant_count_line: bool,
+  /// Split charge suffixes such as `Fe2+`, `Fe+2`, or `Cl-` off symbols into
+  /// `Atom::formal_charge`; the original symbol is kept in `Atom::label`
+  pub charge_suffixes: bool,
+  /// Column of the atom line holding each atom's mass, counting the symbol
+  /// as column 1, read into `Atom::mass`; atoms without the column keep
+  /// the periodic-table mass, and columns 1 to 4 are never read as masses
+  pub mass_column: Option<usize>,
+}
+
 /// Parse an XYZ file from a reader
 pub fn parse_xyz<R: Read>(reader: R) -> Result<Molecule, ParseError> {
-  let buf_reader = BufReader::new(reader);
-  let lines: Vec<String> = buf_reader
-    .lines()
-    .collect::<Result<Vec<_>, _>>()
+  parse_xyz_with_options(reader, &ParseOptions::default())
+}
+
+/// Parse an XYZ file from a reader with the given options
+pub fn parse_xyz_with_options<R: Read>(
+  reader: R,
+  options: &ParseOptions,
+) -> Result<Molecule, ParseError> {
+  let content = read_content(reader)?;
+  let lines = split_lines(&content);
+
+  let (molecule, consumed) = parse_frame(&lines, 0, options)?;
+  check_no_extra_lines(&lines[consumed..], molecule.atoms.len())?;
+
+  Ok(molecule)
+}
+
+/// Check if there are extra atom lines beyond what was declared
+fn check_no_extra_lines(remaining_lines: &[(usize, &str)], atom_count: usize) -> Result<(), ParseError> {
+  let extra_atom_lines = remaining_lines
+    .iter()
+    .filter(|(_, l)| !l.trim().is_empty())
+    .count();
+
+  if extra_atom_lines > 0 {
+    return Err(ParseError::AtomCountMismatch {
+      expected: atom_count,
+      actual: atom_count + extra_atom_lines,
+    });
+  }
+
+  Ok(())
+}
+
+/// Parse error with the structure read before the input ran out
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartialParse {
+  pub error: ParseError,
+  /// Comment and atoms read so far, present only when the file declared
+  /// more atoms than it contains
+  pub molecule: Option<Molecule>,
+}
+
+impl fmt::Display for PartialParse {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    self.error.fmt(f)
+  }
+}
+
+impl Error for PartialParse {}
+
+/// Parse an XYZ file, keeping the atoms read so far if the file is truncated
+///
+/// Fails exactly like `parse_xyz_with_options`, but when there are fewer atom
+/// lines than declared the error also carries the atoms that were parsed.
+pub fn parse_xyz_partial<R: Read>(
+  reader: R,
+  options: &ParseOptions,
+) -> Result<Molecule, PartialParse> {
+  let content = read_content(reader).map_err(|error| PartialParse { error, molecule: None })?;
+  let lines = split_lines(&content);
+
+  let mut molecule = Molecule {
+    atoms: Vec::new(),
+    comment: String::new(),
+    charge: None,
+    multiplicity: None,
+  };
+  match parse_frame_into(&lines, 0, options, &mut molecule) {
+    Ok(consumed) => match check_no_extra_lines(&lines[consumed..], molecule.atoms.len()) {
+      Ok(()) => Ok(molecule),
+      Err(error) => Err(PartialParse { error, molecule: None }),
+    },
+    // Within a frame, a count mismatch only arises from running out of lines
+    Err(error @ ParseError::AtomCountMismatch { .. }) => Err(PartialParse {
+      error,
+      molecule: Some(molecule),
+    }),
+    Err(error) => Err(PartialParse { error, molecule: None }),
+  }
+}
+
+/// Parse every frame of a multi-frame XYZ file (trajectory)
+///
+/// Frames follow each other directly, each with its own count and comment
+/// line. Blank lines between frames are skipped.
+pub fn parse_xyz_frames<R: Read>(
+  reader: R,
+  options: &ParseOptions,
+) -> Result<Vec<Molecule>, ParseError> {
+  parse_xyz_frames_with_progress(reader, options, |_| {})
+}
+
+/// Parse every frame of a multi-frame XYZ file, calling `progress` with the
+/// number of frames parsed so far after each frame
+pub fn parse_xyz_frames_with_progress<R: Read, F: FnMut(usize)>(
+  reader: R,
+  options: &ParseOptions,
+  mut progress: F,
+) -> Result<Vec<Molecule>, ParseError> {
+  let content = read_content(reader)?;
+  let lines = split_lines(&content);
+
+  let mut frames = Vec::new();
+  let mut start = 0;
+  loop {
+    let (molecule, consumed) = parse_frame(&lines, start, options)?;
+    frames.push(molecule);
+    start += consumed;
+    progress(frames.len());
+
+    match lines[start..].iter().position(|(_, l)| !l.trim().is_empty()) {
+      Some(next) => start += next,
+      None => break,
+    }
+  }
+
+  Ok(frames)
+}
+
+/// Reads XYZ frames one at a time from a buffered reader, such as a pipe
+///
+/// Unlike `parse_xyz_frames`, each frame is returned as soon as its last
+/// line has been read, so this works on input that never ends. Blank lines
+/// between frames are skipped. Iteration stops at the end of the input or
+/// after the first error.
+pub struct FrameReader<R> {
+  reader: R,
+  options: ParseOptions,
+  /// Lines and bytes consumed so far, for error positions
+  line: usize,
+  offset: usize,
+  done: bool,
+}
+
+impl<R: BufRead> FrameReader<R> {
+  pub fn new(reader: R, options: ParseOptions) -> Self {
+    Self {
+      reader,
+      options,
+      line: 0,
+      offset: 0,
+      done: false,
+    }
+  }
+
+  /// Next line with its byte offset and without its terminator, or `None` at the end
+  fn read_line(&mut self) -> Result<Option<(usize, String)>, ParseError> {
+    let mut line = String::new();
+    let read = self
+      .reader
+      .read_line(&mut line)
+      .map_err(|e| ParseError::InvalidAtomCount(e.to_string()))?;
+    if read == 0 {
+      return Ok(None);
+    }
+
+    let offset = self.offset;
+    self.offset += read;
+    self.line += 1;
+
+    if line.ends_with('\n') {
+      line.pop();
+      if line.ends_with('\r') {
+        line.pop();
+      }
+    }
+    Ok(Some((offset, line)))
+  }
+
+  fn read_frame(&mut self) -> Result<Option<Molecule>, ParseError> {
+    let count_line = loop {
+      match self.read_line()? {
+        None => return Ok(None),
+        Some((_, line)) if line.trim().is_empty() => continue,
+        Some(line) => break line,
+      }
+    };
+    let line_base = self.line - 1;
+
+    // Read the comment and atom lines; an invalid count is reported by parse_frame_into
+    let mut raw = vec![count_line];
+    if let Ok(count) = count_field(&raw[0].1, &self.options).parse::<usize>() {
+      for _ in 0..count.saturating_add(1) {
+        match self.read_line()? {
+          Some(line) => raw.push(line),
+          None => break,
+        }
+      }
+    }
+
+    let lines: Vec<(usize, &str)> = raw.iter().map(|(offset, line)| (*offset, line.as_str())).collect();
+    let mut molecule = Molecule {
+      atoms: Vec::new(),
+      comment: String::new(),
+      charge: None,
+      multiplicity: None,
+    };
+    parse_frame_into(&lines, line_base, &self.options, &mut molecule)?;
+    Ok(Some(molecule))
+  }
+}
+
+impl<R: BufRead> Iterator for FrameReader<R> {
+  type Item = Result<Molecule, ParseError>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    if self.done {
+      return None;
+    }
+
+    match self.read_frame() {
+      Ok(Some(molecule)) => Some(Ok(molecule)),
+      Ok(None) => {
+        self.done = true;
+        None
+      }
+      Err(e) => {
+        self.done = true;
+        Some(Err(e))
+      }
+    }
+  }
+}
+
+/// Read the whole input into a string
+fn read_content<R: Read>(mut reader: R) -> Result<String, ParseError> {
+  let mut content = String::new();
+  reader
+    .read_to_string(&mut content)
     .map_err(|e| ParseError::InvalidAtomCount(e.to_string()))?;
+  Ok(content)
+}
+
+/// Parse the frame whose count line is `lines[start]`, returning it with the
+/// number of lines it spans
+fn parse_frame(
+  lines: &[(usize, &str)],
+  start: usize,
+  options: &ParseOptions,
+) -> Result<(Molecule, usize), ParseError> {
+  let mut molecule = Molecule {
+    atoms: Vec::new(),
+    comment: String::new(),
+    charge: None,
+    multiplicity: None,
+  };
+  let consumed = parse_frame_into(&lines[start..], start, options, &mut molecule)?;
+  Ok((molecule, consumed))
+}
+
+/// Parse a frame starting at `lines[0]` into `molecule`, which keeps the
+/// comment and atoms read before any error
+///
+/// `line_base` is the number of input lines before the frame, for error positions.
+fn parse_frame_into(
+  lines: &[(usize, &str)],
+  line_base: usize,
+  options: &ParseOptions,
+  molecule: &mut Molecule,
+) -> Result<usize, ParseError> {
 
   // Check for empty file (no lines or only whitespace)
-  if lines.is_empty() || lines.iter().all(|l| l.trim().is_empty()) {
+  if lines.is_empty() || lines.iter().all(|(_, l)| l.trim().is_empty()) {
     return Err(ParseError::EmptyFile);
   }
 
   // First line: atom count
-  let first_line = lines.first().ok_or(ParseError::EmptyFile)?;
-  let atom_count_str = first_line.trim();
+  let (_, first_line) = lines.first().ok_or(ParseError::EmptyFile)?;
+  let atom_count = parse_atom_count(first_line, options)?;
+
+  // Second line: comment (must exist even if empty)
+  if lines.len() < 2 {
+    return Err(ParseError::MissingCommentLine);
+  }
+
+  molecule.comment = lines[1].1.to_string();
+  if options.charge_multiplicity
+    && let Some((charge, multiplicity)) = parse_charge_multiplicity(&molecule.comment)
+  {
+    molecule.charge = Some(charge);
+    molecule.multiplicity = Some(multiplicity);
+  }
+
+  // Parse atom lines (starting from line 3, index 2)
+  let atoms = &mut molecule.atoms;
+  let atom_lines = &lines[2..];
+  // Don't trust a huge declared count before seeing the lines
+  atoms.reserve(atom_count.min(atom_lines.len()));
+
+  // We need exactly atom_count valid atom lines
+  for i in 0..atom_count {
+    let line_num = line_base + i + 3; // 1-indexed, starting from the frame's third line
+
+    // Check if we have enough lines
+    if i >= atom_lines.len() {
+      return Err(ParseError::AtomCountMismatch {
+        expected: atom_count,
+        actual: i,
+      });
+    }
+
+    let (line_offset, line) = atom_lines[i];
+    let position = |column: usize| Position {
+      line: line_num,
+      column: column + 1,
+      offset: line_offset + column,
+    };
+
+    let (element, [x, y, z], rest) = parse_atom_fields(line, position, options)?;
+    // Fields after the coordinates are only collected when an option reads them
+    let rest: Vec<(usize, &str)> = if options.frozen_column || options.keep_extra_columns || options.mass_column.is_some() {
+      rest.collect()
+    } else {
+      Vec::new()
+    };
+
+    let symbol = element;
+    let (element, formal_charge) = match split_charge_suffix(symbol).filter(|_| options.charge_suffixes) {
+      Some((base, charge)) => (base, Some(charge)),
+      None => (symbol, None),
+    };
+
+    let (mut element, isotope, mut label) = if options.resolve_aliases {
+      resolve_alias(element)
+    } else {
+      (element.to_string(), None, None)
+    };
+    if formal_charge.is_some() {
+      label = Some(symbol.to_string());
+    }
+
+    if options.infer_elements && element_symbol(&element).is_none() {
+      label.get_or_insert_with(|| element.clone());
+      element = infer_element(&element).unwrap_or(UNKNOWN_ELEMENT).to_string();
+    }
+
+    let frozen = match rest.last() {
+      Some(&(column, flag)) if options.frozen_column => parse_frozen_flag(flag).ok_or_else(|| {
+        ParseError::InvalidAtomLine(
+          position(column),
+          format!("'{}' is not a frozen flag (expected 0, 1, F, or T)", flag),
+        )
+      })?,
+      _ => false,
+    };
+
+    // The symbol and coordinates are the first four columns
+    let mass = match options.mass_column.and_then(|c| c.checked_sub(5)).and_then(|i| rest.get(i)) {
+      Some(&(column, field)) => Some(parse_mass(field).ok_or_else(|| {
+        ParseError::InvalidAtomLine(
+          position(column),
+          format!("'{}' is not a valid mass (expected a positive number)", field),
+        )
+      })?),
+      None => None,
+    };
+
+    let extra = if options.keep_extra_columns {
+      rest.iter().map(|(_, field)| field.to_string()).collect()
+    } else {
+      Vec::new()
+    };
+
+    atoms.push(Atom {
+      element,
+      x,
+      y,
+      z,
+      isotope,
+      label,
+      extra,
+      atom_type: None,
+      frozen,
+      partial_charge: None,
+      formal_charge,
+      mass,
+    });
+  }
+
+  Ok(atom_count + 2)
+}
+
+/// Parse the atom count line, which must hold a non-negative integer
+fn parse_atom_count(line: &str, options: &ParseOptions) -> Result<usize, ParseError> {
+  let atom_count_str = count_field(line, options);
 
   if atom_count_str.is_empty() {
     return Err(ParseError::EmptyFile);
@@ -95,112 +689,249 @@ pub fn parse_xyz<R: Read>(reader: R) -> Result<Molecule, ParseError> {
     )));
   }
 
-  let atom_count = atom_count as usize;
+  Ok(atom_count as usize)
+}
 
-  // Second line: comment (must exist even if empty)
-  if lines.len() < 2 {
-    return Err(ParseError::MissingCommentLine);
+/// Split an atom line into its element symbol, three coordinates, and the
+/// remaining fields, without allocating
+///
+/// `position` maps a byte column in the line to its location in the input.
+fn parse_atom_fields<'a>(
+  line: &'a str,
+  position: impl Fn(usize) -> Position,
+  options: &ParseOptions,
+) -> Result<(&'a str, [f64; 3], Fields<'a>), ParseError> {
+  let mut fields = Fields { line, end: 0 };
+  let mut parts = [(0, ""); 4];
+  let mut found = 0;
+  for part in fields.by_ref().take(4) {
+    parts[found] = part;
+    found += 1;
   }
 
-  let comment = lines[1].clone();
-
-  // Parse atom lines (starting from line 3, index 2)
-  let mut atoms = Vec::with_capacity(atom_count);
-  let atom_lines = &lines[2..];
-
-  // We need exactly atom_count valid atom lines
-  for i in 0..atom_count {
-    let line_num = i + 3; // 1-indexed, starting from line 3
-
-    // Check if we have enough lines
-    if i >= atom_lines.len() {
-      return Err(ParseError::AtomCountMismatch {
-        expected: atom_count,
-        actual: i,
-      });
-    }
+  // Empty lines in atom section are invalid
+  if found == 0 {
+    return Err(ParseError::InvalidAtomLine(
+      position(0),
+      "empty line in atom section".to_string(),
+    ));
+  }
 
-    let line = &atom_lines[i];
-    let trimmed = line.trim();
+  // Need at least element + 3 coordinates
+  if found < 4 {
+    return Err(ParseError::InvalidAtomLine(
+      position(0),
+      format!("expected at least 4 fields, found {}", found),
+    ));
+  }
 
-    // Empty lines in atom section are invalid
-    if trimmed.is_empty() {
+  // Invisible characters such as a zero-width space don't separate fields,
+  // so they would otherwise surface as a baffling invalid symbol or number
+  for &(column, field) in parts.iter().filter(|(_, field)| !field.is_ascii()) {
+    if let Some((offset, c)) = field.char_indices().find(|&(_, c)| is_invisible(c)) {
       return Err(ParseError::InvalidAtomLine(
-        line_num,
-        "empty line in atom section".to_string(),
+        position(column + offset),
+        format!("invisible character U+{:04X} in '{}'; separate fields with spaces or tabs", c as u32, field),
       ));
     }
+  }
 
-    let parts: Vec<&str> = trimmed.split_whitespace().collect();
+  let (element_column, element) = parts[0];
 
-    // Need at least element + 3 coordinates
-    if parts.len() < 4 {
-      return Err(ParseError::InvalidAtomLine(
-        line_num,
-        format!("expected at least 4 fields, found {}", parts.len()),
-      ));
+  if !is_plausible_element(element) {
+    return Err(ParseError::InvalidAtomLine(
+      position(element_column),
+      format!("element symbol '{}' appears to be a number", element),
+    ));
+  }
+
+  // Parse coordinates
+  let coordinate = |(column, field): (usize, &str)| {
+    let value = parse_coordinate(field, position(column))?;
+    match options.max_coordinate {
+      Some(bound) if value.abs() > bound => Err(ParseError::InvalidCoordinate(
+        position(column),
+        format!("'{}' is outside the allowed range of +/-{}", field, bound),
+      )),
+      _ => Ok(value),
     }
+  };
+  let coordinates = [coordinate(parts[1])?, coordinate(parts[2])?, coordinate(parts[3])?];
 
-    let element = parts[0];
+  Ok((element, coordinates, fields))
+}
 
-    // Check if element looks like a number (invalid - should be alphanumeric starting with letter)
-    if element.chars().next().map_or(true, |c| c.is_ascii_digit() || c == '-' || c == '+' || c == '.') {
-      return Err(ParseError::InvalidAtomLine(
-        line_num,
-        format!("element symbol '{}' appears to be a number", element),
-      ));
-    }
+/// Split content into lines, keeping the byte offset at which each line starts
+///
+/// Like `str::lines`, this strips `\n` and `\r\n` terminators.
+fn split_lines(content: &str) -> Vec<(usize, &str)> {
+  let mut lines = Vec::new();
+  let mut offset = 0;
+
+  for raw in content.split_inclusive('\n') {
+    // A final line without a newline may still end in '\r'
+    let line = raw.strip_suffix('\n').unwrap_or(raw);
+    let line = line.strip_suffix('\r').unwrap_or(line);
+    lines.push((offset, line));
+    offset += raw.len();
+  }
 
-    // Parse coordinates
-    let x = parse_coordinate(parts[1], line_num)?;
-    let y = parse_coordinate(parts[2], line_num)?;
-    let z = parse_coordinate(parts[3], line_num)?;
+  lines
+}
 
-    atoms.push(Atom {
-      element: element.to_string(),
-      x,
-      y,
-      z,
-    });
+/// Text of the atom count line that must hold the count
+fn count_field<'a>(line: &'a str, options: &ParseOptions) -> &'a str {
+  if options.tolerant_count_line {
+    line.split_whitespace().next().unwrap_or("")
+  } else {
+    line.trim()
   }
+}
 
-  // Check if there are extra atom lines beyond what was declared
-  let remaining_lines = &atom_lines[atom_count..];
-  let extra_atom_lines = remaining_lines
-    .iter()
-    .filter(|l| !l.trim().is_empty())
-    .count();
+/// Value of a frozen-atom flag column, or `None` if it is not a flag
+fn parse_frozen_flag(flag: &str) -> Option<bool> {
+  match flag {
+    "1" | "T" | "t" => Some(true),
+    "0" | "F" | "f" => Some(false),
+    _ => None,
+  }
+}
 
-  if extra_atom_lines > 0 {
-    return Err(ParseError::AtomCountMismatch {
-      expected: atom_count,
-      actual: atom_count + extra_atom_lines,
-    });
+/// Value of a mass column, or `None` if it is not a positive, finite number
+fn parse_mass(field: &str) -> Option<f64> {
+  field.parse().ok().filter(|m: &f64| m.is_finite() && *m > 0.0)
+}
+
+/// Leading `charge multiplicity` pair of a comment, such as `-1 2 anion`
+///
+/// Both numbers must be integers and the multiplicity at least 1; anything
+/// else gives `None`.
+fn parse_charge_multiplicity(comment: &str) -> Option<(i32, u32)> {
+  let mut fields = comment.split_whitespace();
+  let charge = fields.next()?.parse::<i32>().ok()?;
+  let multiplicity = fields.next()?.parse::<u32>().ok().filter(|m| *m >= 1)?;
+  Some((charge, multiplicity))
+}
+
+/// Whitespace-separated fields of a line with the byte column at which each starts
+struct Fields<'a> {
+  line: &'a str,
+  /// Byte offset just past the last field returned
+  end: usize,
+}
+
+impl<'a> Iterator for Fields<'a> {
+  type Item = (usize, &'a str);
+
+  fn next(&mut self) -> Option<Self::Item> {
+    let start = self.end + self.line[self.end..].find(|c: char| !c.is_whitespace())?;
+    self.end = self.line[start..].find(char::is_whitespace).map_or(self.line.len(), |len| start + len);
+    Some((start, &self.line[start..self.end]))
   }
+}
 
-  Ok(Molecule { atoms, comment })
+/// Whether `token` could be an element symbol in an atom line
+///
+/// Tokens that start like a number, with a digit, sign, or decimal point,
+/// are rejected, as is the empty token. Anything else is left for element
+/// lookup to judge, so labels such as `X1` or `C12` are plausible.
+pub fn is_plausible_element(token: &str) -> bool {
+  token.chars().next().is_some_and(|c| !(c.is_ascii_digit() || c == '-' || c == '+' || c == '.'))
 }
 
-/// Parse a coordinate value, rejecting NaN and Inf
-fn parse_coordinate(s: &str, line_num: usize) -> Result<f64, ParseError> {
-  let lower = s.to_lowercase();
+/// Whether `c` is a zero-width or formatting character that is not
+/// whitespace, such as U+200B ZERO WIDTH SPACE or a byte order mark
+///
+/// Unicode spaces such as U+00A0 NO-BREAK SPACE are whitespace and already
+/// separate fields.
+fn is_invisible(c: char) -> bool {
+  matches!(c, '\u{200B}'..='\u{200F}' | '\u{2060}'..='\u{2064}' | '\u{FEFF}' | '\u{00AD}')
+}
+
+/// Split a trailing charge such as `2+`, `+2`, `-`, or `--` off a symbol,
+/// returning the base symbol and the charge
+///
+/// Symbols without a sign in the suffix, such as `C12`, have no charge.
+fn split_charge_suffix(symbol: &str) -> Option<(&str, i32)> {
+  let base = symbol.trim_end_matches(|c: char| c.is_ascii_digit() || c == '+' || c == '-');
+  let suffix = &symbol[base.len()..];
+  if base.is_empty() || suffix.is_empty() {
+    return None;
+  }
 
+  let sign = |c: char| match c {
+    '+' => Some(1),
+    '-' => Some(-1),
+    _ => None,
+  };
+  let first = suffix.chars().next()?;
+  let last = suffix.chars().last()?;
+  let charge = if let Some(s) = sign(last).filter(|_| sign(first).is_none()) {
+    // Magnitude then sign, as in `2+`
+    s * parse_magnitude(&suffix[..suffix.len() - 1])?
+  } else if let Some(s) = sign(first).filter(|_| sign(last).is_none()) {
+    // Sign then magnitude, as in `+2`
+    s * parse_magnitude(&suffix[1..])?
+  } else {
+    // Signs alone count the charge, as in `-` or `--`
+    let s = sign(first)?;
+    if !suffix.chars().all(|c| sign(c) == Some(s)) {
+      return None;
+    }
+    s * suffix.len() as i32
+  };
+  Some((base, charge))
+}
+
+/// Digits of a charge magnitude, which must not be empty or signed
+fn parse_magnitude(digits: &str) -> Option<i32> {
+  if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+    return None;
+  }
+  digits.parse().ok()
+}
+
+/// Split an aliased symbol into its base element, isotope, and original label
+///
+/// `D` and `T` become hydrogen with mass numbers 2 and 3, and a leading `@`
+/// tag is stripped so that `@C` is treated as carbon.
+fn resolve_alias(symbol: &str) -> (String, Option<u16>, Option<String>) {
+  let base = symbol.strip_prefix('@').unwrap_or(symbol);
+
+  let (element, isotope) = match base {
+    "D" => ("H", Some(2)),
+    "T" => ("H", Some(3)),
+    _ => (base, None),
+  };
+
+  // Keep the original symbol only when it differs from the element
+  let label = if element == symbol {
+    None
+  } else {
+    Some(symbol.to_string())
+  };
+
+  (element.to_string(), isotope, label)
+}
+
+/// Parse a coordinate value, rejecting NaN and Inf
+fn parse_coordinate(s: &str, position: Position) -> Result<f64, ParseError> {
   // Reject special values
-  if lower == "nan" || lower == "inf" || lower == "-inf" || lower == "+inf" {
+  if ["nan", "inf", "-inf", "+inf"].iter().any(|special| s.eq_ignore_ascii_case(special)) {
     return Err(ParseError::InvalidCoordinate(
-      line_num,
+      position,
       format!("'{}' is not a valid coordinate (NaN/Inf not allowed)", s),
     ));
   }
 
   let value: f64 = s.parse().map_err(|_| {
-    ParseError::InvalidCoordinate(line_num, format!("'{}' is not a valid number", s))
+    ParseError::InvalidCoordinate(position, format!("'{}' is not a valid number", s))
   })?;
 
   // Double-check for NaN/Inf after parsing (in case of edge cases)
   if value.is_nan() || value.is_infinite() {
     return Err(ParseError::InvalidCoordinate(
-      line_num,
+      position,
       format!("'{}' resulted in NaN or Infinity", s),
     ));
   }
@@ -213,6 +944,54 @@ pub fn parse_xyz_str(content: &str) -> Result<Molecule, ParseError> {
   parse_xyz(content.as_bytes())
 }
 
+/// Parse XYZ content without copying the comment or element symbols
+///
+/// Accepts and rejects exactly what `parse_xyz_str` does, but the only
+/// allocations are the atom list and line index. Parser options are not
+/// supported; use the owned API for aliases, extra columns, and the like.
+pub fn parse_xyz_borrowed(content: &str) -> Result<MoleculeRef<'_>, ParseError> {
+  let options = ParseOptions::default();
+  let lines = split_lines(content);
+
+  if lines.iter().all(|(_, l)| l.trim().is_empty()) {
+    return Err(ParseError::EmptyFile);
+  }
+  let atom_count = parse_atom_count(lines[0].1, &options)?;
+  if lines.len() < 2 {
+    return Err(ParseError::MissingCommentLine);
+  }
+
+  let atom_lines = &lines[2..];
+  let mut atoms = Vec::with_capacity(atom_count.min(atom_lines.len()));
+  for i in 0..atom_count {
+    let Some(&(line_offset, line)) = atom_lines.get(i) else {
+      return Err(ParseError::AtomCountMismatch {
+        expected: atom_count,
+        actual: i,
+      });
+    };
+    let position = |column: usize| Position {
+      line: i + 3,
+      column: column + 1,
+      offset: line_offset + column,
+    };
+
+    let (element, [x, y, z], _) = parse_atom_fields(line, position, &options)?;
+    atoms.push(AtomRef {
+      element,
+      x,
+      y,
+      z,
+    });
+  }
+  check_no_extra_lines(&atom_lines[atom_count..], atom_count)?;
+
+  Ok(MoleculeRef {
+    atoms,
+    comment: lines[1].1,
+  })
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -292,6 +1071,25 @@ mod tests {
     assert_eq!(result.atoms.len(), 1);
   }
 
+  #[test]
+  fn test_parse_file_with_unicode_space_separated_fields() {
+    let content = "2\ncomment\nO\u{00A0}0.0\u{00A0}0.0\u{00A0}1.5\nH\u{2003}0.0\u{3000}0.757\u{2009}-0.467\n";
+    let result = parse_xyz_str(content).unwrap();
+
+    assert_eq!(result.atoms[0].element, "O");
+    assert!(approx_eq(result.atoms[0].z, 1.5));
+    assert!(approx_eq(result.atoms[1].y, 0.757));
+  }
+
+  #[test]
+  fn test_reject_invisible_separator_with_its_code_point() {
+    let content = "1\ncomment\nO 0.0\u{200B}0.0 0.0 0.0\n";
+    let err = parse_xyz_str(content).unwrap_err().to_string();
+
+    assert!(err.contains("line 3, column 6"), "Error was: {}", err);
+    assert!(err.contains("invisible character U+200B"), "Error was: {}", err);
+  }
+
   #[test]
   fn test_parse_file_with_multiple_spaces_between_fields() {
     let content = "1\ncomment\nO    0.0    0.0    0.0\n";
@@ -327,6 +1125,20 @@ mod tests {
     assert_eq!(result.atoms[1].element, "dummy2");
   }
 
+  #[test]
+  fn test_plausible_element_symbols() {
+    for token in ["C", "Cl", "X1", "C12", "Fe3+", "Du"] {
+      assert!(is_plausible_element(token), "'{}' should be plausible", token);
+    }
+  }
+
+  #[test]
+  fn test_numeric_looking_tokens_are_not_elements() {
+    for token in ["2H", "-C", "+Na", ".O", "0.0", ""] {
+      assert!(!is_plausible_element(token), "'{}' should not be plausible", token);
+    }
+  }
+
   // ==================== Atom Count Validation ====================
 
   #[test]
@@ -473,6 +1285,25 @@ mod tests {
     assert!(err.contains("missing comment line"), "Error was: {}", err);
   }
 
+  #[test]
+  fn test_reject_file_ending_right_after_atom_count() {
+    for content in ["0", "3", "3\r\n"] {
+      let result = parse_xyz_str(content);
+
+      assert_eq!(result, Err(ParseError::MissingCommentLine), "Content was: {:?}", content);
+    }
+  }
+
+  #[test]
+  fn test_strip_carriage_return_from_comment_line() {
+    let result = parse_xyz_str("1\r\n\r\nC 0.0 0.0 0.0\r\n").unwrap();
+    assert_eq!(result.comment, "");
+
+    // No newline after a carriage-return-only comment
+    let result = parse_xyz_str("0\n\r").unwrap();
+    assert_eq!(result.comment, "");
+  }
+
   #[test]
   fn test_reject_file_with_atom_count_and_comment_but_missing_atoms() {
     let content = "1\ncomment\n";
@@ -492,4 +1323,604 @@ mod tests {
     let err = result.unwrap_err().to_string();
     assert!(err.contains("invalid atom line"), "Error was: {}", err);
   }
+
+  // ==================== Extra Columns ====================
+
+  #[test]
+  fn test_drop_extra_columns_by_default() {
+    let content = "1\ncomment\nC 0.0 0.0 0.0 -0.42 type=CT\n";
+    let result = parse_xyz_str(content).unwrap();
+
+    assert!(result.atoms[0].extra.is_empty());
+  }
+
+  #[test]
+  fn test_keep_extra_columns_when_requested() {
+    let options = ParseOptions {
+      keep_extra_columns: true,
+      ..ParseOptions::default()
+    };
+    let content = "2\ncomment\nC 0.0 0.0 0.0 -0.42\ttype=CT  1\nH 1.0 0.0 0.0\n";
+    let result = parse_xyz_with_options(content.as_bytes(), &options).unwrap();
+
+    assert_eq!(result.atoms[0].extra, vec!["-0.42", "type=CT", "1"]);
+    assert!(result.atoms[1].extra.is_empty());
+  }
+
+  // ==================== Tolerant Count Line ====================
+
+  #[test]
+  fn test_reject_trailing_text_on_count_line_by_default() {
+    let err = parse_xyz_str("2 atoms
+water
+O 0 0 0
+H 1 0 0
+").unwrap_err().to_string();
+    assert!(err.contains("'2 atoms' is not a valid integer"), "Error was: {}", err);
+  }
+
+  #[test]
+  fn test_ignore_trailing_text_on_count_line_when_requested() {
+    let options = ParseOptions {
+      tolerant_count_line: true,
+      ..ParseOptions::default()
+    };
+    for count_line in ["2 atoms", "2  # water", "  2\t", "2"] {
+      let content = format!("{}\nwater\nO 0 0 0\nH 1 0 0\n", count_line);
+      let result = parse_xyz_with_options(content.as_bytes(), &options).unwrap();
+      assert_eq!(result.atoms.len(), 2, "Count line was: {:?}", count_line);
+    }
+
+    let err = parse_xyz_with_options("two atoms\nwater\n".as_bytes(), &options).unwrap_err().to_string();
+    assert!(err.contains("'two' is not a valid integer"), "Error was: {}", err);
+  }
+
+  #[test]
+  fn test_frame_reader_ignores_trailing_count_text() {
+    let options = ParseOptions {
+      tolerant_count_line: true,
+      ..ParseOptions::default()
+    };
+    let content = "1 atom\nfirst\nHe 0 0 0\n2 atoms\nsecond\nHe 0 0 0\nHe 1 0 0\n";
+    let frames: Vec<Molecule> = FrameReader::new(content.as_bytes(), options).collect::<Result<_, _>>().unwrap();
+
+    assert_eq!(frames.iter().map(|f| f.atoms.len()).collect::<Vec<_>>(), vec![1, 2]);
+  }
+
+  // ==================== Element Inference ====================
+
+  #[test]
+  fn test_infer_elements_from_labels_when_requested() {
+    let options = ParseOptions {
+      infer_elements: true,
+      ..ParseOptions::default()
+    };
+    let content = "4
+coarse grained
+CG1 0 0 0
+Cl 1 0 0
+Q2 2 0 0
+CA 3 0 0
+";
+    let result = parse_xyz_with_options(content.as_bytes(), &options).unwrap();
+
+    let elements: Vec<&str> = result.atoms.iter().map(|a| a.element.as_str()).collect();
+    let labels: Vec<Option<&str>> = result.atoms.iter().map(|a| a.label.as_deref()).collect();
+    assert_eq!(elements, vec!["C", "Cl", "X", "CA"]);
+    assert_eq!(labels, vec![Some("CG1"), None, Some("Q2"), None]);
+
+    let result = parse_xyz_str(content).unwrap();
+    assert_eq!(result.atoms[0].element, "CG1");
+    assert_eq!(result.atoms[0].label, None);
+  }
+
+  // ==================== Frozen Atoms ====================
+
+  #[test]
+  fn test_read_frozen_flag_column_when_requested() {
+    let options = ParseOptions {
+      frozen_column: true,
+      ..ParseOptions::default()
+    };
+    let content = "4\ncomment\nC 0.0 0.0 0.0 1\nC 1.5 0.0 0.0 -0.1 0\nH 2.0 1.0 0.0 T\nH 2.0 -1.0 0.0\n";
+    let result = parse_xyz_with_options(content.as_bytes(), &options).unwrap();
+
+    let frozen: Vec<bool> = result.atoms.iter().map(|a| a.frozen).collect();
+    assert_eq!(frozen, vec![true, false, true, false]);
+
+    let result = parse_xyz_str(content).unwrap();
+    assert!(result.atoms.iter().all(|a| !a.frozen));
+  }
+
+  #[test]
+  fn test_reject_invalid_frozen_flag() {
+    let options = ParseOptions {
+      frozen_column: true,
+      ..ParseOptions::default()
+    };
+    let content = "1\ncomment\nC 0.0 0.0 0.0 -0.42\n";
+    let err = parse_xyz_with_options(content.as_bytes(), &options).unwrap_err().to_string();
+
+    assert!(err.contains("line 3, column 15"), "Error was: {}", err);
+    assert!(err.contains("'-0.42' is not a frozen flag"), "Error was: {}", err);
+  }
+
+  // ==================== Mass Column ====================
+
+  #[test]
+  fn test_read_mass_column_when_requested() {
+    let options = ParseOptions {
+      mass_column: Some(5),
+      ..ParseOptions::default()
+    };
+    let content = "3\nheavy water\nO 0.0 0.0 0.117 15.995\nH 0.0 0.757 -0.467 2.014\nH 0.0 -0.757 -0.467\n";
+    let result = parse_xyz_with_options(content.as_bytes(), &options).unwrap();
+
+    let masses: Vec<Option<f64>> = result.atoms.iter().map(|a| a.mass).collect();
+    assert_eq!(masses, vec![Some(15.995), Some(2.014), None]);
+
+    let result = parse_xyz_str(content).unwrap();
+    assert!(result.atoms.iter().all(|a| a.mass.is_none()));
+  }
+
+  #[test]
+  fn test_reject_invalid_mass() {
+    let options = ParseOptions {
+      mass_column: Some(6),
+      ..ParseOptions::default()
+    };
+    let content = "1\ncomment\nC 0.0 0.0 0.0 0.1 -12\n";
+    let err = parse_xyz_with_options(content.as_bytes(), &options).unwrap_err().to_string();
+
+    assert!(err.contains("line 3, column 19"), "Error was: {}", err);
+    assert!(err.contains("'-12' is not a valid mass"), "Error was: {}", err);
+  }
+
+  // ==================== Charge and Multiplicity ====================
+
+  #[test]
+  fn test_parse_charge_multiplicity_when_requested() {
+    let options = ParseOptions {
+      charge_multiplicity: true,
+      ..ParseOptions::default()
+    };
+    let content = "1\n-1 2 hydroxyl radical anion\nO 0.0 0.0 0.0\n";
+
+    let result = parse_xyz_with_options(content.as_bytes(), &options).unwrap();
+    assert_eq!((result.charge, result.multiplicity), (Some(-1), Some(2)));
+    assert_eq!(result.comment, "-1 2 hydroxyl radical anion");
+
+    let result = parse_xyz_str(content).unwrap();
+    assert_eq!((result.charge, result.multiplicity), (None, None));
+  }
+
+  #[test]
+  fn test_ignore_comment_without_charge_multiplicity() {
+    let options = ParseOptions {
+      charge_multiplicity: true,
+      ..ParseOptions::default()
+    };
+    for comment in ["water", "0", "0 0", "1.5 1", "0 -1", "", "energy -76.4 1"] {
+      let content = format!("1\n{}\nO 0.0 0.0 0.0\n", comment);
+      let result = parse_xyz_with_options(content.as_bytes(), &options).unwrap();
+      assert_eq!((result.charge, result.multiplicity), (None, None), "Comment was: {:?}", comment);
+    }
+  }
+
+  // ==================== Charge Suffixes ====================
+
+  fn charge_suffix_options() -> ParseOptions {
+    ParseOptions {
+      charge_suffixes: true,
+      ..Default::default()
+    }
+  }
+
+  #[test]
+  fn test_split_charge_suffixes() {
+    let content = "5\nions\nFe2+ 0 0 0\nCl- 1 0 0\nO2- 2 0 0\nFe+3 3 0 0\nO-- 4 0 0\n";
+    let result = parse_xyz_with_options(content.as_bytes(), &charge_suffix_options()).unwrap();
+
+    let atoms: Vec<(&str, Option<i32>, Option<&str>)> = result
+      .atoms
+      .iter()
+      .map(|a| (a.element.as_str(), a.formal_charge, a.label.as_deref()))
+      .collect();
+    assert_eq!(
+      atoms,
+      vec![
+        ("Fe", Some(2), Some("Fe2+")),
+        ("Cl", Some(-1), Some("Cl-")),
+        ("O", Some(-2), Some("O2-")),
+        ("Fe", Some(3), Some("Fe+3")),
+        ("O", Some(-2), Some("O--")),
+      ]
+    );
+  }
+
+  #[test]
+  fn test_symbols_without_a_charge_suffix_are_unchanged() {
+    let content = "3\ncomment\nC12 0 0 0\nFe2+3 1 0 0\nNa 2 0 0\n";
+    let result = parse_xyz_with_options(content.as_bytes(), &charge_suffix_options()).unwrap();
+
+    assert_eq!(result.atoms[0].element, "C12");
+    assert_eq!(result.atoms[1].element, "Fe2+3");
+    assert_eq!(result.atoms[2].element, "Na");
+    assert!(result.atoms.iter().all(|a| a.formal_charge.is_none() && a.label.is_none()));
+  }
+
+  #[test]
+  fn test_keep_charge_suffixes_verbatim_by_default() {
+    let result = parse_xyz_str("1\ncomment\nFe2+ 0 0 0\n").unwrap();
+
+    assert_eq!(result.atoms[0].element, "Fe2+");
+    assert_eq!(result.atoms[0].formal_charge, None);
+  }
+
+  // ==================== Partial Results ====================
+
+  #[test]
+  fn test_partial_parse_returns_atoms_read_before_truncation() {
+    let content = "4\ntruncated\nO 0.0 0.0 0.0\nH 0.757 0.586 0.0\n";
+    let partial = parse_xyz_partial(content.as_bytes(), &ParseOptions::default()).unwrap_err();
+
+    assert_eq!(partial.error, ParseError::AtomCountMismatch { expected: 4, actual: 2 });
+    let molecule = partial.molecule.unwrap();
+    assert_eq!(molecule.comment, "truncated");
+    assert_eq!(molecule.atoms.len(), 2);
+    assert_eq!(molecule.atoms[1].element, "H");
+  }
+
+  #[test]
+  fn test_partial_parse_has_no_molecule_for_other_errors() {
+    for content in ["2\nc\nO 0 0 0\nH 0 x 0\n", "1\nc\nO 0 0 0\nH 0 0 0\n", ""] {
+      let partial = parse_xyz_partial(content.as_bytes(), &ParseOptions::default()).unwrap_err();
+      assert!(partial.molecule.is_none(), "Content was: {:?}", content);
+    }
+  }
+
+  #[test]
+  fn test_partial_parse_matches_strict_parse_on_valid_input() {
+    let content = "2\nc\nO 0 0 0\nH 1 0 0\n\n";
+
+    assert_eq!(
+      parse_xyz_partial(content.as_bytes(), &ParseOptions::default()).unwrap(),
+      parse_xyz_str(content).unwrap()
+    );
+  }
+
+  #[test]
+  fn test_strict_parse_still_fails_on_truncation() {
+    let err = parse_xyz_str("4\nc\nO 0 0 0\n").unwrap_err();
+
+    assert_eq!(err, ParseError::AtomCountMismatch { expected: 4, actual: 1 });
+  }
+
+  // ==================== JSON Errors ====================
+
+  #[cfg(feature = "serde")]
+  #[test]
+  fn test_serialize_error_with_kind_and_position() {
+    let err = parse_xyz_str("1\ncomment\nC 0.0 abc 0.0\n").unwrap_err();
+    let json = serde_json::to_value(&err).unwrap();
+
+    assert_eq!(json["kind"], "InvalidCoordinate");
+    assert_eq!(json["line"], 3);
+    assert_eq!(json["column"], 7);
+    assert_eq!(json["message"], err.to_string());
+  }
+
+  #[cfg(feature = "serde")]
+  #[test]
+  fn test_serialize_error_without_position() {
+    let json = serde_json::to_value(ParseError::AtomCountMismatch { expected: 3, actual: 2 }).unwrap();
+
+    assert_eq!(
+      json,
+      serde_json::json!({
+        "kind": "AtomCountMismatch",
+        "expected": 3,
+        "actual": 2,
+        "message": "atom count mismatch: expected 3 atoms, found 2",
+      })
+    );
+  }
+
+  // ==================== Coordinate Range ====================
+
+  #[test]
+  fn test_accept_huge_coordinates_by_default() {
+    let content = "1\ncomment\nC 1e300 0.0 0.0\n";
+    let result = parse_xyz_str(content).unwrap();
+
+    assert_eq!(result.atoms[0].x, 1e300);
+  }
+
+  #[test]
+  fn test_reject_coordinate_outside_bound() {
+    let options = ParseOptions {
+      max_coordinate: Some(DEFAULT_MAX_COORDINATE),
+      ..ParseOptions::default()
+    };
+    let content = "2\ncomment\nC 0.0 0.0 0.0\nH 1.0 -1.7e308 0.0\n";
+    let err = parse_xyz_with_options(content.as_bytes(), &options)
+      .unwrap_err()
+      .to_string();
+
+    assert!(err.contains("line 4, column 7"), "Error was: {}", err);
+    assert!(err.contains("outside the allowed range"), "Error was: {}", err);
+  }
+
+  #[test]
+  fn test_accept_coordinate_at_bound() {
+    let options = ParseOptions {
+      max_coordinate: Some(10.0),
+      ..ParseOptions::default()
+    };
+    let content = "1\ncomment\nC -10.0 10.0 0.0\n";
+
+    assert!(parse_xyz_with_options(content.as_bytes(), &options).is_ok());
+  }
+
+  // ==================== Multi-Frame Files ====================
+
+  #[test]
+  fn test_parse_all_frames() {
+    let content = "2\nframe 0\nH 0.0 0.0 0.0\nH 0.74 0.0 0.0\n2\nframe 1\nH 0.0 0.0 0.0\nH 0.80 0.0 0.0\n\n";
+    let frames = parse_xyz_frames(content.as_bytes(), &ParseOptions::default()).unwrap();
+
+    assert_eq!(frames.len(), 2);
+    assert_eq!(frames[0].comment, "frame 0");
+    assert_eq!(frames[1].atoms[1].x, 0.80);
+  }
+
+  #[test]
+  fn test_frames_may_differ_in_atom_count_and_be_separated_by_blank_lines() {
+    let content = "1\na\nC 0 0 0\n\n2\nb\nC 0 0 0\nO 1.2 0 0\n";
+    let frames = parse_xyz_frames(content.as_bytes(), &ParseOptions::default()).unwrap();
+
+    assert_eq!(frames.iter().map(|f| f.atoms.len()).collect::<Vec<_>>(), vec![1, 2]);
+  }
+
+  #[test]
+  fn test_frame_errors_report_file_line_numbers() {
+    let content = "1\na\nC 0 0 0\n1\nb\nC 0 abc 0\n";
+    let err = parse_xyz_frames(content.as_bytes(), &ParseOptions::default())
+      .unwrap_err()
+      .to_string();
+
+    assert!(err.contains("line 6, column 5"), "Error was: {}", err);
+  }
+
+  #[test]
+  fn test_truncated_last_frame_is_an_error() {
+    let content = "1\na\nC 0 0 0\n3\nb\nC 0 0 0\n";
+    let err = parse_xyz_frames(content.as_bytes(), &ParseOptions::default()).unwrap_err();
+
+    assert_eq!(err, ParseError::AtomCountMismatch { expected: 3, actual: 1 });
+  }
+
+  #[test]
+  fn test_progress_reports_each_frame() {
+    let content = "1\na\nC 0 0 0\n1\nb\nC 0 0 0\n1\nc\nC 0 0 0\n";
+    let mut reported = Vec::new();
+    let frames = parse_xyz_frames_with_progress(content.as_bytes(), &ParseOptions::default(), |n| {
+      reported.push(n)
+    })
+    .unwrap();
+
+    assert_eq!(frames.len(), 3);
+    assert_eq!(reported, vec![1, 2, 3]);
+  }
+
+  #[test]
+  fn test_frame_reader_matches_parse_xyz_frames() {
+    let content = "2\nframe 0\r\nH 0 0 0\nH 0.74 0 0\n\n\n1\nframe 1\nHe 1 2 3";
+    let streamed: Vec<Molecule> = FrameReader::new(content.as_bytes(), ParseOptions::default())
+      .collect::<Result<_, _>>()
+      .unwrap();
+
+    assert_eq!(streamed, parse_xyz_frames(content.as_bytes(), &ParseOptions::default()).unwrap());
+  }
+
+  #[test]
+  fn test_frame_reader_yields_frames_before_an_error() {
+    let content = "1\na\nC 0 0 0\n1\nb\nC 0 abc 0\n1\nc\nC 0 0 0\n";
+    let mut reader = FrameReader::new(content.as_bytes(), ParseOptions::default());
+
+    assert!(reader.next().unwrap().is_ok());
+    let err = reader.next().unwrap().unwrap_err().to_string();
+    assert!(err.contains("line 6, column 5"), "Error was: {}", err);
+    assert!(reader.next().is_none());
+  }
+
+  #[test]
+  fn test_frame_reader_reports_truncated_frame() {
+    let mut reader = FrameReader::new("3\nc\nC 0 0 0\n".as_bytes(), ParseOptions::default());
+
+    assert_eq!(
+      reader.next().unwrap(),
+      Err(ParseError::AtomCountMismatch { expected: 3, actual: 1 })
+    );
+  }
+
+  #[test]
+  fn test_single_frame_parser_still_rejects_extra_frames() {
+    let content = "1\na\nC 0 0 0\n1\nb\nC 0 0 0\n";
+    let err = parse_xyz_str(content).unwrap_err().to_string();
+
+    assert!(err.contains("atom count mismatch"), "Error was: {}", err);
+  }
+
+  // ==================== Comment Metadata ====================
+
+  #[test]
+  fn test_comment_fields_parses_key_value_pairs() {
+    let content = "0\nenergy=-76.4 step=10 label=\"water dimer\"\n";
+    let fields = parse_xyz_str(content).unwrap().comment_fields();
+
+    assert_eq!(fields.len(), 3);
+    assert_eq!(fields["energy"], "-76.4");
+    assert_eq!(fields["step"], "10");
+    assert_eq!(fields["label"], "water dimer");
+  }
+
+  #[test]
+  fn test_comment_fields_skips_free_text() {
+    let content = "0\nWater molecule from step=5 of run\n";
+    let fields = parse_xyz_str(content).unwrap().comment_fields();
+
+    assert_eq!(fields.len(), 1);
+    assert_eq!(fields["step"], "5");
+  }
+
+  #[test]
+  fn test_comment_fields_empty_for_plain_comment() {
+    let content = "0\nWater molecule\n";
+    let fields = parse_xyz_str(content).unwrap().comment_fields();
+
+    assert!(fields.is_empty());
+  }
+
+  #[test]
+  fn test_comment_fields_handles_extended_xyz_header() {
+    let content = "0\nLattice=\"10.0 0.0 0.0 0.0 10.0 0.0 0.0 0.0 10.0\" Properties=species:S:1:pos:R:3 pbc=\"T T T\"\n";
+    let fields = parse_xyz_str(content).unwrap().comment_fields();
+
+    assert_eq!(fields["Lattice"], "10.0 0.0 0.0 0.0 10.0 0.0 0.0 0.0 10.0");
+    assert_eq!(fields["Properties"], "species:S:1:pos:R:3");
+    assert_eq!(fields["pbc"], "T T T");
+  }
+
+  #[test]
+  fn test_energy_from_comment() {
+    let molecule = parse_xyz_str("0\nstep=3 energy=-76.4\n").unwrap();
+    assert_eq!(molecule.energy(), Some(-76.4));
+
+    assert_eq!(parse_xyz_str("0\nWater molecule\n").unwrap().energy(), None);
+    assert_eq!(parse_xyz_str("0\nenergy=unknown\n").unwrap().energy(), None);
+    assert_eq!(parse_xyz_str("0\nenergy=NaN\n").unwrap().energy(), None);
+    assert_eq!(parse_xyz_str("0\nenergy=inf\n").unwrap().energy(), None);
+    assert_eq!(parse_xyz_str("0\nenergy=-infinity\n").unwrap().energy(), None);
+  }
+
+  // ==================== Error Positions ====================
+
+  #[test]
+  fn test_report_position_of_invalid_coordinate() {
+    let content = "1\ncomment\nO  0.0 abc 0.0\n";
+    let result = parse_xyz_str(content);
+
+    match result {
+      Err(ParseError::InvalidCoordinate(pos, _)) => {
+        assert_eq!(pos, Position { line: 3, column: 8, offset: 17 });
+      }
+      other => panic!("Expected InvalidCoordinate, got {:?}", other),
+    }
+  }
+
+  #[test]
+  fn test_report_position_of_numeric_element_symbol() {
+    let content = "1\r\ncomment\r\n  1.0 0.0 0.0 0.0\r\n";
+    let result = parse_xyz_str(content);
+
+    match result {
+      Err(ParseError::InvalidAtomLine(pos, _)) => {
+        assert_eq!(pos, Position { line: 3, column: 3, offset: 14 });
+      }
+      other => panic!("Expected InvalidAtomLine, got {:?}", other),
+    }
+  }
+
+  #[test]
+  fn test_error_display_includes_line_and_column() {
+    let content = "1\ncomment\nO 0.0 0.0 NaN\n";
+    let err = parse_xyz_str(content).unwrap_err().to_string();
+
+    assert!(err.contains("line 3, column 11"), "Error was: {}", err);
+  }
+
+  // ==================== Isotope and Alias Handling ====================
+
+  fn alias_options() -> ParseOptions {
+    ParseOptions {
+      resolve_aliases: true,
+      ..ParseOptions::default()
+    }
+  }
+
+  #[test]
+  fn test_keep_aliases_verbatim_by_default() {
+    let content = "2\ncomment\nD 0.0 0.0 0.0\n@C 1.0 0.0 0.0\n";
+    let result = parse_xyz_str(content).unwrap();
+
+    assert_eq!(result.atoms[0].element, "D");
+    assert_eq!(result.atoms[0].isotope, None);
+    assert_eq!(result.atoms[1].element, "@C");
+    assert_eq!(result.atoms[1].label, None);
+  }
+
+  #[test]
+  fn test_resolve_hydrogen_isotopes() {
+    let content = "3\ncomment\nH 0.0 0.0 0.0\nD 1.0 0.0 0.0\nT 2.0 0.0 0.0\n";
+    let result = parse_xyz_with_options(content.as_bytes(), &alias_options()).unwrap();
+
+    assert_eq!(result.atoms[0].element, "H");
+    assert_eq!(result.atoms[0].isotope, None);
+    assert_eq!(result.atoms[0].label, None);
+    assert_eq!(result.atoms[1].element, "H");
+    assert_eq!(result.atoms[1].isotope, Some(2));
+    assert_eq!(result.atoms[1].label.as_deref(), Some("D"));
+    assert_eq!(result.atoms[2].element, "H");
+    assert_eq!(result.atoms[2].isotope, Some(3));
+    assert_eq!(result.atoms[2].label.as_deref(), Some("T"));
+  }
+
+  #[test]
+  fn test_strip_tag_from_element_symbol() {
+    let content = "2\ncomment\n@C 0.0 0.0 0.0\n@D 1.0 0.0 0.0\n";
+    let result = parse_xyz_with_options(content.as_bytes(), &alias_options()).unwrap();
+
+    assert_eq!(result.atoms[0].element, "C");
+    assert_eq!(result.atoms[0].isotope, None);
+    assert_eq!(result.atoms[0].label.as_deref(), Some("@C"));
+    assert_eq!(result.atoms[1].element, "H");
+    assert_eq!(result.atoms[1].isotope, Some(2));
+    assert_eq!(result.atoms[1].label.as_deref(), Some("@D"));
+  }
+
+  // ==================== Borrowed Parsing ====================
+
+  #[test]
+  fn test_borrowed_matches_owned() {
+    let content = "3\nwater\r\nO 0.0 0.0 0.117\nH 0.0 0.757 -0.467 extra\n  H 0.0 -0.757 -0.467\n\n";
+    let borrowed = parse_xyz_borrowed(content).unwrap();
+
+    assert_eq!(borrowed.to_molecule(), parse_xyz_str(content).unwrap());
+    assert!(content.as_bytes().as_ptr_range().contains(&borrowed.atoms[1].element.as_ptr()));
+    assert_eq!(borrowed.comment, "water");
+  }
+
+  #[test]
+  fn test_borrowed_rejects_what_owned_rejects() {
+    for content in [
+      "",
+      "  \n",
+      "2.5\ncomment\n",
+      "-1\ncomment\n",
+      "1\n",
+      "2\ncomment\nH 0 0 0\n",
+      "1\ncomment\nH 0 0 0\nH 1 0 0\n",
+      "1\ncomment\n1 0 0 0\n",
+      "1\ncomment\nH 0 nan 0\n",
+      "1\ncomment\nH 0 0\n",
+    ] {
+      assert_eq!(
+        parse_xyz_borrowed(content).unwrap_err(),
+        parse_xyz_str(content).unwrap_err(),
+        "Input was: {:?}",
+        content
+      );
+    }
+  }
 }
@@ -0,0 +1,187 @@
+/// Tabulated properties of one element
+struct ElementData {
+  symbol: &'static str,
+  /// Van der Waals radius in Angstrom (Bondi, Alvarez for metals)
+  vdw_radius: f64,
+  /// Single-bond covalent radius in Angstrom (Cordero et al. 2008)
+  covalent_radius: f64,
+}
+
+const ELEMENTS: [ElementData; 21] = [
+  ElementData { symbol: "H", vdw_radius: 1.20, covalent_radius: 0.31 },
+  ElementData { symbol: "He", vdw_radius: 1.40, covalent_radius: 0.28 },
+  ElementData { symbol: "Li", vdw_radius: 1.82, covalent_radius: 1.28 },
+  ElementData { symbol: "B", vdw_radius: 1.92, covalent_radius: 0.84 },
+  ElementData { symbol: "C", vdw_radius: 1.70, covalent_radius: 0.76 },
+  ElementData { symbol: "N", vdw_radius: 1.55, covalent_radius: 0.71 },
+  ElementData { symbol: "O", vdw_radius: 1.52, covalent_radius: 0.66 },
+  ElementData { symbol: "F", vdw_radius: 1.47, covalent_radius: 0.57 },
+  ElementData { symbol: "Na", vdw_radius: 2.27, covalent_radius: 1.66 },
+  ElementData { symbol: "Mg", vdw_radius: 1.73, covalent_radius: 1.41 },
+  ElementData { symbol: "Si", vdw_radius: 2.10, covalent_radius: 1.11 },
+  ElementData { symbol: "P", vdw_radius: 1.80, covalent_radius: 1.07 },
+  ElementData { symbol: "S", vdw_radius: 1.80, covalent_radius: 1.05 },
+  ElementData { symbol: "Cl", vdw_radius: 1.75, covalent_radius: 1.02 },
+  ElementData { symbol: "K", vdw_radius: 2.75, covalent_radius: 2.03 },
+  ElementData { symbol: "Ca", vdw_radius: 2.31, covalent_radius: 1.76 },
+  ElementData { symbol: "Fe", vdw_radius: 2.00, covalent_radius: 1.32 },
+  ElementData { symbol: "Cu", vdw_radius: 1.40, covalent_radius: 1.32 },
+  ElementData { symbol: "Zn", vdw_radius: 1.39, covalent_radius: 1.22 },
+  ElementData { symbol: "Br", vdw_radius: 1.85, covalent_radius: 1.20 },
+  ElementData { symbol: "I", vdw_radius: 1.98, covalent_radius: 1.39 },
+];
+
+/// Isotope masses in g/mol (AME2020) as (symbol, mass number, mass), for
+/// isotopes commonly used in labeling
+const ISOTOPES: [(&str, u16, f64); 8] = [
+  ("H", 1, 1.007825),
+  ("H", 2, 2.014102),
+  ("H", 3, 3.016049),
+  ("C", 12, 12.0),
+  ("C", 13, 13.003355),
+  ("N", 15, 15.000109),
+  ("O", 17, 16.999132),
+  ("O", 18, 17.999160),
+];
+
+/// Every element symbol through oganesson, by atomic number
+const SYMBOLS: [&str; 118] = [
+  "H", "He", "Li", "Be", "B", "C", "N", "O", "F", "Ne", "Na", "Mg", "Al", "Si", "P", "S", "Cl", "Ar", "K", "Ca",
+  "Sc", "Ti", "V", "Cr", "Mn", "Fe", "Co", "Ni", "Cu", "Zn", "Ga", "Ge", "As", "Se", "Br", "Kr", "Rb", "Sr", "Y",
+  "Zr", "Nb", "Mo", "Tc", "Ru", "Rh", "Pd", "Ag", "Cd", "In", "Sn", "Sb", "Te", "I", "Xe", "Cs", "Ba", "La", "Ce",
+  "Pr", "Nd", "Pm", "Sm", "Eu", "Gd", "Tb", "Dy", "Ho", "Er", "Tm", "Yb", "Lu", "Hf", "Ta", "W", "Re", "Os", "Ir",
+  "Pt", "Au", "Hg", "Tl", "Pb", "Bi", "Po", "At", "Rn", "Fr", "Ra", "Ac", "Th", "Pa", "U", "Np", "Pu", "Am", "Cm",
+  "Bk", "Cf", "Es", "Fm", "Md", "No", "Lr", "Rf", "Db", "Sg", "Bh", "Hs", "Mt", "Ds", "Rg", "Cn", "Nh", "Fl", "Mc",
+  "Lv", "Ts", "Og",
+];
+
+/// Standard atomic weight in g/mol of each element in `SYMBOLS` (IUPAC,
+/// abridged), or the mass number of the longest-lived isotope for elements
+/// without one
+const MASSES: [f64; 118] = [
+  1.008, 4.0026, 6.94, 9.0122, 10.81, 12.011, 14.007, 15.999, 18.998, 20.180,
+  22.990, 24.305, 26.982, 28.085, 30.974, 32.06, 35.45, 39.948, 39.098, 40.078,
+  44.956, 47.867, 50.942, 51.996, 54.938, 55.845, 58.933, 58.693, 63.546, 65.38,
+  69.723, 72.630, 74.922, 78.971, 79.904, 83.798, 85.468, 87.62, 88.906, 91.224,
+  92.906, 95.95, 98.0, 101.07, 102.91, 106.42, 107.87, 112.41, 114.82, 118.71,
+  121.76, 127.60, 126.90, 131.29, 132.91, 137.33, 138.91, 140.12, 140.91, 144.24,
+  145.0, 150.36, 151.96, 157.25, 158.93, 162.50, 164.93, 167.26, 168.93, 173.05,
+  174.97, 178.49, 180.95, 183.84, 186.21, 190.23, 192.22, 195.08, 196.97, 200.59,
+  204.38, 207.2, 208.98, 209.0, 210.0, 222.0, 223.0, 226.0, 227.0, 232.04,
+  231.04, 238.03, 237.0, 244.0, 243.0, 247.0, 247.0, 251.0, 252.0, 257.0,
+  258.0, 259.0, 266.0, 267.0, 268.0, 269.0, 270.0, 269.0, 278.0, 281.0,
+  282.0, 285.0, 286.0, 289.0, 290.0, 293.0, 294.0, 294.0,
+];
+
+/// Canonical spelling of an element symbol such as `Cl` for `CL`, or `None`
+/// if it names no element
+pub fn element_symbol(symbol: &str) -> Option<&'static str> {
+  SYMBOLS.iter().copied().find(|s| s.eq_ignore_ascii_case(symbol))
+}
+
+/// Best-effort element for a non-standard label such as `CG1` or `Fe2`
+///
+/// Only the leading letters count. Two letters are tried first when the
+/// second is lowercase (`Cl1` is chlorine), then the first letter alone
+/// (`CA` is carbon), then two letters in any case (`ZN` is zinc).
+pub fn infer_element(label: &str) -> Option<&'static str> {
+  let letters: Vec<char> = label.chars().take_while(|c| c.is_ascii_alphabetic()).take(2).collect();
+  let two: String = letters.iter().collect();
+  let one: String = letters.iter().take(1).collect();
+
+  if letters.len() == 2
+    && letters[1].is_ascii_lowercase()
+    && let Some(symbol) = element_symbol(&two)
+  {
+    return Some(symbol);
+  }
+  element_symbol(&one).or_else(|| if letters.len() == 2 { element_symbol(&two) } else { None })
+}
+
+/// Look up an element by symbol, ignoring case
+fn lookup(symbol: &str) -> Option<&'static ElementData> {
+  ELEMENTS.iter().find(|e| e.symbol.eq_ignore_ascii_case(symbol))
+}
+
+/// Van der Waals radius in Angstrom, if tabulated
+pub fn vdw_radius(symbol: &str) -> Option<f64> {
+  lookup(symbol).map(|e| e.vdw_radius)
+}
+
+/// Single-bond covalent radius in Angstrom, if tabulated
+pub fn covalent_radius(symbol: &str) -> Option<f64> {
+  lookup(symbol).map(|e| e.covalent_radius)
+}
+
+/// Standard atomic weight in g/mol, for any element symbol
+pub fn atomic_mass(symbol: &str) -> Option<f64> {
+  SYMBOLS.iter().position(|s| s.eq_ignore_ascii_case(symbol)).map(|i| MASSES[i])
+}
+
+/// Mass in g/mol of the isotope of `symbol` with the given mass number, if
+/// tabulated
+pub fn isotope_mass(symbol: &str, mass_number: u16) -> Option<f64> {
+  ISOTOPES
+    .iter()
+    .find(|(s, n, _)| s.eq_ignore_ascii_case(symbol) && *n == mass_number)
+    .map(|&(_, _, mass)| mass)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_lookup_ignores_case() {
+    assert_eq!(vdw_radius("CL"), Some(1.75));
+    assert_eq!(covalent_radius("cl"), Some(1.02));
+    assert_eq!(atomic_mass("FE"), Some(55.845));
+    assert_eq!(vdw_radius("Xx"), None);
+  }
+
+  #[test]
+  fn test_isotope_masses() {
+    assert_eq!(isotope_mass("H", 2), Some(2.014102));
+    assert_eq!(isotope_mass("h", 3), Some(3.016049));
+    assert_eq!(isotope_mass("H", 4), None);
+    for &(symbol, mass_number, mass) in &ISOTOPES {
+      assert!((mass - f64::from(mass_number)).abs() < 0.02, "{}{}", symbol, mass_number);
+    }
+  }
+
+  #[test]
+  fn test_every_element_has_a_mass() {
+    assert!(MASSES.iter().all(|&mass| mass > 0.0));
+    assert_eq!(atomic_mass("Ar"), Some(39.948));
+    assert_eq!(atomic_mass("au"), Some(196.97));
+    assert_eq!(atomic_mass("Og"), Some(294.0));
+    assert_eq!(atomic_mass("Xx"), None);
+  }
+
+  #[test]
+  fn test_infer_element_from_label() {
+    assert_eq!(infer_element("CG1"), Some("C"));
+    assert_eq!(infer_element("OW"), Some("O"));
+    assert_eq!(infer_element("Cl1"), Some("Cl"));
+    assert_eq!(infer_element("Fe2"), Some("Fe"));
+    assert_eq!(infer_element("ZN"), Some("Zn"));
+    assert_eq!(infer_element("BEAD_A"), Some("B"));
+    assert_eq!(infer_element("Q1"), None);
+    assert_eq!(infer_element("_X"), None);
+    assert_eq!(element_symbol("og"), Some("Og"));
+  }
+
+  #[test]
+  fn test_tabulated_elements_are_real() {
+    for element in &ELEMENTS {
+      assert_eq!(element_symbol(element.symbol), Some(element.symbol));
+    }
+  }
+
+  #[test]
+  fn test_covalent_radii_are_smaller_than_vdw_radii() {
+    for element in &ELEMENTS {
+      assert!(element.covalent_radius < element.vdw_radius, "{}", element.symbol);
+    }
+  }
+}
@@ -0,0 +1,261 @@
+use bevy::color::Srgba;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+
+/// Top-level keys of a scene file; anything else is reported as unknown
+const FIELDS: [&str; 15] = [
+  "inputs",
+  "palette",
+  "color_scheme",
+  "uniform_color",
+  "radii",
+  "uniform_radius",
+  "camera",
+  "ambient_occlusion",
+  "ambient_occlusion_strength",
+  "outlines",
+  "outline_thickness",
+  "shadows",
+  "ground_plane",
+  "scale_bar",
+  "stereo",
+];
+
+/// Names accepted for `color_scheme`, as for --color-scheme
+const COLOR_SCHEMES: [&str; 5] = ["element", "uniform", "cross_section", "coordination", "displacement"];
+
+/// Names accepted for `radii`, as for --radii
+const RADIUS_SOURCES: [&str; 3] = ["vdw", "covalent", "uniform"];
+
+/// Camera placement saved in a scene
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SceneCamera {
+  /// Orientation quaternion as `[x, y, z, w]`
+  pub rotation: [f32; 4],
+  pub distance: f32,
+  /// Point the camera orbits, in scene coordinates
+  pub target: [f32; 3],
+}
+
+/// Everything needed to reproduce a visualization
+///
+/// Every field is optional so a hand-written scene only needs the settings it
+/// changes; saved scenes fill in all of them.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SceneConfig {
+  pub inputs: Vec<String>,
+  pub palette: Option<String>,
+  /// `element`, `uniform`, `cross_section`, `coordination`, or `displacement`
+  pub color_scheme: Option<String>,
+  /// Hex color such as `#ffffff`
+  pub uniform_color: Option<String>,
+  /// `vdw`, `covalent`, or `uniform`
+  pub radii: Option<String>,
+  pub uniform_radius: Option<f32>,
+  pub camera: Option<SceneCamera>,
+  pub ambient_occlusion: Option<bool>,
+  /// Object thickness assumed by SSAO, as for --ssao-strength
+  pub ambient_occlusion_strength: Option<f32>,
+  pub outlines: Option<bool>,
+  pub outline_thickness: Option<f32>,
+  pub shadows: Option<bool>,
+  pub ground_plane: Option<bool>,
+  pub scale_bar: Option<bool>,
+  pub stereo: Option<bool>,
+}
+
+/// Scene file error types
+#[derive(Debug, Clone, PartialEq)]
+pub enum SceneError {
+  Io(String),
+  Invalid(String),
+}
+
+impl fmt::Display for SceneError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      SceneError::Io(msg) => write!(f, "could not access scene file: {}", msg),
+      SceneError::Invalid(msg) => write!(f, "invalid scene file: {}", msg),
+    }
+  }
+}
+
+impl Error for SceneError {}
+
+/// Load a scene file from disk, returning it with any unknown top-level keys
+pub fn load_scene(path: &str) -> Result<(SceneConfig, Vec<String>), SceneError> {
+  let content = fs::read_to_string(path).map_err(|e| SceneError::Io(e.to_string()))?;
+  parse_scene(&content)
+}
+
+/// Parse a JSON scene, returning it with any unknown top-level keys
+///
+/// Unknown keys are skipped rather than rejected so scenes saved by newer
+/// versions still load; the caller decides how to warn about them.
+pub fn parse_scene(content: &str) -> Result<(SceneConfig, Vec<String>), SceneError> {
+  let value: serde_json::Value = serde_json::from_str(content).map_err(|e| SceneError::Invalid(e.to_string()))?;
+
+  let unknown = match &value {
+    serde_json::Value::Object(map) => map.keys().filter(|k| !FIELDS.contains(&k.as_str())).cloned().collect(),
+    _ => return Err(SceneError::Invalid("expected a JSON object".to_string())),
+  };
+
+  let scene = serde_json::from_value(value).map_err(|e| SceneError::Invalid(e.to_string()))?;
+  validate(&scene)?;
+  Ok((scene, unknown))
+}
+
+/// Check settings against the same rules as the matching command-line flags
+///
+/// The camera distance is not checked against the zoom limits here; the
+/// viewer clamps it, as scrolling would.
+fn validate(scene: &SceneConfig) -> Result<(), SceneError> {
+  let invalid = |msg: String| Err(SceneError::Invalid(msg));
+
+  if let Some(name) = &scene.color_scheme
+    && !COLOR_SCHEMES.contains(&name.to_lowercase().as_str())
+  {
+    return invalid(format!("unknown color_scheme '{}' (expected {})", name, COLOR_SCHEMES.join(", ")));
+  }
+  if let Some(hex) = &scene.uniform_color
+    && Srgba::hex(hex).is_err()
+  {
+    return invalid(format!("uniform_color '{}' is not a hex color such as #ffffff", hex));
+  }
+  if let Some(name) = &scene.radii
+    && !RADIUS_SOURCES.contains(&name.to_lowercase().as_str())
+  {
+    return invalid(format!("unknown radii '{}' (expected {})", name, RADIUS_SOURCES.join(", ")));
+  }
+  if let Some(radius) = scene.uniform_radius
+    && !(radius.is_finite() && radius > 0.0)
+  {
+    return invalid(format!("uniform_radius must be a positive number, got {}", radius));
+  }
+  if let Some(strength) = scene.ambient_occlusion_strength
+    && !(strength.is_finite() && strength > 0.0)
+  {
+    return invalid(format!("ambient_occlusion_strength must be a positive number, got {}", strength));
+  }
+  if let Some(thickness) = scene.outline_thickness
+    && !(thickness.is_finite() && thickness >= 0.0)
+  {
+    return invalid(format!("outline_thickness must be zero or more, got {}", thickness));
+  }
+  if let Some(camera) = &scene.camera {
+    let length = camera.rotation.iter().map(|c| c * c).sum::<f32>().sqrt();
+    if !length.is_finite() || length < 1e-6 {
+      return invalid(format!("camera rotation {:?} is not a nonzero quaternion", camera.rotation));
+    }
+    if !(camera.distance.is_finite() && camera.distance > 0.0) {
+      return invalid(format!("camera distance must be a positive number, got {}", camera.distance));
+    }
+    if !camera.target.iter().all(|c| c.is_finite()) {
+      return invalid(format!("camera target {:?} is not a finite point", camera.target));
+    }
+  }
+  Ok(())
+}
+
+/// Write a scene to disk as pretty-printed JSON
+pub fn save_scene(path: &str, scene: &SceneConfig) -> Result<(), SceneError> {
+  let content = serde_json::to_string_pretty(scene).map_err(|e| SceneError::Invalid(e.to_string()))?;
+  fs::write(path, content + "\n").map_err(|e| SceneError::Io(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_partial_scene() {
+    let content = r#"{ "inputs": ["water.xyz"], "radii": "covalent", "camera": { "rotation": [0, 0, 0, 1], "distance": 8, "target": [1, 2, 3] } }"#;
+    let (scene, unknown) = parse_scene(content).unwrap();
+
+    assert_eq!(scene.inputs, vec!["water.xyz"]);
+    assert_eq!(scene.radii.as_deref(), Some("covalent"));
+    assert_eq!(scene.camera.unwrap().target, [1.0, 2.0, 3.0]);
+    assert_eq!(scene.shadows, None);
+    assert!(unknown.is_empty());
+  }
+
+  #[test]
+  fn test_report_unknown_fields() {
+    let (scene, unknown) = parse_scene(r#"{ "outlines": true, "lighting": "studio" }"#).unwrap();
+
+    assert_eq!(scene.outlines, Some(true));
+    assert_eq!(unknown, vec!["lighting"]);
+  }
+
+  #[test]
+  fn test_known_fields_match_config() {
+    let value = serde_json::to_value(SceneConfig::default()).unwrap();
+    let mut keys: Vec<&str> = value.as_object().unwrap().keys().map(String::as_str).collect();
+    let mut fields = FIELDS.to_vec();
+    keys.sort();
+    fields.sort();
+
+    assert_eq!(keys, fields);
+  }
+
+  #[test]
+  fn test_reject_invalid_scene() {
+    let err = parse_scene(r#"{ "outlines": "yes" }"#).unwrap_err().to_string();
+    assert!(err.contains("invalid scene file"), "Error was: {}", err);
+
+    let err = parse_scene("[1, 2]").unwrap_err().to_string();
+    assert!(err.contains("expected a JSON object"), "Error was: {}", err);
+  }
+
+  #[test]
+  fn test_reject_out_of_range_settings() {
+    for (content, message) in [
+      (r#"{ "uniform_radius": -1 }"#, "uniform_radius must be a positive number"),
+      (r#"{ "outline_thickness": -0.1 }"#, "outline_thickness must be zero or more"),
+      (r#"{ "ambient_occlusion_strength": 0 }"#, "ambient_occlusion_strength must be a positive number"),
+      (r#"{ "color_scheme": "rainbow" }"#, "unknown color_scheme 'rainbow'"),
+      (r#"{ "radii": "huge" }"#, "unknown radii 'huge'"),
+      (r#"{ "uniform_color": "blue" }"#, "uniform_color 'blue' is not a hex color"),
+    ] {
+      let err = parse_scene(content).unwrap_err().to_string();
+      assert!(err.contains(message), "Error was: {}", err);
+    }
+  }
+
+  #[test]
+  fn test_reject_degenerate_camera() {
+    let camera = |rotation: &str, distance: &str| {
+      format!(r#"{{ "camera": {{ "rotation": {}, "distance": {}, "target": [0, 0, 0] }} }}"#, rotation, distance)
+    };
+
+    let err = parse_scene(&camera("[0, 0, 0, 0]", "10")).unwrap_err().to_string();
+    assert!(err.contains("not a nonzero quaternion"), "Error was: {}", err);
+    let err = parse_scene(&camera("[0, 0, 0, 1]", "-5")).unwrap_err().to_string();
+    assert!(err.contains("camera distance must be a positive number"), "Error was: {}", err);
+    // Names are matched without regard to case, as on the command line
+    assert!(parse_scene(r#"{ "color_scheme": "Uniform", "radii": "VDW", "outline_thickness": 0 }"#).is_ok());
+  }
+
+  #[test]
+  fn test_round_trip() {
+    let scene = SceneConfig {
+      inputs: vec!["a.xyz".to_string(), "b.mol2".to_string()],
+      color_scheme: Some("uniform".to_string()),
+      uniform_color: Some("#336699".to_string()),
+      outline_thickness: Some(0.1),
+      ambient_occlusion_strength: Some(0.5),
+      camera: Some(SceneCamera {
+        rotation: [0.0, 0.6, 0.0, 0.8],
+        distance: 12.5,
+        target: [0.0, -1.0, 2.0],
+      }),
+      ..SceneConfig::default()
+    };
+    let content = serde_json::to_string_pretty(&scene).unwrap();
+
+    assert_eq!(parse_scene(&content).unwrap(), (scene, Vec::new()));
+  }
+}
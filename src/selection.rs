@@ -0,0 +1,383 @@
+use crate::parser::Molecule;
+use crate::spatial::SpatialGrid;
+use std::error::Error;
+use std::fmt;
+
+/// Parsed atom selection query
+///
+/// The grammar is a small subset of the VMD selection language:
+///
+/// ```text
+/// expr    := and ("or" and)*
+/// and     := unary ("and" unary)*
+/// unary   := "not" unary | "within" NUMBER "of" unary | primary
+/// primary := "(" expr ")" | "all" | "none"
+///          | "element" SYMBOL+ | "index" RANGE+
+/// ```
+///
+/// Indices are 0-based and ranges such as `1-5` are inclusive. Keywords and
+/// element symbols are case-insensitive.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Selection {
+  All,
+  None,
+  Element(Vec<String>),
+  Index(Vec<(usize, usize)>),
+  Within(f64, Box<Selection>),
+  Not(Box<Selection>),
+  And(Box<Selection>, Box<Selection>),
+  Or(Box<Selection>, Box<Selection>),
+}
+
+/// Selection query error types
+#[derive(Debug, Clone, PartialEq)]
+pub enum SelectionError {
+  UnexpectedEnd,
+  UnexpectedToken(String),
+  InvalidNumber(String),
+  InvalidRange(String),
+}
+
+impl fmt::Display for SelectionError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      SelectionError::UnexpectedEnd => write!(f, "unexpected end of selection"),
+      SelectionError::UnexpectedToken(token) => write!(f, "unexpected '{}' in selection", token),
+      SelectionError::InvalidNumber(token) => write!(f, "'{}' is not a valid distance", token),
+      SelectionError::InvalidRange(token) => write!(f, "'{}' is not a valid index range", token),
+    }
+  }
+}
+
+impl Error for SelectionError {}
+
+const KEYWORDS: [&str; 9] = ["and", "or", "not", "within", "of", "all", "none", "element", "index"];
+
+impl Selection {
+  /// Parse a selection query
+  pub fn parse(query: &str) -> Result<Selection, SelectionError> {
+    let tokens = tokenize(query);
+    let mut parser = SelectionParser { tokens, pos: 0 };
+    let selection = parser.expr()?;
+
+    match parser.peek() {
+      Some(token) => Err(SelectionError::UnexpectedToken(token.to_string())),
+      None => Ok(selection),
+    }
+  }
+
+  /// Indices of the atoms in `molecule` matching this selection, in ascending order
+  pub fn evaluate(&self, molecule: &Molecule) -> Vec<usize> {
+    self
+      .mask(molecule)
+      .iter()
+      .enumerate()
+      .filter_map(|(i, &selected)| selected.then_some(i))
+      .collect()
+  }
+
+  fn mask(&self, molecule: &Molecule) -> Vec<bool> {
+    let atoms = &molecule.atoms;
+    match self {
+      Selection::All => vec![true; atoms.len()],
+      Selection::None => vec![false; atoms.len()],
+      Selection::Element(symbols) => atoms
+        .iter()
+        .map(|a| symbols.iter().any(|s| s.eq_ignore_ascii_case(&a.element)))
+        .collect(),
+      Selection::Index(ranges) => (0..atoms.len())
+        .map(|i| ranges.iter().any(|&(start, end)| start <= i && i <= end))
+        .collect(),
+      Selection::Within(distance, inner) => {
+        let inner = inner.mask(molecule);
+        let mut mask = inner.clone();
+        if atoms.is_empty() || *distance <= 0.0 {
+          return mask;
+        }
+
+        let positions: Vec<[f64; 3]> = atoms.iter().map(|a| [a.x, a.y, a.z]).collect();
+        let grid = SpatialGrid::new(&positions, *distance);
+        for (i, _) in inner.iter().enumerate().filter(|(_, selected)| **selected) {
+          for j in grid.neighbors_within(positions[i], *distance) {
+            mask[j] = true;
+          }
+        }
+        mask
+      }
+      Selection::Not(inner) => inner.mask(molecule).iter().map(|s| !s).collect(),
+      Selection::And(a, b) => a.mask(molecule).iter().zip(b.mask(molecule)).map(|(x, y)| *x && y).collect(),
+      Selection::Or(a, b) => a.mask(molecule).iter().zip(b.mask(molecule)).map(|(x, y)| *x || y).collect(),
+    }
+  }
+}
+
+impl Molecule {
+  /// Indices of atoms matching a selection query such as `element O and index 0-5`
+  ///
+  /// See [`Selection`] for the supported grammar.
+  pub fn select(&self, query: &str) -> Result<Vec<usize>, SelectionError> {
+    Ok(Selection::parse(query)?.evaluate(self))
+  }
+
+  /// Copy of the molecule keeping only atoms of the listed elements
+  ///
+  /// Element symbols match case-insensitively and the kept atoms stay in file
+  /// order. Use [`Molecule::filter_elements_indexed`] to also get their
+  /// original indices.
+  pub fn filter_elements(&self, elements: &[&str]) -> Molecule {
+    self.filter_elements_indexed(elements).0
+  }
+
+  /// Like [`Molecule::filter_elements`], also returning the index in `self` of
+  /// each kept atom
+  pub fn filter_elements_indexed(&self, elements: &[&str]) -> (Molecule, Vec<usize>) {
+    let symbols = elements.iter().map(|e| e.to_string()).collect();
+    let indices = Selection::Element(symbols).evaluate(self);
+    let molecule = Molecule {
+      atoms: indices.iter().map(|&i| self.atoms[i].clone()).collect(),
+      comment: self.comment.clone(),
+      charge: self.charge,
+      multiplicity: self.multiplicity,
+    };
+    (molecule, indices)
+  }
+}
+
+/// Split a query into words, treating parentheses as separate tokens
+fn tokenize(query: &str) -> Vec<String> {
+  query
+    .replace('(', " ( ")
+    .replace(')', " ) ")
+    .split_whitespace()
+    .map(str::to_string)
+    .collect()
+}
+
+/// Recursive-descent parser over the query tokens
+struct SelectionParser {
+  tokens: Vec<String>,
+  pos: usize,
+}
+
+impl SelectionParser {
+  fn peek(&self) -> Option<&str> {
+    self.tokens.get(self.pos).map(String::as_str)
+  }
+
+  fn next(&mut self) -> Result<String, SelectionError> {
+    let token = self.tokens.get(self.pos).cloned().ok_or(SelectionError::UnexpectedEnd)?;
+    self.pos += 1;
+    Ok(token)
+  }
+
+  fn eat_keyword(&mut self, keyword: &str) -> bool {
+    if self.peek().is_some_and(|t| t.eq_ignore_ascii_case(keyword)) {
+      self.pos += 1;
+      true
+    } else {
+      false
+    }
+  }
+
+  fn expr(&mut self) -> Result<Selection, SelectionError> {
+    let mut left = self.and()?;
+    while self.eat_keyword("or") {
+      left = Selection::Or(Box::new(left), Box::new(self.and()?));
+    }
+    Ok(left)
+  }
+
+  fn and(&mut self) -> Result<Selection, SelectionError> {
+    let mut left = self.unary()?;
+    while self.eat_keyword("and") {
+      left = Selection::And(Box::new(left), Box::new(self.unary()?));
+    }
+    Ok(left)
+  }
+
+  fn unary(&mut self) -> Result<Selection, SelectionError> {
+    if self.eat_keyword("not") {
+      return Ok(Selection::Not(Box::new(self.unary()?)));
+    }
+
+    if self.eat_keyword("within") {
+      let token = self.next()?;
+      let distance = token
+        .parse::<f64>()
+        .ok()
+        .filter(|d| d.is_finite() && *d >= 0.0)
+        .ok_or(SelectionError::InvalidNumber(token))?;
+      if !self.eat_keyword("of") {
+        return Err(self.unexpected());
+      }
+      return Ok(Selection::Within(distance, Box::new(self.unary()?)));
+    }
+
+    self.primary()
+  }
+
+  fn primary(&mut self) -> Result<Selection, SelectionError> {
+    let token = self.next()?;
+
+    match token.to_lowercase().as_str() {
+      "(" => {
+        let inner = self.expr()?;
+        match self.next()?.as_str() {
+          ")" => Ok(inner),
+          other => Err(SelectionError::UnexpectedToken(other.to_string())),
+        }
+      }
+      "all" => Ok(Selection::All),
+      "none" => Ok(Selection::None),
+      "element" => {
+        let mut symbols = Vec::new();
+        while let Some(symbol) = self.peek() {
+          if symbol == "(" || symbol == ")" || KEYWORDS.iter().any(|k| symbol.eq_ignore_ascii_case(k)) {
+            break;
+          }
+          symbols.push(symbol.to_string());
+          self.pos += 1;
+        }
+        if symbols.is_empty() {
+          return Err(self.unexpected());
+        }
+        Ok(Selection::Element(symbols))
+      }
+      "index" => {
+        let mut ranges = Vec::new();
+        while let Some(token) = self.peek() {
+          if !token.starts_with(|c: char| c.is_ascii_digit()) {
+            break;
+          }
+          let range = parse_range(token).ok_or_else(|| SelectionError::InvalidRange(token.to_string()))?;
+          ranges.push(range);
+          self.pos += 1;
+        }
+        if ranges.is_empty() {
+          return Err(self.unexpected());
+        }
+        Ok(Selection::Index(ranges))
+      }
+      _ => Err(SelectionError::UnexpectedToken(token)),
+    }
+  }
+
+  /// Error for whatever token is next, or for running out of tokens
+  fn unexpected(&self) -> SelectionError {
+    match self.peek() {
+      Some(token) => SelectionError::UnexpectedToken(token.to_string()),
+      None => SelectionError::UnexpectedEnd,
+    }
+  }
+}
+
+/// Parse `N` or an inclusive range `N-M`
+fn parse_range(token: &str) -> Option<(usize, usize)> {
+  match token.split_once('-') {
+    Some((start, end)) => {
+      let (start, end) = (start.parse().ok()?, end.parse().ok()?);
+      (start <= end).then_some((start, end))
+    }
+    None => token.parse().ok().map(|i| (i, i)),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parser::parse_xyz_str;
+
+  fn water_dimer() -> Molecule {
+    parse_xyz_str(
+      "6\ndimer\nO 0.0 0.0 0.0\nH 0.757 0.586 0.0\nH -0.757 0.586 0.0\n\
+       O 3.0 0.0 0.0\nH 3.757 0.586 0.0\nH 2.243 0.586 0.0\n",
+    )
+    .unwrap()
+  }
+
+  #[test]
+  fn test_select_element() {
+    let molecule = water_dimer();
+
+    assert_eq!(molecule.select("element O").unwrap(), vec![0, 3]);
+    assert_eq!(molecule.select("ELEMENT h o").unwrap(), vec![0, 1, 2, 3, 4, 5]);
+  }
+
+  #[test]
+  fn test_select_index_ranges() {
+    let molecule = water_dimer();
+
+    assert_eq!(molecule.select("index 1-2 5").unwrap(), vec![1, 2, 5]);
+    assert_eq!(molecule.select("index 4-10").unwrap(), vec![4, 5]);
+  }
+
+  #[test]
+  fn test_select_all_and_none() {
+    let molecule = water_dimer();
+
+    assert_eq!(molecule.select("all").unwrap().len(), 6);
+    assert!(molecule.select("none").unwrap().is_empty());
+  }
+
+  #[test]
+  fn test_select_and_or_not() {
+    let molecule = water_dimer();
+
+    assert_eq!(molecule.select("element H and index 0-2").unwrap(), vec![1, 2]);
+    assert_eq!(molecule.select("index 0 or index 4").unwrap(), vec![0, 4]);
+    assert_eq!(molecule.select("not element H").unwrap(), vec![0, 3]);
+  }
+
+  #[test]
+  fn test_and_binds_tighter_than_or() {
+    let molecule = water_dimer();
+
+    assert_eq!(molecule.select("index 0 or element H and index 3-5").unwrap(), vec![0, 4, 5]);
+    assert_eq!(molecule.select("(index 0 or element H) and index 0-3").unwrap(), vec![0, 1, 2]);
+  }
+
+  #[test]
+  fn test_select_within() {
+    let molecule = water_dimer();
+
+    // The inner selection is included, as in VMD
+    assert_eq!(molecule.select("within 2.4 of index 0").unwrap(), vec![0, 1, 2, 5]);
+    assert_eq!(molecule.select("element O and within 2.4 of index 1").unwrap(), vec![0, 3]);
+    assert_eq!(molecule.select("within 0 of index 4").unwrap(), vec![4]);
+  }
+
+  #[test]
+  fn test_filter_elements() {
+    let molecule = water_dimer();
+    let (oxygens, indices) = molecule.filter_elements_indexed(&["o"]);
+
+    assert_eq!(indices, vec![0, 3]);
+    assert_eq!(oxygens.atoms.len(), 2);
+    assert_eq!(oxygens.atoms[1].x, 3.0);
+    assert_eq!(oxygens.comment, "dimer");
+    assert_eq!(molecule.filter_elements(&["O", "H"]), molecule);
+    assert!(molecule.filter_elements(&["Fe"]).atoms.is_empty());
+  }
+
+  #[test]
+  fn test_reject_malformed_queries() {
+    let molecule = water_dimer();
+    let error = |query: &str| molecule.select(query).unwrap_err();
+
+    assert_eq!(error(""), SelectionError::UnexpectedEnd);
+    assert_eq!(error("element"), SelectionError::UnexpectedEnd);
+    assert_eq!(error("index 5-2"), SelectionError::InvalidRange("5-2".to_string()));
+    assert_eq!(error("within far of all"), SelectionError::InvalidNumber("far".to_string()));
+    assert_eq!(error("within 2 all"), SelectionError::UnexpectedToken("all".to_string()));
+    assert_eq!(error("(index 0"), SelectionError::UnexpectedEnd);
+    assert_eq!(error("index 0 index 1"), SelectionError::UnexpectedToken("index".to_string()));
+    assert_eq!(error("residue 5"), SelectionError::UnexpectedToken("residue".to_string()));
+  }
+
+  #[test]
+  fn test_error_display() {
+    let err = water_dimer().select("element O and").unwrap_err().to_string();
+
+    assert!(err.contains("unexpected end of selection"), "Error was: {}", err);
+  }
+}
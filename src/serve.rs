@@ -0,0 +1,225 @@
+use crate::parser::Molecule;
+use serde::Serialize;
+use std::error::Error;
+use std::fmt;
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tungstenite::{Message, WebSocket};
+
+/// New position of one atom in a `delta` message
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MovedAtom {
+  pub index: usize,
+  pub position: [f64; 3],
+}
+
+/// JSON message sent to clients, tagged by `type`
+///
+/// A client receives a `frame` with the whole molecule when it connects and
+/// whenever the atoms' count or elements change. Other updates are a
+/// `delta` listing only the atoms that moved; deltas carry no comment.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage<'a> {
+  Frame { molecule: &'a Molecule },
+  Delta { moved: Vec<MovedAtom> },
+}
+
+impl ServerMessage<'_> {
+  /// Message taking clients from `previous` to `current`, or `None` if no
+  /// atom moved
+  pub fn between<'a>(previous: Option<&Molecule>, current: &'a Molecule) -> Option<ServerMessage<'a>> {
+    let Some(previous) = previous.filter(|p| {
+      p.atoms.len() == current.atoms.len() && p.atoms.iter().zip(&current.atoms).all(|(a, b)| a.element == b.element)
+    }) else {
+      return Some(ServerMessage::Frame { molecule: current });
+    };
+
+    let moved: Vec<MovedAtom> = previous
+      .atoms
+      .iter()
+      .zip(&current.atoms)
+      .enumerate()
+      .filter(|(_, (a, b))| (a.x, a.y, a.z) != (b.x, b.y, b.z))
+      .map(|(index, (_, b))| MovedAtom {
+        index,
+        position: [b.x, b.y, b.z],
+      })
+      .collect();
+    (!moved.is_empty()).then_some(ServerMessage::Delta { moved })
+  }
+
+  /// Encode as a WebSocket text message
+  fn to_message(&self) -> Message {
+    Message::text(serde_json::to_string(self).expect("server messages always serialize"))
+  }
+}
+
+/// How long a connecting client may take to finish its WebSocket handshake
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Server error types
+#[derive(Debug, Clone, PartialEq)]
+pub enum ServeError {
+  Bind(String),
+}
+
+impl fmt::Display for ServeError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      ServeError::Bind(msg) => write!(f, "could not listen for connections: {}", msg),
+    }
+  }
+}
+
+impl Error for ServeError {}
+
+/// State shared by the accept thread and publishers
+#[derive(Default)]
+struct Shared {
+  current: Option<Molecule>,
+  clients: Vec<WebSocket<TcpStream>>,
+}
+
+/// WebSocket server pushing the latest molecule to every connected client
+pub struct FrameServer {
+  shared: Arc<Mutex<Shared>>,
+  local_addr: SocketAddr,
+}
+
+impl FrameServer {
+  /// Listen on `addr`, accepting clients on a background thread
+  ///
+  /// Each client is sent the current frame, if any, as soon as its
+  /// handshake completes. Handshakes run on their own thread so a slow or
+  /// silent connection cannot hold up other clients; ones that fail or take
+  /// longer than `HANDSHAKE_TIMEOUT` are dropped.
+  pub fn bind<A: ToSocketAddrs>(addr: A) -> Result<Self, ServeError> {
+    let listener = TcpListener::bind(addr).map_err(|e| ServeError::Bind(e.to_string()))?;
+    let local_addr = listener.local_addr().map_err(|e| ServeError::Bind(e.to_string()))?;
+    let shared = Arc::new(Mutex::new(Shared::default()));
+
+    let accepted = Arc::clone(&shared);
+    std::thread::spawn(move || {
+      for stream in listener.incoming().flatten() {
+        let accepted = Arc::clone(&accepted);
+        std::thread::spawn(move || {
+          if stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT)).is_err() {
+            return;
+          }
+          let Ok(mut client) = tungstenite::accept(stream) else {
+            return;
+          };
+          if client.get_ref().set_read_timeout(None).is_err() {
+            return;
+          }
+          let mut shared = accepted.lock().expect("frame server lock poisoned");
+          if let Some(molecule) = &shared.current
+            && client.send(ServerMessage::Frame { molecule }.to_message()).is_err()
+          {
+            return;
+          }
+          shared.clients.push(client);
+        });
+      }
+    });
+
+    Ok(Self { shared, local_addr })
+  }
+
+  /// Address the server is listening on
+  pub fn local_addr(&self) -> SocketAddr {
+    self.local_addr
+  }
+
+  /// Send `molecule` to every client and keep it for clients that connect later
+  ///
+  /// Clients that can no longer be reached are dropped.
+  pub fn publish(&self, molecule: &Molecule) {
+    let mut shared = self.shared.lock().expect("frame server lock poisoned");
+    if let Some(update) = ServerMessage::between(shared.current.as_ref(), molecule) {
+      let message = update.to_message();
+      shared.clients.retain_mut(|client| client.send(message.clone()).is_ok());
+    }
+    shared.current = Some(molecule.clone());
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parser::parse_xyz_str;
+
+  const WATER: &str = "3\nwater\nO 0.0 0.0 0.117\nH 0.0 0.757 -0.467\nH 0.0 -0.757 -0.467\n";
+
+  #[test]
+  fn test_delta_lists_moved_atoms() {
+    let before = parse_xyz_str(WATER).unwrap();
+    let mut after = before.clone();
+    after.atoms[2].y = -0.8;
+
+    assert_eq!(
+      ServerMessage::between(Some(&before), &after),
+      Some(ServerMessage::Delta {
+        moved: vec![MovedAtom {
+          index: 2,
+          position: [0.0, -0.8, -0.467],
+        }],
+      })
+    );
+    assert_eq!(ServerMessage::between(Some(&before), &before), None);
+  }
+
+  #[test]
+  fn test_full_frame_when_atoms_change() {
+    let water = parse_xyz_str(WATER).unwrap();
+    let helium = parse_xyz_str("1\n\nHe 0 0 0\n").unwrap();
+
+    assert_eq!(ServerMessage::between(None, &water), Some(ServerMessage::Frame { molecule: &water }));
+    assert_eq!(ServerMessage::between(Some(&water), &helium), Some(ServerMessage::Frame { molecule: &helium }));
+  }
+
+  #[test]
+  fn test_message_format() {
+    let delta = ServerMessage::Delta {
+      moved: vec![MovedAtom {
+        index: 1,
+        position: [1.0, 2.0, 3.0],
+      }],
+    };
+    assert_eq!(
+      serde_json::to_string(&delta).unwrap(),
+      r#"{"type":"delta","moved":[{"index":1,"position":[1.0,2.0,3.0]}]}"#
+    );
+  }
+
+  #[test]
+  fn test_client_receives_frame_then_delta() {
+    let server = FrameServer::bind("127.0.0.1:0").unwrap();
+    let mut water = parse_xyz_str(WATER).unwrap();
+    server.publish(&water);
+
+    let (mut client, _) = tungstenite::connect(format!("ws://{}", server.local_addr())).unwrap();
+    let frame: serde_json::Value = serde_json::from_str(client.read().unwrap().to_text().unwrap()).unwrap();
+    assert_eq!(frame["type"], "frame");
+    assert_eq!(frame["molecule"]["comment"], "water");
+
+    water.atoms[0].z = 0.2;
+    server.publish(&water);
+    let delta: serde_json::Value = serde_json::from_str(client.read().unwrap().to_text().unwrap()).unwrap();
+    assert_eq!(delta["type"], "delta");
+    assert_eq!(delta["moved"][0]["index"], 0);
+  }
+
+  #[test]
+  fn test_silent_connection_does_not_block_clients() {
+    let server = FrameServer::bind("127.0.0.1:0").unwrap();
+    server.publish(&parse_xyz_str(WATER).unwrap());
+
+    let _silent = TcpStream::connect(server.local_addr()).unwrap();
+    let (mut client, _) = tungstenite::connect(format!("ws://{}", server.local_addr())).unwrap();
+    let frame: serde_json::Value = serde_json::from_str(client.read().unwrap().to_text().unwrap()).unwrap();
+    assert_eq!(frame["type"], "frame");
+  }
+}
@@ -0,0 +1,176 @@
+use std::collections::HashMap;
+
+/// Uniform grid that buckets points into cubic cells for fast neighbor queries
+#[derive(Debug, Clone)]
+pub struct SpatialGrid {
+  cell_size: f64,
+  positions: Vec<[f64; 3]>,
+  cells: HashMap<[i64; 3], Vec<usize>>,
+}
+
+impl SpatialGrid {
+  /// Build a grid over `positions` with cubic cells of edge `cell_size`
+  ///
+  /// A cell size close to the typical query radius gives the best performance.
+  ///
+  /// # Panics
+  ///
+  /// Panics if `cell_size` is not a positive, finite number.
+  pub fn new(positions: &[[f64; 3]], cell_size: f64) -> Self {
+    assert!(
+      cell_size.is_finite() && cell_size > 0.0,
+      "cell size must be positive and finite, got {}",
+      cell_size
+    );
+
+    let mut cells: HashMap<[i64; 3], Vec<usize>> = HashMap::new();
+    for (i, p) in positions.iter().enumerate() {
+      cells.entry(cell_of(p, cell_size)).or_default().push(i);
+    }
+
+    Self {
+      cell_size,
+      positions: positions.to_vec(),
+      cells,
+    }
+  }
+
+  /// Number of points in the grid
+  pub fn len(&self) -> usize {
+    self.positions.len()
+  }
+
+  /// Whether the grid holds no points
+  pub fn is_empty(&self) -> bool {
+    self.positions.is_empty()
+  }
+
+  /// Indices of all points within `radius` of `point` (inclusive), in ascending order
+  pub fn neighbors_within(&self, point: [f64; 3], radius: f64) -> Vec<usize> {
+    if radius.is_nan() || radius < 0.0 {
+      return Vec::new();
+    }
+
+    let r2 = radius * radius;
+    let lo = cell_of(&[point[0] - radius, point[1] - radius, point[2] - radius], self.cell_size);
+    let hi = cell_of(&[point[0] + radius, point[1] + radius, point[2] + radius], self.cell_size);
+
+    let within = |i: &usize| distance_squared(&self.positions[*i], &point) <= r2;
+
+    // Scanning a huge empty region is slower than checking every occupied cell
+    let span = (0..3).fold(1u128, |acc, k| {
+      acc.saturating_mul((hi[k] as i128 - lo[k] as i128 + 1) as u128)
+    });
+
+    let mut result: Vec<usize> = if span > self.cells.len() as u128 {
+      self
+        .cells
+        .iter()
+        .filter(|(cell, _)| (0..3).all(|k| cell[k] >= lo[k] && cell[k] <= hi[k]))
+        .flat_map(|(_, indices)| indices.iter().filter(|i| within(i)).copied())
+        .collect()
+    } else {
+      let mut found = Vec::new();
+      for x in lo[0]..=hi[0] {
+        for y in lo[1]..=hi[1] {
+          for z in lo[2]..=hi[2] {
+            if let Some(indices) = self.cells.get(&[x, y, z]) {
+              found.extend(indices.iter().filter(|i| within(i)).copied());
+            }
+          }
+        }
+      }
+      found
+    };
+
+    result.sort_unstable();
+    result
+  }
+}
+
+/// Cell coordinates containing a point
+fn cell_of(p: &[f64; 3], cell_size: f64) -> [i64; 3] {
+  [
+    (p[0] / cell_size).floor() as i64,
+    (p[1] / cell_size).floor() as i64,
+    (p[2] / cell_size).floor() as i64,
+  ]
+}
+
+/// Squared Euclidean distance between two points
+pub fn distance_squared(a: &[f64; 3], b: &[f64; 3]) -> f64 {
+  let dx = a[0] - b[0];
+  let dy = a[1] - b[1];
+  let dz = a[2] - b[2];
+  dx * dx + dy * dy + dz * dz
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // Small deterministic xorshift generator so tests need no extra dependency
+  fn random_points(n: usize, extent: f64, mut seed: u64) -> Vec<[f64; 3]> {
+    let mut next = || {
+      seed ^= seed << 13;
+      seed ^= seed >> 7;
+      seed ^= seed << 17;
+      (seed >> 11) as f64 / (1u64 << 53) as f64 * extent - extent / 2.0
+    };
+    (0..n).map(|_| [next(), next(), next()]).collect()
+  }
+
+  fn brute_force(points: &[[f64; 3]], point: [f64; 3], radius: f64) -> Vec<usize> {
+    (0..points.len())
+      .filter(|&i| distance_squared(&points[i], &point) <= radius * radius)
+      .collect()
+  }
+
+  #[test]
+  fn test_matches_brute_force_on_random_points() {
+    for seed in [1, 42, 0x9e3779b97f4a7c15] {
+      let points = random_points(500, 20.0, seed);
+      let grid = SpatialGrid::new(&points, 2.0);
+      let queries = random_points(50, 24.0, seed.wrapping_add(7));
+
+      for query in queries {
+        for radius in [0.5, 2.0, 3.7] {
+          assert_eq!(grid.neighbors_within(query, radius), brute_force(&points, query, radius));
+        }
+      }
+    }
+  }
+
+  #[test]
+  fn test_matches_brute_force_for_radius_much_larger_than_cells() {
+    let points = random_points(200, 10.0, 3);
+    let grid = SpatialGrid::new(&points, 0.1);
+
+    assert_eq!(grid.neighbors_within([0.0, 0.0, 0.0], 1e6), brute_force(&points, [0.0, 0.0, 0.0], 1e6));
+  }
+
+  #[test]
+  fn test_includes_points_exactly_at_radius() {
+    let points = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [-2.0, 0.0, 0.0]];
+    let grid = SpatialGrid::new(&points, 1.0);
+
+    assert_eq!(grid.neighbors_within([0.0, 0.0, 0.0], 1.0), vec![0, 1]);
+  }
+
+  #[test]
+  fn test_empty_grid_and_invalid_radius_return_nothing() {
+    let grid = SpatialGrid::new(&[], 1.0);
+    assert!(grid.is_empty());
+    assert!(grid.neighbors_within([0.0, 0.0, 0.0], 5.0).is_empty());
+
+    let grid = SpatialGrid::new(&[[0.0, 0.0, 0.0]], 1.0);
+    assert!(grid.neighbors_within([0.0, 0.0, 0.0], -1.0).is_empty());
+    assert!(grid.neighbors_within([0.0, 0.0, 0.0], f64::NAN).is_empty());
+  }
+
+  #[test]
+  #[should_panic(expected = "cell size must be positive")]
+  fn test_reject_zero_cell_size() {
+    SpatialGrid::new(&[[0.0, 0.0, 0.0]], 0.0);
+  }
+}
@@ -0,0 +1,188 @@
+use bevy::camera::visibility::RenderLayers;
+use bevy::camera::{RenderTarget, ScalingMode};
+use bevy::core_pipeline::tonemapping::Tonemapping;
+use bevy::light::NotShadowCaster;
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureFormat};
+use bevy::window::PrimaryWindow;
+
+/// Render layer holding the full-screen quads that composite the eye images
+const COMPOSITE_LAYER: usize = 1;
+
+/// Red/cyan anaglyph stereo settings
+#[derive(Resource)]
+pub struct StereoSettings {
+  pub enabled: bool,
+  /// Distance between the left and right eye cameras
+  pub eye_separation: f32,
+}
+
+impl Default for StereoSettings {
+  fn default() -> Self {
+    Self {
+      enabled: false,
+      eye_separation: 0.3,
+    }
+  }
+}
+
+/// Offscreen camera for one eye of the stereo pair (-1.0 for left, 1.0 for right)
+#[derive(Component)]
+pub struct StereoEye(pub f32);
+
+/// Camera that composites the two eye images into the anaglyph
+#[derive(Component)]
+pub struct StereoComposite;
+
+/// Offscreen images the eye cameras render into
+#[derive(Resource)]
+pub struct StereoImages {
+  left: Handle<Image>,
+  right: Handle<Image>,
+}
+
+/// Spawn the (inactive) eye cameras and the compositing scene
+pub fn setup_stereo(
+  mut commands: Commands,
+  mut meshes: ResMut<Assets<Mesh>>,
+  mut materials: ResMut<Assets<StandardMaterial>>,
+  mut images: ResMut<Assets<Image>>,
+  window: Single<&Window, With<PrimaryWindow>>,
+) {
+  let width = window.physical_width().max(1);
+  let height = window.physical_height().max(1);
+  let composite_layer = RenderLayers::layer(COMPOSITE_LAYER);
+  let quad = meshes.add(Rectangle::new(1.0, 1.0));
+
+  let eyes = [
+    (-1.0, Color::srgb(1.0, 0.0, 0.0), -2),
+    (1.0, Color::srgb(0.0, 1.0, 1.0), -1),
+  ];
+  let mut eye_images = Vec::with_capacity(eyes.len());
+
+  for (side, tint, order) in eyes {
+    let image = images.add(Image::new_target_texture(
+      width,
+      height,
+      TextureFormat::Rgba8Unorm,
+      Some(TextureFormat::Rgba8UnormSrgb),
+    ));
+
+    // Eye camera; its transform is kept in sync by update_camera
+    commands.spawn((
+      Camera3d::default(),
+      Camera {
+        order,
+        is_active: false,
+        ..default()
+      },
+      RenderTarget::Image(image.clone().into()),
+      StereoEye(side),
+    ));
+
+    // Full-screen quad showing this eye through a red or cyan filter.
+    // Additive blending sums the two filtered images into the anaglyph.
+    commands.spawn((
+      Mesh3d(quad.clone()),
+      MeshMaterial3d(materials.add(StandardMaterial {
+        base_color: tint,
+        base_color_texture: Some(image.clone()),
+        alpha_mode: AlphaMode::Add,
+        unlit: true,
+        ..default()
+      })),
+      NotShadowCaster,
+      composite_layer.clone(),
+    ));
+
+    eye_images.push(image);
+  }
+
+  commands.spawn((
+    Camera3d::default(),
+    Camera {
+      order: 1,
+      is_active: false,
+      clear_color: ClearColorConfig::Custom(Color::BLACK),
+      ..default()
+    },
+    Projection::from(OrthographicProjection {
+      scaling_mode: ScalingMode::Fixed {
+        width: 1.0,
+        height: 1.0,
+      },
+      ..OrthographicProjection::default_3d()
+    }),
+    Tonemapping::None,
+    Transform::from_xyz(0.0, 0.0, 1.0).looking_at(Vec3::ZERO, Vec3::Y),
+    composite_layer,
+    StereoComposite,
+  ));
+
+  commands.insert_resource(StereoImages {
+    left: eye_images[0].clone(),
+    right: eye_images[1].clone(),
+  });
+}
+
+pub fn toggle_stereo(
+  keyboard: Res<ButtonInput<KeyCode>>,
+  mut settings: ResMut<StereoSettings>,
+) {
+  if keyboard.just_pressed(KeyCode::Digit3) {
+    settings.enabled = !settings.enabled;
+  }
+  if keyboard.just_pressed(KeyCode::BracketLeft) {
+    settings.eye_separation = (settings.eye_separation - 0.05).max(0.0);
+  }
+  if keyboard.just_pressed(KeyCode::BracketRight) {
+    settings.eye_separation += 0.05;
+  }
+}
+
+/// Switch between the normal camera and the stereo cameras
+///
+/// The eye cameras take the normal camera's projection, so a custom field
+/// of view or clip planes apply in stereo too.
+pub fn apply_stereo(
+  settings: Res<StereoSettings>,
+  mut camera_query: Query<(&mut Camera, &mut Projection, Has<StereoEye>, Has<StereoComposite>)>,
+) {
+  if !settings.is_changed() {
+    return;
+  }
+
+  let main_projection = camera_query
+    .iter()
+    .find(|(_, _, is_eye, is_composite)| !is_eye && !is_composite)
+    .map(|(_, projection, _, _)| projection.clone());
+  for (mut camera, mut projection, is_eye, is_composite) in camera_query.iter_mut() {
+    camera.is_active = (is_eye || is_composite) == settings.enabled;
+    if is_eye && let Some(main_projection) = &main_projection {
+      *projection = main_projection.clone();
+    }
+  }
+}
+
+/// Keep the eye images the same size as the window
+pub fn resize_stereo_images(
+  window_query: Query<&Window, (With<PrimaryWindow>, Changed<Window>)>,
+  stereo_images: Res<StereoImages>,
+  mut images: ResMut<Assets<Image>>,
+) {
+  for window in window_query.iter() {
+    let size = Extent3d {
+      width: window.physical_width().max(1),
+      height: window.physical_height().max(1),
+      depth_or_array_layers: 1,
+    };
+
+    for handle in [&stereo_images.left, &stereo_images.right] {
+      if let Some(image) = images.get_mut(handle)
+        && image.texture_descriptor.size != size
+      {
+        image.resize(size);
+      }
+    }
+  }
+}
@@ -0,0 +1,70 @@
+//! Synthetic structures for tests and benchmarks
+//!
+//! These build large, deterministic inputs in memory so tests of scaling
+//! behavior don't need big fixture files checked in.
+
+use crate::parser::{Atom, Molecule};
+
+/// Simple cubic lattice of `nx * ny * nz` atoms of one element
+///
+/// The atom at lattice site `(i, j, k)` sits at
+/// `(i * spacing, j * spacing, k * spacing)`, so the lattice starts at the
+/// origin and extends along the positive axes. Atoms are ordered with `i`
+/// varying fastest, then `j`, then `k`: site `(i, j, k)` has index
+/// `i + nx * (j + ny * k)`. The comment records the lattice shape.
+pub fn generate_lattice_molecule(nx: usize, ny: usize, nz: usize, element: &str, spacing: f64) -> Molecule {
+  let mut atoms = Vec::with_capacity(nx * ny * nz);
+  for k in 0..nz {
+    for j in 0..ny {
+      for i in 0..nx {
+        atoms.push(Atom {
+          element: element.to_string(),
+          x: i as f64 * spacing,
+          y: j as f64 * spacing,
+          z: k as f64 * spacing,
+          isotope: None,
+          label: None,
+          extra: Vec::new(),
+          atom_type: None,
+          frozen: false,
+          partial_charge: None,
+          formal_charge: None,
+          mass: None,
+        });
+      }
+    }
+  }
+
+  Molecule {
+    atoms,
+    comment: format!("{}x{}x{} {} lattice, spacing {}", nx, ny, nz, element, spacing),
+    charge: None,
+    multiplicity: None,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_lattice_layout() {
+    let lattice = generate_lattice_molecule(3, 2, 4, "Ar", 1.5);
+
+    assert_eq!(lattice.atoms.len(), 24);
+    assert!(lattice.atoms.iter().all(|a| a.element == "Ar"));
+
+    // Site (1, 1, 2) has index 1 + 3 * (1 + 2 * 2)
+    let atom = &lattice.atoms[16];
+    assert_eq!((atom.x, atom.y, atom.z), (1.5, 1.5, 3.0));
+
+    let (min, max) = lattice.bounding_box().unwrap();
+    assert_eq!(min, [0.0, 0.0, 0.0]);
+    assert_eq!(max, [3.0, 1.5, 4.5]);
+  }
+
+  #[test]
+  fn test_empty_lattice() {
+    assert!(generate_lattice_molecule(0, 5, 5, "C", 1.0).atoms.is_empty());
+  }
+}
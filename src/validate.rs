@@ -0,0 +1,141 @@
+use crate::parser::Molecule;
+use crate::spatial::{distance_squared, SpatialGrid};
+use std::fmt;
+
+/// Atoms closer than this (in Angstrom) are treated as duplicates
+pub const DUPLICATE_TOLERANCE: f64 = 1e-4;
+
+/// Atoms closer than this (in Angstrom) are unlikely to be bonded partners;
+/// the shortest real bond, H-H, is about 0.74
+pub const SHORT_DISTANCE: f64 = 0.5;
+
+/// Suspicious but parseable feature of a structure
+#[derive(Debug, Clone, PartialEq)]
+pub enum Warning {
+  /// Two atoms at (nearly) the same position
+  DuplicatePosition { first: usize, second: usize },
+  /// Element symbol not written in the usual `C`, `Cl` form
+  UnusualCase { index: usize, element: String },
+  /// Two atoms much closer together than any real bond
+  ShortDistance { first: usize, second: usize, distance: f64 },
+}
+
+impl fmt::Display for Warning {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Warning::DuplicatePosition { first, second } => {
+        write!(f, "atoms {} and {} are at the same position", first, second)
+      }
+      Warning::UnusualCase { index, element } => {
+        write!(f, "atom {} has element symbol '{}' in unusual case", index, element)
+      }
+      Warning::ShortDistance { first, second, distance } => {
+        write!(f, "atoms {} and {} are only {:.3} Angstrom apart", first, second, distance)
+      }
+    }
+  }
+}
+
+impl Molecule {
+  /// Check for features that parse fine but usually indicate bad data
+  ///
+  /// Pair warnings list each pair once with `first < second`. Warnings are
+  /// ordered by atom index.
+  pub fn validate(&self) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+
+    for (index, atom) in self.atoms.iter().enumerate() {
+      if !has_usual_case(&atom.element) {
+        warnings.push(Warning::UnusualCase {
+          index,
+          element: atom.element.clone(),
+        });
+      }
+    }
+
+    let positions: Vec<[f64; 3]> = self.atoms.iter().map(|a| [a.x, a.y, a.z]).collect();
+    let grid = SpatialGrid::new(&positions, SHORT_DISTANCE);
+    for (first, p) in positions.iter().enumerate() {
+      for second in grid.neighbors_within(*p, SHORT_DISTANCE) {
+        if second <= first {
+          continue;
+        }
+
+        let distance = distance_squared(p, &positions[second]).sqrt();
+        warnings.push(if distance < DUPLICATE_TOLERANCE {
+          Warning::DuplicatePosition { first, second }
+        } else {
+          Warning::ShortDistance { first, second, distance }
+        });
+      }
+    }
+
+    warnings.sort_by_key(|w| match w {
+      Warning::UnusualCase { index, .. } => (*index, 0),
+      Warning::DuplicatePosition { first, second } | Warning::ShortDistance { first, second, .. } => {
+        (*first, *second + 1)
+      }
+    });
+    warnings
+  }
+}
+
+/// Whether an alphabetic symbol is capitalized like `C` or `Cl`
+///
+/// Symbols with digits or punctuation (atom labels such as `C1`) are not checked.
+fn has_usual_case(symbol: &str) -> bool {
+  if !symbol.chars().all(|c| c.is_ascii_alphabetic()) {
+    return true;
+  }
+
+  let mut chars = symbol.chars();
+  chars.next().is_some_and(|c| c.is_ascii_uppercase()) && chars.all(|c| c.is_ascii_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parser::parse_xyz_str;
+
+  #[test]
+  fn test_clean_structure_has_no_warnings() {
+    let molecule = parse_xyz_str("3\nwater\nO 0.0 0.0 0.117\nH 0.0 0.757 -0.467\nH 0.0 -0.757 -0.467\n").unwrap();
+
+    assert!(molecule.validate().is_empty());
+  }
+
+  #[test]
+  fn test_warn_about_duplicate_positions() {
+    let molecule = parse_xyz_str("3\n\nC 0 0 0\nH 1 0 0\nC 0 0 0.00001\n").unwrap();
+
+    assert_eq!(molecule.validate(), vec![Warning::DuplicatePosition { first: 0, second: 2 }]);
+  }
+
+  #[test]
+  fn test_warn_about_unusual_case() {
+    let molecule = parse_xyz_str("4\n\nCL 0 0 0\ncl 2 0 0\nCl 4 0 0\nC1 6 0 0\n").unwrap();
+    let indices: Vec<usize> = molecule
+      .validate()
+      .iter()
+      .map(|w| match w {
+        Warning::UnusualCase { index, .. } => *index,
+        other => panic!("unexpected warning {:?}", other),
+      })
+      .collect();
+
+    assert_eq!(indices, vec![0, 1]);
+  }
+
+  #[test]
+  fn test_warn_about_short_distances() {
+    let molecule = parse_xyz_str("2\n\nO 0 0 0\nH 0.3 0 0\n").unwrap();
+    let warnings = molecule.validate();
+
+    assert!(
+      matches!(warnings.as_slice(), [Warning::ShortDistance { first: 0, second: 1, distance }] if (distance - 0.3).abs() < 1e-12),
+      "Warnings were: {:?}",
+      warnings
+    );
+    assert!(warnings[0].to_string().contains("0.300 Angstrom"));
+  }
+}
@@ -0,0 +1,81 @@
+use crate::parser::Molecule;
+use std::io::{self, Write};
+
+/// Write a molecule in XYZ format
+///
+/// Coordinates use Rust's shortest round-trip formatting, so parsing the
+/// output gives back exactly the same values. Each atom is written with its
+/// original label when it has one, and extra columns kept by the parser are
+/// written after the coordinates.
+pub fn write_xyz<W: Write>(molecule: &Molecule, mut writer: W) -> io::Result<()> {
+  writeln!(writer, "{}", molecule.atoms.len())?;
+  writeln!(writer, "{}", molecule.comment)?;
+
+  for atom in &molecule.atoms {
+    let symbol = atom.label.as_deref().unwrap_or(&atom.element);
+    write!(writer, "{:<4} {:>14} {:>14} {:>14}", symbol, atom.x, atom.y, atom.z)?;
+    for field in &atom.extra {
+      write!(writer, " {}", field)?;
+    }
+    writeln!(writer)?;
+  }
+
+  Ok(())
+}
+
+/// Format a molecule as an XYZ string
+pub fn write_xyz_string(molecule: &Molecule) -> String {
+  let mut buffer = Vec::new();
+  write_xyz(molecule, &mut buffer).expect("writing to a Vec cannot fail");
+  String::from_utf8(buffer).expect("XYZ output is valid UTF-8")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parser::{parse_xyz_str, parse_xyz_with_options, ParseOptions};
+
+  #[test]
+  fn test_round_trip_preserves_atoms() {
+    let content = "3\nwater molecule\nO 0.0 0.0 0.117\nH 0.0 0.757 -0.467\nH 0.1 -0.757 -1e-10\n";
+    let molecule = parse_xyz_str(content).unwrap();
+
+    let written = write_xyz_string(&molecule);
+    assert_eq!(parse_xyz_str(&written).unwrap(), molecule);
+  }
+
+  #[test]
+  fn test_round_trip_preserves_extra_columns() {
+    let options = ParseOptions {
+      keep_extra_columns: true,
+      ..ParseOptions::default()
+    };
+    let content = "1\n\nC 1.5 -2.25 3.0 -0.42 CT\n";
+    let molecule = parse_xyz_with_options(content.as_bytes(), &options).unwrap();
+
+    let written = write_xyz_string(&molecule);
+    let reparsed = parse_xyz_with_options(written.as_bytes(), &options).unwrap();
+    assert_eq!(reparsed, molecule);
+  }
+
+  #[test]
+  fn test_write_original_labels() {
+    let options = ParseOptions {
+      infer_elements: true,
+      resolve_aliases: true,
+      ..ParseOptions::default()
+    };
+    let content = "3
+
+BEAD_A 0 0 0
+D 1 0 0
+O 2 0 0
+";
+    let molecule = parse_xyz_with_options(content.as_bytes(), &options).unwrap();
+    let written = write_xyz_string(&molecule);
+
+    let symbols: Vec<&str> = written.lines().skip(2).map(|l| l.split_whitespace().next().unwrap()).collect();
+    assert_eq!(symbols, vec!["BEAD_A", "D", "O"]);
+    assert_eq!(parse_xyz_with_options(written.as_bytes(), &options).unwrap(), molecule);
+  }
+}